@@ -0,0 +1,187 @@
+//! Frame time of the toon passes at common resolutions and feature combinations.
+//!
+//! Run with `cargo bench --bench frame_time`. Each case renders a fixed scene offscreen and waits
+//! for the GPU after every frame, the reported ms/frame includes the whole frame so compare a
+//! case against the `off` row at the same resolution rather than reading it on its own.
+//! Pass a case name as an argument to only run the cases containing it.
+
+use std::time::{Duration, Instant};
+
+use bevy::{
+    app::ScheduleRunnerPlugin,
+    core_pipeline::bloom::Bloom,
+    pbr::ScreenSpaceAmbientOcclusion,
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+        renderer::RenderDevice,
+        settings::{Backends, WgpuSettings},
+        RenderApp, RenderPlugin,
+    },
+    window::ExitCondition,
+    winit::WinitPlugin,
+};
+use bevy_simpletoon::plugin::{SimpletoonPlugin, SimpletoonSettings};
+
+const RESOLUTIONS: [(&str, UVec2); 3] = [
+    ("1080p", UVec2::new(1920, 1080)),
+    ("1440p", UVec2::new(2560, 1440)),
+    ("4K", UVec2::new(3840, 2160)),
+];
+const WARMUP_FRAMES: u32 = 120;
+const MEASURED_FRAMES: u32 = 300;
+
+#[derive(Clone, Copy)]
+struct Case {
+    name: &'static str,
+    settings: Option<SimpletoonSettings>,
+    hdr: bool,
+    ssao: bool,
+}
+
+fn cases() -> Vec<Case> {
+    let case = |name, settings| Case { name, settings: Some(settings), hdr: false, ssao: false };
+    vec![
+        Case { name: "off", settings: None, hdr: false, ssao: false },
+        case("default", SimpletoonSettings::default()),
+        case("thick strokes", SimpletoonSettings { stroke_size: 5.0, ..default() }),
+        case(
+            "dilated strokes",
+            SimpletoonSettings { stroke_size: 5.0, stroke_dilation: true, ..default() },
+        ),
+        Case {
+            hdr: true,
+            ..case("emissive strokes", SimpletoonSettings { emissive_strokes: 4.0, ..default() })
+        },
+        Case {
+            ssao: true,
+            ..case("ssao banding", SimpletoonSettings { ssao_banding: true, ..default() })
+        },
+    ]
+}
+
+fn main() {
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+        backends: Backends::PRIMARY,
+        ..default()
+    });
+    if bevy::tasks::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default())).is_none() {
+        eprintln!("no Vulkan, Metal or DX12 adapter available, nothing to measure");
+        return;
+    }
+
+    let filter: Vec<String> = std::env::args().skip(1).filter(|arg| !arg.starts_with('-')).collect();
+
+    println!("{:<8} {:<18} {:>10} {:>10}", "size", "case", "ms/frame", "vs off");
+    for (size_name, size) in RESOLUTIONS {
+        let mut off = None;
+        for case in cases() {
+            if !filter.is_empty() && !filter.iter().any(|f| case.name.contains(f.as_str())) && case.name != "off" {
+                continue;
+            }
+            let ms = frame_time(case, size).as_secs_f64() * 1000.0;
+            let off_ms = *off.get_or_insert(ms);
+            println!("{size_name:<8} {:<18} {ms:>10.3} {:>+10.3}", case.name, ms - off_ms);
+        }
+    }
+}
+
+fn frame_time(case: Case, size: UVec2) -> Duration {
+    let mut app = App::new();
+    app.add_plugins((
+        DefaultPlugins
+            .set(WindowPlugin {
+                primary_window: None,
+                exit_condition: ExitCondition::DontExit,
+                close_when_requested: false,
+            })
+            .set(RenderPlugin {
+                render_creation: WgpuSettings {
+                    backends: Some(Backends::PRIMARY),
+                    ..default()
+                }
+                .into(),
+                ..default()
+            })
+            .disable::<WinitPlugin>(),
+        ScheduleRunnerPlugin::default(),
+        SimpletoonPlugin::default(),
+    ));
+
+    spawn_scene(app.world_mut(), case, size);
+
+    app.finish();
+    app.cleanup();
+
+    // Wait for the GPU every frame so the measurement covers the work actually submitted
+    let frame = |app: &mut App| {
+        app.update();
+        app.sub_app(RenderApp)
+            .world()
+            .resource::<RenderDevice>()
+            .wgpu_device()
+            .poll(bevy::render::render_resource::Maintain::Wait);
+    };
+
+    for _ in 0..WARMUP_FRAMES {
+        frame(&mut app);
+    }
+    let start = Instant::now();
+    for _ in 0..MEASURED_FRAMES {
+        frame(&mut app);
+    }
+    start.elapsed() / MEASURED_FRAMES
+}
+
+fn spawn_scene(world: &mut World, case: Case, size: UVec2) {
+    let mut image = Image::new_fill(
+        Extent3d { width: size.x, height: size.y, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        &[0; 4],
+        TextureFormat::Rgba8UnormSrgb,
+        default(),
+    );
+    image.texture_descriptor.usage |= TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING;
+    let target = world.resource_mut::<Assets<Image>>().add(image);
+
+    let sphere = world.resource_mut::<Assets<Mesh>>().add(Sphere::new(0.5).mesh().uv(32, 18));
+    let cube = world.resource_mut::<Assets<Mesh>>().add(Cuboid::default());
+    let plane = world.resource_mut::<Assets<Mesh>>().add(Plane3d::default().mesh().size(50.0, 50.0));
+    let material = world.resource_mut::<Assets<StandardMaterial>>().add(StandardMaterial {
+        perceptual_roughness: 0.8,
+        ..default()
+    });
+
+    // A grid of shapes so there are plenty of edges on screen
+    for x in -5..=5 {
+        for z in -5..=5 {
+            let mesh = if (x + z) % 2 == 0 { sphere.clone() } else { cube.clone() };
+            world.spawn((
+                Mesh3d(mesh),
+                MeshMaterial3d(material.clone()),
+                Transform::from_xyz(x as f32 * 1.5, 0.5, z as f32 * 1.5),
+            ));
+        }
+    }
+    world.spawn((Mesh3d(plane), MeshMaterial3d(material)));
+    world.spawn((
+        DirectionalLight { shadows_enabled: true, ..default() },
+        Transform::from_xyz(4.0, 8.0, 4.0).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+
+    let mut camera = world.spawn((
+        Camera3d::default(),
+        Camera { target: RenderTarget::Image(target.into()), hdr: case.hdr, ..default() },
+        Transform::from_xyz(0.0, 8.0, 12.0).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+    if let Some(settings) = case.settings {
+        camera.insert(settings);
+    }
+    if case.hdr {
+        camera.insert(Bloom::default());
+    }
+    if case.ssao {
+        camera.insert((ScreenSpaceAmbientOcclusion::default(), Msaa::Off));
+    }
+}
@@ -0,0 +1,116 @@
+//! Bevy's forward decals under the toon pass.
+//!
+//! The decals are marked with `ToonDecal`, so their faded borders aren't outlined as colour edges. The one stuck on
+//! the cube is its child, and is banded into the cube's two bands rather than the ground's four.
+
+use bevy::{
+    asset::RenderAssetUsages,
+    pbr::decal::{ForwardDecal, ForwardDecalMaterial, ForwardDecalMaterialExt},
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+use bevy_simpletoon::entity_id::{ToonBands, ToonDecal};
+use bevy_simpletoon::plugin::{SimpletoonPlugin, SimpletoonSettings};
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, SimpletoonPlugin::default()))
+        .add_systems(Startup, setup)
+        .add_systems(Update, rotate)
+        .run();
+}
+
+#[derive(Component)]
+struct Spinning;
+
+/// A soft edged ring, the alpha fading out towards its inner and outer edges.
+fn ring_image(size: u32) -> Image {
+    let mut data = Vec::with_capacity((size * size * 4) as usize);
+    for y in 0..size {
+        for x in 0..size {
+            let p = (Vec2::new(x as f32, y as f32) + 0.5) / size as f32 * 2.0 - 1.0;
+            let alpha = 1.0 - ((p.length() - 0.65).abs() / 0.25).clamp(0.0, 1.0);
+            data.extend_from_slice(&[230, 40, 60, (alpha * 255.0) as u8]);
+        }
+    }
+    Image::new(
+        Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut decal_materials: ResMut<Assets<ForwardDecalMaterial<StandardMaterial>>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let decal_material = decal_materials.add(ForwardDecalMaterial {
+        base: StandardMaterial {
+            base_color_texture: Some(images.add(ring_image(128))),
+            alpha_mode: AlphaMode::Blend,
+            ..default()
+        },
+        extension: ForwardDecalMaterialExt { depth_fade_factor: 1.0 },
+    });
+
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(20.0, 20.0))),
+        MeshMaterial3d(materials.add(Color::srgb(0.75, 0.75, 0.7))),
+    ));
+    commands.spawn((
+        ForwardDecal,
+        ToonDecal,
+        MeshMaterial3d(decal_material.clone()),
+        Transform::from_xyz(-3.0, 0.0, 1.0).with_scale(Vec3::splat(3.0)),
+    ));
+
+    commands
+        .spawn((
+            Mesh3d(meshes.add(Cuboid::from_length(2.0))),
+            MeshMaterial3d(materials.add(Color::srgb(0.3, 0.55, 0.9))),
+            ToonBands(2),
+            Transform::from_xyz(1.5, 1.0, 0.0),
+            Spinning,
+        ))
+        .with_child((
+            ForwardDecal,
+            ToonDecal,
+            MeshMaterial3d(decal_material),
+            Transform::from_xyz(0.0, 1.0, 0.0).with_scale(Vec3::splat(1.6)),
+        ));
+
+    commands.spawn((
+        DirectionalLight {
+            shadows_enabled: true,
+            illuminance: 4000.0,
+            ..default()
+        },
+        Transform::from_xyz(4.0, 8.0, 2.0).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+
+    // Per-entity overrides need Msaa::Off
+    commands.spawn((
+        Camera3d::default(),
+        SimpletoonSettings {
+            colour_banding: 4.0,
+            ..default()
+        },
+        Msaa::Off,
+        Transform::from_xyz(0.0, 7.0, 9.0).looking_at(Vec3::new(0.0, 0.5, 0.0), Vec3::Y),
+    ));
+}
+
+fn rotate(mut spinning: Query<&mut Transform, With<Spinning>>, time: Res<Time>) {
+    for mut transform in &mut spinning {
+        transform.rotate_y(time.delta_secs() / 2.0);
+    }
+}
@@ -0,0 +1,74 @@
+//! Black strokes on white with `ToonShading::LineArt`, e.g. for colouring book pages.
+//!
+//! Press space to switch between line art and the banded scene, and P to save the window to `line_art.png`.
+
+use bevy::{
+    prelude::*,
+    render::view::screenshot::{save_to_disk, Screenshot},
+};
+use bevy_simpletoon::plugin::{SimpletoonPlugin, SimpletoonSettings, ToonShading};
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, SimpletoonPlugin::default()))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (toggle_shading, save_page))
+        .run();
+}
+
+fn setup(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>, mut materials: ResMut<Assets<StandardMaterial>>) {
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(20.0, 20.0))),
+        MeshMaterial3d(materials.add(Color::srgb(0.75, 0.75, 0.7))),
+    ));
+    commands.spawn((
+        Mesh3d(meshes.add(Torus::new(0.6, 1.2))),
+        MeshMaterial3d(materials.add(Color::srgb(0.9, 0.45, 0.3))),
+        Transform::from_xyz(-2.0, 0.3, 0.0),
+    ));
+    commands.spawn((
+        Mesh3d(meshes.add(Cuboid::from_length(1.5))),
+        MeshMaterial3d(materials.add(Color::srgb(0.3, 0.55, 0.9))),
+        Transform::from_xyz(1.8, 0.75, -0.5).with_rotation(Quat::from_rotation_y(0.6)),
+    ));
+    commands.spawn((
+        Mesh3d(meshes.add(Sphere::new(0.7))),
+        MeshMaterial3d(materials.add(Color::srgb(0.45, 0.8, 0.4))),
+        Transform::from_xyz(0.2, 0.7, 1.6),
+    ));
+
+    commands.spawn((
+        DirectionalLight {
+            illuminance: 4000.0,
+            ..default()
+        },
+        Transform::from_xyz(4.0, 8.0, 2.0).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+
+    commands.spawn((
+        Camera3d::default(),
+        SimpletoonSettings {
+            shading: ToonShading::LineArt,
+            line_art_background: Vec4::ONE,
+            stroke_colour: Srgba::BLACK,
+            stroke_size: 2.0,
+            ..default()
+        },
+        Transform::from_xyz(0.0, 5.0, 8.0).looking_at(Vec3::new(0.0, 0.5, 0.0), Vec3::Y),
+    ));
+}
+
+fn toggle_shading(keys: Res<ButtonInput<KeyCode>>, mut settings: Single<&mut SimpletoonSettings>) {
+    if keys.just_pressed(KeyCode::Space) {
+        settings.shading = match settings.shading {
+            ToonShading::LineArt => ToonShading::Banded,
+            _ => ToonShading::LineArt,
+        };
+    }
+}
+
+fn save_page(mut commands: Commands, keys: Res<ButtonInput<KeyCode>>) {
+    if keys.just_pressed(KeyCode::KeyP) {
+        commands.spawn(Screenshot::primary_window()).observe(save_to_disk("line_art.png"));
+    }
+}
@@ -0,0 +1,104 @@
+//! Particles under the toon pass, kept out of it with `ToonVfxExclusion`.
+//!
+//! The sparks are additive quads drawn in the transparent pass, which is also where `bevy_hanabi` draws its effects,
+//! so a camera with hanabi particles only needs the same `ToonVfxExclusion`. Press space to toggle it and see the
+//! sparks banded and outlined without it.
+
+use bevy::prelude::*;
+use bevy_simpletoon::plugin::{SimpletoonPlugin, SimpletoonSettings};
+use bevy_simpletoon::vfx::ToonVfxExclusion;
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, SimpletoonPlugin::default()))
+        .add_systems(Startup, setup)
+        .add_systems(Update, (move_sparks, face_camera, toggle_exclusion))
+        .run();
+}
+
+#[derive(Component)]
+struct Spark {
+    speed: f32,
+    phase: f32,
+}
+
+fn setup(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>, mut materials: ResMut<Assets<StandardMaterial>>) {
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(20.0, 20.0))),
+        MeshMaterial3d(materials.add(Color::srgb(0.75, 0.75, 0.7))),
+    ));
+    commands.spawn((
+        Mesh3d(meshes.add(Cylinder::new(0.6, 0.4))),
+        MeshMaterial3d(materials.add(Color::srgb(0.35, 0.3, 0.3))),
+        Transform::from_xyz(0.0, 0.2, 0.0),
+    ));
+
+    let spark = meshes.add(Rectangle::new(0.25, 0.25));
+    let spark_material = materials.add(StandardMaterial {
+        base_color: Color::srgb(1.0, 0.55, 0.15),
+        emissive: LinearRgba::rgb(4.0, 1.6, 0.3),
+        alpha_mode: AlphaMode::Add,
+        unlit: true,
+        ..default()
+    });
+    for i in 0..120 {
+        let phase = i as f32 * 0.618;
+        commands.spawn((
+            Mesh3d(spark.clone()),
+            MeshMaterial3d(spark_material.clone()),
+            Transform::default(),
+            Spark {
+                speed: 0.6 + (i % 7) as f32 * 0.1,
+                phase,
+            },
+        ));
+    }
+
+    commands.spawn((
+        DirectionalLight {
+            shadows_enabled: true,
+            illuminance: 4000.0,
+            ..default()
+        },
+        Transform::from_xyz(4.0, 8.0, 2.0).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+
+    commands.spawn((
+        Camera3d::default(),
+        SimpletoonSettings::default(),
+        ToonVfxExclusion::default(),
+        Transform::from_xyz(0.0, 3.0, 7.0).looking_at(Vec3::new(0.0, 1.2, 0.0), Vec3::Y),
+    ));
+}
+
+/// Sparks rise out of the brazier in a loose spiral and start over at the bottom.
+fn move_sparks(mut sparks: Query<(&mut Transform, &Spark)>, time: Res<Time>) {
+    for (mut transform, spark) in &mut sparks {
+        let t = (time.elapsed_secs() * spark.speed + spark.phase).fract();
+        let angle = spark.phase * 10.0 + t * 4.0;
+        let radius = 0.2 + t * 0.6;
+        transform.translation = Vec3::new(angle.cos() * radius, 0.4 + t * 3.0, angle.sin() * radius);
+        transform.scale = Vec3::splat(1.0 - t);
+    }
+}
+
+fn face_camera(mut sparks: Query<&mut Transform, (With<Spark>, Without<Camera3d>)>, camera: Single<&Transform, With<Camera3d>>) {
+    for mut transform in &mut sparks {
+        transform.rotation = camera.rotation;
+    }
+}
+
+fn toggle_exclusion(
+    mut commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    camera: Single<(Entity, Has<ToonVfxExclusion>), With<Camera3d>>,
+) {
+    if !keys.just_pressed(KeyCode::Space) {
+        return;
+    }
+    let (entity, excluded) = *camera;
+    match excluded {
+        true => commands.entity(entity).remove::<ToonVfxExclusion>(),
+        false => commands.entity(entity).insert(ToonVfxExclusion::default()),
+    };
+}
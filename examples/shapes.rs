@@ -17,7 +17,7 @@ fn main() {
     App::new()
         .add_plugins((
             DefaultPlugins.set(ImagePlugin::default_nearest()),
-            SimpletoonPlugin,
+            SimpletoonPlugin::default(),
         ))
         .add_systems(Startup, setup)
         .add_systems(
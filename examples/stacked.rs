@@ -0,0 +1,81 @@
+//! Two toon passes on one camera: broad colour bands before tonemapping, and fine strokes after FXAA.
+//!
+//! The camera's own `SimpletoonSettings` only band, their edge thresholds are out of reach. The `ToonInstance` child
+//! carries the settings of the pass added by `SimpletoonPlugin::labeled("strokes")`, which only strokes.
+
+use bevy::{
+    core_pipeline::{core_3d::graph::Node3d, fxaa::Fxaa},
+    prelude::*,
+};
+use bevy_simpletoon::instance::ToonInstance;
+use bevy_simpletoon::plugin::{SimpletoonPlugin, SimpletoonSettings};
+
+fn main() {
+    App::new()
+        .add_plugins((
+            DefaultPlugins,
+            SimpletoonPlugin::before_depth_of_field(),
+            SimpletoonPlugin::labeled("strokes").between(Node3d::Fxaa, Node3d::EndMainPassPostProcessing),
+        ))
+        .add_systems(Startup, setup)
+        .add_systems(Update, rotate)
+        .run();
+}
+
+#[derive(Component)]
+struct Spinning;
+
+fn setup(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>, mut materials: ResMut<Assets<StandardMaterial>>) {
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(20.0, 20.0))),
+        MeshMaterial3d(materials.add(Color::srgb(0.75, 0.75, 0.7))),
+    ));
+    commands.spawn((
+        Mesh3d(meshes.add(Torus::new(0.6, 1.2))),
+        MeshMaterial3d(materials.add(Color::srgb(0.9, 0.45, 0.3))),
+        Transform::from_xyz(-1.8, 1.0, 0.0),
+        Spinning,
+    ));
+    commands.spawn((
+        Mesh3d(meshes.add(Sphere::new(1.0))),
+        MeshMaterial3d(materials.add(Color::srgb(0.3, 0.55, 0.9))),
+        Transform::from_xyz(1.8, 1.0, 0.0),
+    ));
+
+    commands.spawn((
+        DirectionalLight {
+            shadows_enabled: true,
+            illuminance: 4000.0,
+            ..default()
+        },
+        Transform::from_xyz(4.0, 8.0, 2.0).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+
+    commands
+        .spawn((
+            Camera3d::default(),
+            SimpletoonSettings {
+                colour_banding: 3.0,
+                depth_threshold: f32::MAX,
+                normal_threshold: f32::MAX,
+                colour_threshold: f32::MAX,
+                ..default()
+            },
+            Fxaa::default(),
+            Transform::from_xyz(0.0, 5.0, 8.0).looking_at(Vec3::new(0.0, 0.5, 0.0), Vec3::Y),
+        ))
+        .with_child((
+            ToonInstance("strokes"),
+            SimpletoonSettings {
+                // Out of range for every colour, so nothing is banded twice
+                banding_range: Vec2::splat(f32::MAX),
+                ..default()
+            },
+        ));
+}
+
+fn rotate(mut spinning: Query<&mut Transform, With<Spinning>>, time: Res<Time>) {
+    for mut transform in &mut spinning {
+        transform.rotate_x(time.delta_secs() / 2.0);
+    }
+}
@@ -6,34 +6,218 @@
     forward_io::VertexOutput,
 }
 #import bevy_render::view::View
+#import bevy_pbr::mesh_view_types::{Lights, DIRECTIONAL_LIGHT_FLAGS_SHADOWS_ENABLED_BIT}
 #import bevy_pbr::view_transformations::uv_to_ndc;
 
 @group(0) @binding(0) var screen_texture: texture_2d<f32>;
 @group(0) @binding(1) var texture_sampler: sampler;
 struct ToonPostProcessSettings {
-    depth_threshold: f32,
-    depth_threshold_depth_mul: f32,  // If something is further away, it should require more depth
+    depth_threshold: f32, // metres
+    depth_threshold_depth_mul: f32, // metres per metre of distance
     depth_normal_threshold: f32, // If at a glazing angle, depth threshold should be harsher
-    depth_normal_threshold_mul: f32, // If at a glazing angle, depth threshold should be harsher
+    depth_normal_threshold_mul_along: f32, // How much harsher along the way the surface slopes away on screen
+    depth_normal_threshold_mul_across: f32, // How much harsher across that slope
     normal_threshold: f32,
     colour_threshold: f32,
     stroke_size: f32,
     colour_banding: f32,
-    stroke_colour: vec4f
+    stroke_colour: vec4f,
+    effect_strength: f32, // 0 leaves the source untouched, 1 is the full toon result
+    stroke_space: u32, // 0 = stroke_size in pixels, 1 = stroke_size in world units
+    stroke_dilation: u32, // 1 = strokes come from a dilated 1px edge mask
+    ssao_banding: u32, // 1 = quantize the ambient occlusion baked into the scene colour
+    emissive_strokes: f32, // > 0 = strokes were already drawn in HDR before bloom
+    colour_edge_space: u32, // 0 = rgb, 1 = luma, 2 = oklab
+    chroma_threshold: f32, // oklab only
+    dof_focal_distance: f32,
+    dof_focal_length: f32,
+    dof_coc_scale: f32,
+    dof_max_coc: f32, // 0 = strokes ignore depth of field
+    dof_max_depth: f32,
+    banding_range: vec2f, // min and max luminance that gets banded
+    band_spacing: u32, // 0 = equal steps, 1 = equal perceived steps before the tonemapper
+    band_shoulder: f32, // how hard the tonemapper rolls off highlights, 0 = not at all
+    band_exposure: f32, // colours are banded as if this much brighter
+    line_breakup: f32, // 0 = solid strokes
+    line_breakup_scale: f32, // world units per noise tile
+    line_breakup_contrast: f32,
+    kernel_radius: u32, // only read without push constants, see kernel_radius()
+    stroke_cleanup: u32, // 1 = the edge mask goes through cleanup_edges
+    stroke_smoothing: u32, // 1 = stroke edges are antialiased by smooth_stroke
+    high_contrast_strokes: u32, // 1 = black or white strokes, whichever contrasts more with the scene
+    id_edges: u32, // 1 = stroke between pixels of different entity IDs
+    id_edge_depth: f32, // metres, ID edges further apart in depth are left to the depth detector
+    shadow_edges: u32, // 1 = stroke where the light reaching neighbouring pixels differs by shadow_edge_threshold
+    shadow_edge_threshold: f32,
+    stroke_style: u32, // 0 = solid, 1 = dashed, 2 = dotted
+    dash_length: f32,
+    dash_gap: f32,
+    normal_sampling: u32, // 0 = prepass, 1 = prepass without comparing across depth edges, 2 = from depth
+    normal_detail: u32, // 1 = normal mapped detail is stroked against normal_detail_threshold
+    normal_detail_threshold: f32,
+    blueprint: u32, // 1 = everything that isn't geometry is replaced by the grid below
+    blueprint_background: vec4f,
+    blueprint_grid_colour: vec4f,
+    blueprint_grid_spacing: f32,
+    blueprint_line_width: f32,
+    shading: u32, // 0 = banded, 1 = gooch, 2 = line art
+    gooch_warm: vec3f,
+    gooch_alpha: f32,
+    gooch_cool: vec3f,
+    gooch_beta: f32,
+    line_art_background: vec4f,
+    emissive_range: vec2f, // brightest channel where banding starts fading out, and where it is gone
+    screen_edge_margin: f32, // pixels
+    preserve_alpha: u32, // 1 = alpha is passed through from the source
+    edge_alpha: u32, // 1 = alpha is how much of the pixel is stroke
+    stroke_animation: u32, // 1 = strokes pulse and cycle hue with time
+    stroke_pulse_amplitude: f32,
+    stroke_pulse_frequency: f32, // Hz
+    stroke_hue_cycle: f32, // turns per second
+    channel_banding: u32, // 1 = red, green and blue are banded separately with channel_bands
+    channel_bands: vec3f,
+    channel_red_offset: vec2f, // pixels
+    channel_green_offset: vec2f,
+    channel_blue_offset: vec2f,
+    terrain: u32, // 1 = ToonTerrain meshes are tinted by height and slope
+    terrain_low_colour: vec3f,
+    terrain_base_height: f32,
+    terrain_high_colour: vec3f,
+    terrain_height_range: f32,
+    terrain_cliff_colour: vec3f,
+    terrain_height_bands: f32,
+    terrain_slope_bands: f32,
+    sky: u32, // Number of sky_stops used, 0 = no sky
+    sky_stops: array<vec4f, 4>, // horizon first, zenith last
+    sky_bands: f32,
+    detail: u32, // 0 = off, 1 = detail texture over the screen, 2 = tiled over the world's XZ plane
+    detail_world_size: f32,
+    detail_low_bands: f32,
+    detail_low_threshold_mul: f32,
+    key_light: vec4f, // xyz towards a directional light (w = 0) or a light's position (w = 1), zero without a light
+    key_light_colour: vec3f,
+    transparent_background: u32, // 1 = alpha from depth, for ToonScreenshot
+    impact: u32, // Set while the camera has an ImpactFrame
+    impact_lines: f32,
+    impact_inner_radius: f32,
+    impact_seed: f32,
+    impact_centre: vec2f, // viewport uv
+    speed_lines: u32, // Set while the camera has ToonSpeedLines
+    speed_line_count: f32,
+    speed_line_length: f32,
+    speed_line_inner_radius: f32,
+    speed_line_jitter: f32,
+    speed_line_width: f32,
+    speed_line_seed: f32,
+    speed_line_centre: vec2f, // viewport uv
+    speed_line_colour: vec4f,
+    time: f32, // seconds, wrapped, only current with stroke_animation
+    compare: u32, // Set while the camera has a ToonCompare
+    compare_split: f32, // viewport uv x, the scene is left untouched right of it
 }
 @group(0) @binding(2) var<uniform> settings: ToonPostProcessSettings;
+#ifdef PUSH_CONSTANTS
+// Per-view constants that would otherwise need a pipeline each, see ToonPushConstants
+struct ToonPushConstants {
+    kernel_radius: u32,
+}
+var<push_constant> constants: ToonPushConstants;
+#endif
+#ifdef OUTLINES
+// With MSAA the prepass textures are multisampled, and the 0 passed to textureLoad picks the first sample
+#ifdef MULTISAMPLED_PREPASS
+#ifdef COMPATIBILITY
+@group(0) @binding(3) var depth_prepass_texture: texture_multisampled_2d<f32>;
+#else
+@group(0) @binding(3) var depth_prepass_texture: texture_depth_multisampled_2d;
+#endif
+@group(0) @binding(4) var normal_prepass_texture: texture_multisampled_2d<f32>;
+#else
+#ifdef COMPATIBILITY
+@group(0) @binding(3) var depth_prepass_texture: texture_2d<f32>; // GL can't textureLoad a texture_depth_2d
+#else
 @group(0) @binding(3) var depth_prepass_texture: texture_depth_2d;
+#endif
 @group(0) @binding(4) var normal_prepass_texture: texture_2d<f32>;
+#endif
+@group(0) @binding(9) var noise_texture: texture_2d<f32>; // tiling value noise for line_breakup
+@group(0) @binding(10) var noise_sampler: sampler; // repeats
+#ifndef COMPATIBILITY
+// Bevy's lights and directional shadow maps for the view, for shadow_edges
+@group(0) @binding(12) var<uniform> lights: Lights;
+@group(0) @binding(13) var directional_shadow_textures: texture_depth_2d_array;
+@group(0) @binding(14) var directional_shadow_sampler: sampler_comparison;
+#endif
+#endif
 @group(0) @binding(5) var<uniform> view: View;
+#ifdef BANDING
+#ifndef COMPATIBILITY
+@group(0) @binding(6) var ssao_texture: texture_2d<f32>; // 1x1 white when the camera has no SSAO
+#endif
+#endif
+#ifdef ENTITY_OVERRIDES
+struct ToonOverride {
+    flags: u32, // 1 = no banding, 2 = no colour edges, 4 = pass through, 8 = depth edges only, 16 = only against other entities, 32 = terrain, 64 = decal, 128 = own stroke colour, 256 = imposter
+    normal_threshold_bias: f32, // added to settings.normal_threshold
+    dashes: vec2f, // dash length and gap in pixels, 0 = solid
+    bands: f32, // replaces settings.colour_banding, 0 = the camera's
+    hair_band: vec2f, // hair highlight shift and width
+    hair_colour: vec4f, // hair highlight colour, strength in alpha, 0 = not hair
+    stroke_colour: vec4f, // replaces settings.stroke_colour with flag 128
+    stroke_size: f32, // pixels, replaces settings.stroke_size, 0 = the camera's
+    lod_bounds: vec4f, // world space bounding sphere for ToonOutlineLod, radius 0 = none
+    lod_size: vec2f, // pixels across where strokes are gone, and over how many more they fade back in
+}
+@group(0) @binding(7) var entity_id_texture: texture_2d<u32>; // 1x1 zero when no entity has overrides
+@group(0) @binding(8) var<storage, read> entity_overrides: array<ToonOverride>;
+#endif
+
+@group(0) @binding(15) var vfx_mask_texture: texture_2d<f32>; // how much of the toon result to keep, 1x1 white without ToonVfxExclusion
+@group(0) @binding(16) var detail_texture: texture_2d<f32>; // ToonDetailTexture, 1x1 white without one
+
+#ifdef EDGE_MASK_INPUT
+@group(1) @binding(0) var edge_mask_texture: texture_2d<f32>;
+#endif
 
 
+#ifdef OUTLINES
 fn prepass_depth(frag_coord: vec2f) -> f32 {
+#ifdef COMPATIBILITY
+    return textureLoad(depth_prepass_texture, vec2i(frag_coord), 0).r;
+#else
     return textureLoad(depth_prepass_texture, vec2i(frag_coord), 0);
+#endif
 }
 
+// The normal at this pixel remapped to 0..1 like Bevy's RGB layout, whichever layout the prepass was written in
 fn prepass_normal(frag_coord: vec2f) -> vec3f {
-    return textureLoad(normal_prepass_texture, vec2i(frag_coord), 0).xyz;
+    let encoded = textureLoad(normal_prepass_texture, vec2i(frag_coord), 0);
+#ifdef NORMAL_OCTAHEDRAL
+    return octahedral_decode(encoded.xy) * 0.5 + 0.5;
+#else
+    return encoded.xyz;
+#endif
+}
+
+#ifdef NORMAL_OCTAHEDRAL
+// Unfolds a 0..1 octahedral encoding back into a unit vector
+fn octahedral_decode(encoded: vec2f) -> vec3f {
+    let f = encoded * 2.0 - 1.0;
+    var n = vec3f(f, 1.0 - abs(f.x) - abs(f.y));
+    let t = saturate(-n.z);
+    n.x += select(t, -t, n.x >= 0.0);
+    n.y += select(t, -t, n.y >= 0.0);
+    return normalize(n);
+}
+#endif
+
+// 0 on geometry whose material cleared the normal prepass' alpha with bevy_simpletoon::outline_mask, the sky is
+// cleared to 0 too but keeps its strokes
+fn outline_mask(frag_coord: vec2f) -> f32 {
+    let masked = textureLoad(normal_prepass_texture, vec2i(frag_coord), 0).a < 0.5 && prepass_depth(frag_coord) > 0.0;
+    return select(1.0, 0.0, masked);
 }
+#endif
 
 fn texel_size() -> vec2f {
     return vec2f(1.0, 1.0) / vec2<f32>(textureDimensions(screen_texture));
@@ -43,21 +227,125 @@ fn uv_to_pos(uv: vec2f) -> vec2f {
     return uv * vec2<f32>(textureDimensions(screen_texture));
 }
 
-fn depth_buffer_edge_depth(normal_threshold: f32, bl_uv: vec2f, tr_uv: vec2f, br_uv: vec2f, tl_uv: vec2f) -> f32 {
-    
-    let _edge_depth_threshold = settings.depth_threshold;
+// The camera may only render to part of the target (Camera::viewport), so keep samples inside its rect
+fn clamp_to_viewport(uv: vec2f) -> vec2f {
+    let dims = vec2<f32>(textureDimensions(screen_texture));
+    let min_uv = (view.viewport.xy + 0.5) / dims;
+    let max_uv = (view.viewport.xy + view.viewport.zw - 0.5) / dims;
+    return clamp(uv, min_uv, max_uv);
+}
+
+// Reflects uv back in about the viewport's outermost pixel centres, so an edge kernel hanging over the border sees the
+// same surface on both sides instead of comparing the border pixel with itself
+fn mirror_into_viewport(uv: vec2f) -> vec2f {
+    let dims = vec2<f32>(textureDimensions(screen_texture));
+    let min_uv = (view.viewport.xy + 0.5) / dims;
+    let max_uv = (view.viewport.xy + view.viewport.zw - 0.5) / dims;
+    var mirrored = select(uv, 2.0 * min_uv - uv, uv < min_uv);
+    mirrored = select(mirrored, 2.0 * max_uv - mirrored, mirrored > max_uv);
+    return clamp(mirrored, min_uv, max_uv);
+}
+
+// 0 within settings.screen_edge_margin pixels of the viewport's border, where strokes are dropped
+fn screen_edge_fade(pos: vec2f) -> f32 {
+    let margin = settings.screen_edge_margin;
+    let inside = all(pos >= view.viewport.xy + margin) && all(pos < view.viewport.xy + view.viewport.zw - margin);
+    return select(0.0, 1.0, inside);
+}
+
+// How much of a stroke is left at this point in its pulse
+fn stroke_pulse() -> f32 {
+    if settings.stroke_animation == 0u {
+        return 1.0;
+    }
+    let wave = 0.5 - 0.5 * cos(6.28318531 * settings.stroke_pulse_frequency * settings.time);
+    return 1.0 - settings.stroke_pulse_amplitude * wave;
+}
+
+// Turns the stroke colour round the grey axis as time goes on
+fn cycle_stroke_hue(c: vec3f) -> vec3f {
+    if settings.stroke_animation == 0u || settings.stroke_hue_cycle == 0.0 {
+        return c;
+    }
+    let angle = 6.28318531 * fract(settings.stroke_hue_cycle * settings.time);
+    let axis = vec3f(0.57735027);
+    return c * cos(angle) + cross(axis, c) * sin(angle) + axis * dot(axis, c) * (1.0 - cos(angle));
+}
+
+fn inside_viewport(pos: vec2f) -> bool {
+    return all(pos >= view.viewport.xy) && all(pos < view.viewport.xy + view.viewport.zw);
+}
+
+// uv relative to the camera's viewport rather than the whole target
+fn viewport_uv(uv: vec2f) -> vec2f {
+    return (uv_to_pos(uv) - view.viewport.xy) / view.viewport.zw;
+}
+
+// How much detail SimpletoonSettings::detail keeps at this pixel, 1 leaves the camera's settings as they are
+fn detail_level(uv: vec2f) -> f32 {
+    if settings.detail == 0u {
+        return 1.0;
+    }
+#ifdef OUTLINES
+    if settings.detail == 2u {
+        let depth = prepass_depth(uv_to_pos(uv));
+        if depth <= 0.0 {
+            return 1.0;
+        }
+        let world_pos = position_ndc_to_world(uv_to_ndc(viewport_uv(uv)), depth);
+        return textureSampleLevel(detail_texture, noise_sampler, world_pos.xz / settings.detail_world_size, 0.0).r;
+    }
+#endif
+    return textureSampleLevel(detail_texture, texture_sampler, viewport_uv(uv), 0.0).r;
+}
+
+// Normal and colour edges need more contrast where there's less detail, leaving silhouettes and strong creases
+fn detail_threshold_mul(uv: vec2f) -> f32 {
+    return mix(settings.detail_low_threshold_mul, 1.0, detail_level(uv));
+}
+
+#ifdef OUTLINES
+// Distance in front of the camera, unprojected with the full inverse projection so oblique (portal/mirror)
+// and off-centre frustums work too, 0 where nothing was drawn
+fn view_depth(uv: vec2f) -> f32 {
+    let depth = prepass_depth(uv_to_pos(uv));
+    if depth <= 0.0 {
+        return 0.0;
+    }
+    let view_pos = view.view_from_clip * vec4(uv_to_ndc(viewport_uv(uv)), depth, 1.0);
+    return max(-view_pos.z / view_pos.w, 0.0);
+}
+#endif
+
+#ifdef OUTLINES
+fn depth_buffer_edge_depth(grazing: DepthGrazing, bl_uv: vec2f, tr_uv: vec2f, br_uv: vec2f, tl_uv: vec2f) -> f32 {
     
-    let depth0 = prepass_depth(uv_to_pos(bl_uv));
-    let depth1 = prepass_depth(uv_to_pos(tr_uv));
-    let depth2 = prepass_depth(uv_to_pos(br_uv));
-    let depth3 = prepass_depth(uv_to_pos(tl_uv));
+    // Linear view depth in world units, so thresholds mean the same with reverse-Z, infinite far planes and orthographic cameras
+    let depth0 = view_depth(bl_uv);
+    let depth1 = view_depth(tr_uv);
+    let depth2 = view_depth(br_uv);
+    let depth3 = view_depth(tl_uv);
+
+    // Nothing drawn is infinitely far away, so the edge of the background is always a depth edge
+    let drawn = vec4f(depth0, depth1, depth2, depth3) > vec4f(0.0);
+    if any(drawn) != all(drawn) {
+        return 1.0;
+    }
+    let far = vec4f(3.4e38);
+    let guarded = select(far, vec4f(depth0, depth1, depth2, depth3), drawn);
+    let nearest = min(min(guarded.x, guarded.y), min(guarded.z, guarded.w));
 
     let depth_finite_diff_0 = depth1 - depth0;
     let depth_finite_diff_1 = depth3 - depth2;
 
-    let depth_threshold = _edge_depth_threshold * (depth0 * settings.depth_threshold_depth_mul) * normal_threshold;
+    let depth_threshold = settings.depth_threshold + settings.depth_threshold_depth_mul * nearest;
 
-    var edge_depth = sqrt(pow(depth_finite_diff_0, 2.0) + pow(depth_finite_diff_1, 2.0)) * 100.0;
+    // The diagonal differences turned into the change along and across the slope, measured against their own thresholds
+    let gradient = vec2f(depth_finite_diff_0 - depth_finite_diff_1, depth_finite_diff_0 + depth_finite_diff_1) * inverseSqrt(2.0);
+    let along = dot(gradient, grazing.slope) / grazing.threshold.x;
+    let across = dot(gradient, vec2f(-grazing.slope.y, grazing.slope.x)) / grazing.threshold.y;
+
+    var edge_depth = sqrt(pow(along, 2.0) + pow(across, 2.0));
 
     if edge_depth > depth_threshold { edge_depth = 1.0; }
     else { edge_depth = 0.0; }
@@ -65,16 +353,20 @@ fn depth_buffer_edge_depth(normal_threshold: f32, bl_uv: vec2f, tr_uv: vec2f, br
     return edge_depth;
 }
 
-fn normal_buffer_edge_depth(bl_uv: vec2f, tr_uv: vec2f, br_uv: vec2f, tl_uv: vec2f) -> f32 {
-    let _normal_threshold = settings.normal_threshold;
+fn normal_buffer_edge_depth(uv: vec2f, bl_uv: vec2f, tr_uv: vec2f, br_uv: vec2f, tl_uv: vec2f) -> f32 {
+    var _normal_threshold = settings.normal_threshold * detail_threshold_mul(uv);
+#ifdef ENTITY_OVERRIDES
+    // Animated (skinned) meshes can loosen this so their shifting normals don't draw interior lines
+    _normal_threshold += entity_override(uv_to_pos(uv)).normal_threshold_bias;
+#endif
 
-    let normal0 = prepass_normal(uv_to_pos(bl_uv)).rgb;
-    let normal1 = prepass_normal(uv_to_pos(tr_uv)).rgb;
-    let normal2 = prepass_normal(uv_to_pos(br_uv)).rgb;
-    let normal3 = prepass_normal(uv_to_pos(tl_uv)).rgb;
+    let normal0 = sampled_normal(bl_uv);
+    let normal1 = sampled_normal(tr_uv);
+    let normal2 = sampled_normal(br_uv);
+    let normal3 = sampled_normal(tl_uv);
 
-    let normal_finite_diff_0 = normal1 - normal0;
-    let normal_finite_diff_1 = normal3 - normal2;
+    let normal_finite_diff_0 = (normal1 - normal0) * same_surface(tr_uv, bl_uv);
+    let normal_finite_diff_1 = (normal3 - normal2) * same_surface(tl_uv, br_uv);
 
     var edge_normal = sqrt(dot(normal_finite_diff_0, normal_finite_diff_0) + dot(normal_finite_diff_1, normal_finite_diff_1));
     if edge_normal > _normal_threshold { edge_normal = 1.0; }
@@ -83,44 +375,347 @@ fn normal_buffer_edge_depth(bl_uv: vec2f, tr_uv: vec2f, br_uv: vec2f, tl_uv: vec
     return edge_normal;
 }
 
+// 0 when settings.normal_sampling rejects comparing normals across a depth discontinuity, those are already
+// outlined by the depth edge and a normal edge next to it doubles the line
+fn same_surface(a_uv: vec2f, b_uv: vec2f) -> f32 {
+    if settings.normal_sampling != 1u {
+        return 1.0;
+    }
+    return on_same_surface(a_uv, b_uv);
+}
+
+fn on_same_surface(a_uv: vec2f, b_uv: vec2f) -> f32 {
+    let a = view_depth(a_uv);
+    let b = view_depth(b_uv);
+    return select(0.0, 1.0, abs(a - b) <= 0.1 * min(a, b));
+}
+
+// Normal at this pixel encoded like the normal prepass, from the prepass or from depth per settings.normal_sampling
+fn sampled_normal(uv: vec2f) -> vec3f {
+    if settings.normal_sampling != 2u {
+        return prepass_normal(uv_to_pos(uv)).rgb;
+    }
+    return depth_normal(uv);
+}
+
+// How far the normal prepass, which StandardMaterial normal maps, leans off the surface reconstructed from depth
+fn normal_detail(uv: vec2f) -> vec3f {
+    return prepass_normal(uv_to_pos(uv)).rgb - depth_normal(uv);
+}
+
+// Edges in normal mapped detail like panel gaps and bricks, differences in the geometry itself cancel out
+fn normal_detail_edge(bl_uv: vec2f, tr_uv: vec2f, br_uv: vec2f, tl_uv: vec2f) -> f32 {
+    if settings.normal_detail == 0u {
+        return 0.0;
+    }
+    let diff_0 = (normal_detail(tr_uv) - normal_detail(bl_uv)) * on_same_surface(tr_uv, bl_uv);
+    let diff_1 = (normal_detail(tl_uv) - normal_detail(br_uv)) * on_same_surface(tl_uv, br_uv);
+    return select(0.0, 1.0, sqrt(dot(diff_0, diff_0) + dot(diff_1, diff_1)) > settings.normal_detail_threshold);
+}
+
+// Normal at this pixel reconstructed from depth and encoded like the normal prepass, the prepass' where it can't be
+fn depth_normal(uv: vec2f) -> vec3f {
+    let prepass = prepass_normal(uv_to_pos(uv)).rgb;
+    if prepass_depth(uv_to_pos(uv)) <= 0.0 {
+        return prepass;
+    }
+    let centre = world_position(uv);
+    let texel = texel_size();
+    let normal = cross(depth_tangent(uv, centre, vec2f(0.0, texel.y)), depth_tangent(uv, centre, vec2f(texel.x, 0.0)));
+    // A pixel with nothing drawn on either side has no surface to reconstruct
+    if dot(normal, normal) < 1e-12 {
+        return prepass;
+    }
+    let towards_camera = select(-1.0, 1.0, dot(normal, view.world_position - centre) > 0.0);
+    return normalize(normal) * towards_camera * 0.5 + 0.5;
+}
+
+fn world_position(uv: vec2f) -> vec3f {
+    return position_ndc_to_world(uv_to_ndc(viewport_uv(uv)), prepass_depth(uv_to_pos(uv)));
+}
+
+// Difference in world position to a neighbour along step, taken from whichever side is on the same surface
+fn depth_tangent(uv: vec2f, centre: vec3f, step: vec2f) -> vec3f {
+    let ahead_uv = clamp_to_viewport(uv + step);
+    let behind_uv = clamp_to_viewport(uv - step);
+    let ahead_drawn = prepass_depth(uv_to_pos(ahead_uv)) > 0.0;
+    let behind_drawn = prepass_depth(uv_to_pos(behind_uv)) > 0.0;
+    let ahead = select(vec3f(0.0), world_position(ahead_uv) - centre, ahead_drawn);
+    let behind = select(vec3f(0.0), centre - world_position(behind_uv), behind_drawn);
+    if !behind_drawn || (ahead_drawn && dot(ahead, ahead) < dot(behind, behind)) {
+        return ahead;
+    }
+    return behind;
+}
+
+fn linear_srgb_to_oklab(c: vec3f) -> vec3f {
+    let lms = mat3x3f(
+        0.4122214708, 0.2119034982, 0.0883024619,
+        0.5363325363, 0.6806995451, 0.2817188376,
+        0.0514459929, 0.1073969566, 0.6299787005,
+    ) * max(c, vec3f(0.0));
+    let lms_ = pow(lms, vec3f(1.0 / 3.0));
+    return mat3x3f(
+        0.2104542553, 1.9779984951, 0.0259040371,
+        0.7936177850, -2.4285922050, 0.7827717662,
+        -0.0040720468, 0.4505937099, -0.8086757660,
+    ) * lms_;
+}
+
+// Colour in the space edges are compared in, luma only fills x
+fn edge_colour(c: vec3f) -> vec3f {
+    switch settings.colour_edge_space {
+        case 1u: { return vec3f(dot(c, vec3f(0.2126, 0.7152, 0.0722)), 0.0, 0.0); }
+        case 2u: { return linear_srgb_to_oklab(c); }
+        default: { return c; }
+    }
+}
+
 fn detect_edge_colour(bl_uv: vec2f, tr_uv: vec2f, br_uv: vec2f, tl_uv: vec2f) -> f32 {
-    let _colour_threshold = settings.colour_threshold;
+    let _colour_threshold = settings.colour_threshold * detail_threshold_mul(mix(bl_uv, tr_uv, 0.5));
 
-    let c0 = textureSample(screen_texture, texture_sampler, bl_uv).rgb;
-    let c1 = textureSample(screen_texture, texture_sampler, tr_uv).rgb;
-    let c2 = textureSample(screen_texture, texture_sampler, br_uv).rgb;
-    let c3 = textureSample(screen_texture, texture_sampler, tl_uv).rgb;
+    let c0 = edge_colour(textureSample(screen_texture, texture_sampler, bl_uv).rgb);
+    let c1 = edge_colour(textureSample(screen_texture, texture_sampler, tr_uv).rgb);
+    let c2 = edge_colour(textureSample(screen_texture, texture_sampler, br_uv).rgb);
+    let c3 = edge_colour(textureSample(screen_texture, texture_sampler, tl_uv).rgb);
 
     let finite_diff_0 = c1 - c0;
     let finite_diff_1 = c3 - c2;
 
+    if settings.colour_edge_space == 2u {
+        // Lightness and chroma get their own thresholds
+        let lightness = sqrt(finite_diff_0.x * finite_diff_0.x + finite_diff_1.x * finite_diff_1.x);
+        let chroma = sqrt(dot(finite_diff_0.yz, finite_diff_0.yz) + dot(finite_diff_1.yz, finite_diff_1.yz));
+        return select(0.0, 1.0, lightness > _colour_threshold || chroma > settings.chroma_threshold);
+    }
+
     var edge = sqrt(dot(finite_diff_0, finite_diff_0) + dot(finite_diff_1, finite_diff_1));
     if edge > _colour_threshold { edge = 1.0; }
     else { edge = 0.0; }
 
     return edge;
 }
+#endif
+
+
+#ifdef BANDING
+// SSAO is rendered at viewport size
+fn ambient_occlusion(pos: vec2f) -> f32 {
+#ifdef COMPATIBILITY
+    return 1.0;
+#else
+    let max_pos = vec2i(textureDimensions(ssao_texture)) - 1;
+    return textureLoad(ssao_texture, clamp(vec2i(pos - view.viewport.xy), vec2i(0), max_pos), 0).r;
+#endif
+}
+#endif
+
+#ifdef ENTITY_OVERRIDES
+// Overrides of the entity drawn at this pixel, entity 0 has none
+fn entity_override(pos: vec2f) -> ToonOverride {
+    return entity_overrides[min(entity_id(pos), arrayLength(&entity_overrides) - 1u)];
+}
+
+fn entity_id(pos: vec2f) -> u32 {
+    let max_pos = vec2i(textureDimensions(entity_id_texture)) - 1;
+    return textureLoad(entity_id_texture, clamp(vec2i(pos), vec2i(0), max_pos), 0).r;
+}
 
+#ifdef OUTLINES
+// 0 to 1 as the bounding sphere of the mesh at pos grows on screen, see ToonOutlineLod
+fn mesh_outline_lod(pos: vec2f) -> f32 {
+    let entity = entity_override(pos);
+    if entity.lod_bounds.w <= 0.0 {
+        return 1.0;
+    }
+    let clip_w = max((view.clip_from_world * vec4(entity.lod_bounds.xyz, 1.0)).w, 1e-4);
+    let diameter = entity.lod_bounds.w * view.viewport.w * view.clip_from_view[1][1] / clip_w;
+    return saturate((diameter - entity.lod_size.x) / max(entity.lod_size.y, 1e-4));
+}
+
+fn is_decal(uv: vec2f) -> bool {
+    return (entity_override(uv_to_pos(uv)).flags & 64u) != 0u;
+}
+
+fn is_hull(uv: vec2f) -> bool {
+    return (entity_override(uv_to_pos(uv)).flags & 512u) != 0u;
+}
+
+// Flush meshes like a poster on a wall change neither depth nor normals where they meet, but they do change entity
+fn id_pair_edge(a_uv: vec2f, b_uv: vec2f) -> bool {
+    return entity_id(uv_to_pos(a_uv)) != entity_id(uv_to_pos(b_uv)) && abs(view_depth(a_uv) - view_depth(b_uv)) <= settings.id_edge_depth;
+}
+
+fn entity_id_edge(bl_uv: vec2f, tr_uv: vec2f, br_uv: vec2f, tl_uv: vec2f) -> f32 {
+    if settings.id_edges == 0u {
+        return 0.0;
+    }
+    return select(0.0, 1.0, id_pair_edge(bl_uv, tr_uv) || id_pair_edge(br_uv, tl_uv));
+}
+
+// ToonImposter, only the texels an imposter's alpha mask kept have its ID, so where it changes is the silhouette
+fn imposter_pair_edge(a_uv: vec2f, b_uv: vec2f) -> bool {
+    let flags = entity_override(uv_to_pos(a_uv)).flags | entity_override(uv_to_pos(b_uv)).flags;
+    return (flags & 256u) != 0u && entity_id(uv_to_pos(a_uv)) != entity_id(uv_to_pos(b_uv));
+}
+
+fn imposter_edge(bl_uv: vec2f, tr_uv: vec2f, br_uv: vec2f, tl_uv: vec2f) -> f32 {
+    return select(0.0, 1.0, imposter_pair_edge(bl_uv, tr_uv) || imposter_pair_edge(br_uv, tl_uv));
+}
+#endif
+#endif
+
+#ifdef OUTLINES
+#ifndef COMPATIBILITY
+// How much of the first shadow casting directional light reaches the surface at uv, 1 where nothing is drawn or
+// outside its cascades. Follows bevy's fetch_directional_shadow without blending cascades or soft shadows
+fn cast_shadow(uv: vec2f) -> f32 {
+    if prepass_depth(uv_to_pos(uv)) <= 0.0 {
+        return 1.0;
+    }
+    let world_pos = world_position(uv);
+    let view_z = (view.view_from_world * vec4(world_pos, 1.0)).z;
+    let normal = prepass_normal(uv_to_pos(uv)).rgb * 2.0 - 1.0;
+    for (var i = 0u; i < lights.n_directional_lights; i++) {
+        let light = &lights.directional_lights[i];
+        if ((*light).flags & DIRECTIONAL_LIGHT_FLAGS_SHADOWS_ENABLED_BIT) == 0u {
+            continue;
+        }
+        var cascade = 0u;
+        while cascade < (*light).num_cascades && -view_z >= (*light).cascades[cascade].far_bound {
+            cascade++;
+        }
+        if cascade == (*light).num_cascades {
+            return 1.0;
+        }
+        let normal_offset = (*light).shadow_normal_bias * (*light).cascades[cascade].texel_size * normal;
+        let depth_offset = (*light).shadow_depth_bias * (*light).direction_to_light;
+        let clip = (*light).cascades[cascade].clip_from_world * vec4(world_pos + normal_offset + depth_offset, 1.0);
+        let ndc = clip.xyz / clip.w;
+        if clip.w <= 0.0 || any(ndc.xy < vec2f(-1.0)) || any(ndc > vec3f(1.0)) || ndc.z < 0.0 {
+            return 1.0;
+        }
+        let shadow_uv = ndc.xy * vec2f(0.5, -0.5) + 0.5;
+        let layer = i32((*light).depth_texture_base_index + cascade);
+        return textureSampleCompareLevel(directional_shadow_textures, directional_shadow_sampler, shadow_uv, layer, ndc.z);
+    }
+    return 1.0;
+}
+
+// Hand-drawn animation inks the shapes of cast shadows as well as the objects
+fn cast_shadow_edge(bl_uv: vec2f, tr_uv: vec2f, br_uv: vec2f, tl_uv: vec2f) -> f32 {
+    if settings.shadow_edges == 0u {
+        return 0.0;
+    }
+    let threshold = settings.shadow_edge_threshold;
+    let across = abs(cast_shadow(bl_uv) - cast_shadow(tr_uv)) > threshold || abs(cast_shadow(br_uv) - cast_shadow(tl_uv)) > threshold;
+    return select(0.0, 1.0, across);
+}
+#endif
+#endif
+
+#ifdef BANDING
+// Bands v in equal steps of perceived lightness of the light that went into the tonemapper, whose shoulder is
+// undone with the inverse of v = x / (1 + s * x), x being scaled so the brightest x maps to 1
+fn perceptual_band(v: f32, bands: f32) -> f32 {
+    let s = settings.band_shoulder;
+    let peak = 1.0 / (1.0 - s);
+    let y = select(v, min(v, 1.0), s > 0.0);
+    let scene = y / max(1.0 - s * y, 1.0 - s);
+    let lightness = pow(scene / peak, 1.0 / 3.0);
+    let banded = floor(lightness * bands) / bands;
+    let banded_scene = banded * banded * banded * peak;
+    return banded_scene / (1.0 + s * banded_scene);
+}
+#endif
 
 fn toon_colour(uv: vec2f) -> vec4f {
 
-    let c = textureSample(screen_texture, texture_sampler, uv).rgb;
-    let i = length(c);
-    let new_i = floor(i * settings.colour_banding) / settings.colour_banding;
-    let new_c = normalize(c) * new_i;
+    var c = textureSample(screen_texture, texture_sampler, uv).rgb;
+#ifdef OUTLINES
+    if settings.shading == 1u {
+        return vec4f(gooch_colour(uv, c), 1.0);
+    }
+#endif
+#ifdef BANDING
+    if settings.channel_banding != 0u {
+        // Each plate is read from its own offset, like prints out of register
+        let texel = texel_size();
+        c = vec3f(
+            textureSample(screen_texture, texture_sampler, clamp_to_viewport(uv + settings.channel_red_offset * texel)).r,
+            textureSample(screen_texture, texture_sampler, clamp_to_viewport(uv + settings.channel_green_offset * texel)).g,
+            textureSample(screen_texture, texture_sampler, clamp_to_viewport(uv + settings.channel_blue_offset * texel)).b,
+        );
+    }
+    var bands = mix(settings.detail_low_bands, settings.colour_banding, detail_level(uv));
+#ifdef ENTITY_OVERRIDES
+    let entity = entity_override(uv_to_pos(uv));
+    if (entity.flags & 1u) != 0u {
+        return vec4f(c, 1.0);
+    }
+    if entity.bands > 0.0 {
+        bands = entity.bands;
+    }
+#endif
+    // Highlights and deep shadows outside the range keep their smooth gradients, e.g. around emissive materials
+    let luminance = dot(c, vec3f(0.2126, 0.7152, 0.0722));
+    if luminance < settings.banding_range.x || luminance > settings.banding_range.y {
+        return vec4f(c, 1.0);
+    }
+    if settings.ssao_banding != 0u {
+        // Swap the smooth occlusion already in the scene colour for a banded version of it
+        let ao = ambient_occlusion(uv_to_pos(uv));
+        let banded_ao = ceil(ao * bands) / bands;
+        c = c / max(ao, 0.05) * banded_ao;
+    }
+    // Emissive and other HDR colours keep their gradients, easing out of the bands so they aren't ringed by steps
+    let brightest = max(max(c.r, c.g), c.b);
+    if brightest >= settings.emissive_range.y {
+        return vec4f(c, 1.0);
+    }
+    let emissive = select(0.0, smoothstep(settings.emissive_range.x, settings.emissive_range.y, brightest), brightest > settings.emissive_range.x);
+    let exposure = settings.band_exposure;
+    let i = length(c) * exposure;
+    let new_i = floor(i * bands) / bands / exposure;
+    var new_c = normalize(c) * new_i;
+    if settings.band_spacing == 1u && brightest > 0.0 {
+        new_c = c * (perceptual_band(brightest * exposure, bands) / (brightest * exposure));
+    }
+    if settings.channel_banding != 0u {
+        new_c = floor(c * exposure * settings.channel_bands) / settings.channel_bands / exposure;
+    }
 
     return vec4<f32>(
-        new_c,
+        mix(new_c, c, emissive),
         1.0
     );
+#else
+    return vec4f(c, 1.0);
+#endif
 }
 
-fn get_sampling_scale(pos: vec2f) -> f32 {
-    let d = 1.0 - (prepass_depth(pos) * 700.0);
-    //if depth > 0.999 { return 1.0; }
-    //if depth > 0.998 { return 2.0; }
-    return mix(settings.stroke_size, settings.stroke_size, saturate(d));
-    //return 0.1;
+#ifdef OUTLINES
+// Stroke width in pixels at this fragment
+fn get_sampling_scale(uv: vec2f) -> f32 {
+#ifdef ENTITY_OVERRIDES
+    let entity_size = entity_override(uv_to_pos(uv)).stroke_size;
+    if entity_size > 0.0 {
+        return entity_size;
+    }
+#endif
+    if settings.stroke_space == 0u {
+        return settings.stroke_size;
+    }
+    let depth = prepass_depth(uv_to_pos(uv));
+    if depth <= 0.0 {
+        return 1.0;
+    }
+    // Project a world-space width at this depth onto the screen
+    let ndc = vec4(uv_to_ndc(viewport_uv(uv)), depth, 1.0);
+    let view_pos = view.view_from_clip * ndc;
+    let clip_w = (view.clip_from_view * vec4(view_pos.xyz / view_pos.w, 1.0)).w;
+    let pixels = settings.stroke_size * 0.5 * view.viewport.w * view.clip_from_view[1][1] / clip_w;
+    return clamp(pixels, 1.0, 32.0);
 }
 
 fn position_ndc_to_world(ndc_pos: vec2<f32>, depth: f32) -> vec3<f32> {
@@ -129,38 +724,101 @@ fn position_ndc_to_world(ndc_pos: vec2<f32>, depth: f32) -> vec3<f32> {
 }
 
 fn worldspace_camera_view_direction(uv: vec2f) -> vec3f {
-    let ndc = uv_to_ndc(uv);
+    let ndc = uv_to_ndc(viewport_uv(uv));
     let ray_point = position_ndc_to_world(ndc, prepass_depth(uv_to_pos(uv)));
     return normalize(ray_point - view.world_position).xyz;
 }
 
-fn outline_at_scale(scale: f32, uv: vec2f) -> f32 {
-    let _scale = scale;
-    let texel_size = texel_size();
-
-    let half_scale_floor = floor(_scale * 0.5);
-    let half_scale_ceil = ceil(_scale * 0.5);
+// Rings of taps per edge check, from SimpletoonSettings::kernel_radius
+fn kernel_radius() -> u32 {
+#ifdef PUSH_CONSTANTS
+    return constants.kernel_radius;
+#else
+    return clamp(settings.kernel_radius, 1u, 4u);
+#endif
+}
 
-    let bl_uv = uv - vec2f(texel_size.x, texel_size.y) * half_scale_floor;
-    let tr_uv = uv + vec2f(texel_size.x, texel_size.y) * half_scale_ceil;  
-    let br_uv = uv + vec2f(texel_size.x * half_scale_ceil, -texel_size.y * half_scale_floor);
-    let tl_uv = uv + vec2f(-texel_size.x * half_scale_floor, texel_size.y * half_scale_ceil);
+// How much harsher depth edges are at this pixel, along and across the way the surface slopes away on screen
+struct DepthGrazing {
+    threshold: vec2f,
+    slope: vec2f,
+}
 
+fn outline_at_scale(scale: f32, uv: vec2f) -> f32 {
     let cam_view_dir = worldspace_camera_view_direction(uv);
     let normal0 = prepass_normal(uv_to_pos(uv)).rgb;
     let view_normal = normal0 * 2 - 1;
     let NdotV = (1 - dot(view_normal, -cam_view_dir));
 
     let _depth_normal_threshold = settings.depth_normal_threshold;
-    let _depth_normal_threshold_scale = settings.depth_normal_threshold_mul;
-    
+    let _depth_normal_threshold_scale = vec2f(settings.depth_normal_threshold_mul_along, settings.depth_normal_threshold_mul_across);
+
     let normal_threshold0 = saturate((NdotV - _depth_normal_threshold) / (1.0 - _depth_normal_threshold));
-    let normal_threshold = normal_threshold0 * _depth_normal_threshold_scale + 1;
+    // Depth changes fastest on screen the way the normal leans, uv's y pointing down
+    let camera_normal = (view.view_from_world * vec4(view_normal, 0.0)).xy * vec2f(1.0, -1.0);
+    let slope = select(vec2f(1.0, 0.0), normalize(camera_normal), dot(camera_normal, camera_normal) > 1e-8);
+    let grazing = DepthGrazing(normal_threshold0 * _depth_normal_threshold_scale + 1, slope);
+
+    // Every extra ring checks a smaller cross inside the full width one, filling the gaps a single wide cross leaves in thick strokes
+    let radius = kernel_radius();
+    for (var ring = radius; ring > 0u; ring--) {
+        if outline_cross(scale * f32(ring) / f32(radius), uv, grazing) > 0.5 {
+            return 1.0;
+        }
+    }
+    return 0.0;
+}
 
-    let edge_depth_0 = depth_buffer_edge_depth(normal_threshold, bl_uv, tr_uv, br_uv, tl_uv);
-    let edge_depth_1 = normal_buffer_edge_depth(bl_uv, tr_uv, br_uv, tl_uv);
-    let colour_depth = detect_edge_colour(bl_uv, tr_uv, br_uv, tl_uv);
-    let edge_depth = max(colour_depth, max(edge_depth_0, edge_depth_1));
+fn outline_cross(scale: f32, uv: vec2f, grazing: DepthGrazing) -> f32 {
+    let _scale = scale;
+    let texel_size = texel_size();
+
+    let half_scale_floor = floor(_scale * 0.5);
+    let half_scale_ceil = ceil(_scale * 0.5);
+
+    let bl_uv = mirror_into_viewport(uv - vec2f(texel_size.x, texel_size.y) * half_scale_floor);
+    let tr_uv = mirror_into_viewport(uv + vec2f(texel_size.x, texel_size.y) * half_scale_ceil);
+    let br_uv = mirror_into_viewport(uv + vec2f(texel_size.x * half_scale_ceil, -texel_size.y * half_scale_floor));
+    let tl_uv = mirror_into_viewport(uv + vec2f(-texel_size.x * half_scale_floor, texel_size.y * half_scale_ceil));
+
+    var edge_depth_0 = depth_buffer_edge_depth(grazing, bl_uv, tr_uv, br_uv, tl_uv);
+    var edge_depth_1 = max(normal_buffer_edge_depth(uv, bl_uv, tr_uv, br_uv, tl_uv), normal_detail_edge(bl_uv, tr_uv, br_uv, tl_uv));
+    var colour_depth = detect_edge_colour(bl_uv, tr_uv, br_uv, tl_uv);
+#ifdef ENTITY_OVERRIDES
+    let flags = entity_override(uv_to_pos(uv)).flags;
+    // Wireframes are drawn straight into the scene colour, depth and normal edges still outline these meshes
+    if (flags & 2u) != 0u {
+        colour_depth = 0.0;
+    }
+    // FoliageEdgeMode, leaf cards flicker with normal and colour edges
+    if (flags & 8u) != 0u {
+        colour_depth = 0.0;
+        edge_depth_1 = 0.0;
+    }
+    // ToonDecal, decals fade out over the surface they're on and the colour detector would outline the fade
+    if is_decal(uv) || is_decal(bl_uv) || is_decal(tr_uv) || is_decal(br_uv) || is_decal(tl_uv) {
+        colour_depth = 0.0;
+    }
+    if (flags & 16u) != 0u {
+        let id = entity_id(uv_to_pos(uv));
+        let inside = entity_id(uv_to_pos(bl_uv)) == id && entity_id(uv_to_pos(tr_uv)) == id
+            && entity_id(uv_to_pos(br_uv)) == id && entity_id(uv_to_pos(tl_uv)) == id;
+        edge_depth_0 = select(edge_depth_0, 0.0, inside);
+    }
+    // ToonHullOutline, an inverted hull already outlines this silhouette and a second line would double it up
+    if is_hull(uv) || is_hull(bl_uv) || is_hull(tr_uv) || is_hull(br_uv) || is_hull(tl_uv) {
+        return 0.0;
+    }
+#endif
+    var id_edge = 0.0;
+#ifdef ENTITY_OVERRIDES
+    id_edge = max(entity_id_edge(bl_uv, tr_uv, br_uv, tl_uv), imposter_edge(bl_uv, tr_uv, br_uv, tl_uv));
+#endif
+    var shadow_edge = 0.0;
+#ifndef COMPATIBILITY
+    shadow_edge = cast_shadow_edge(bl_uv, tr_uv, br_uv, tl_uv);
+#endif
+    let edge_depth = max(max(colour_depth, max(id_edge, shadow_edge)), max(edge_depth_0, edge_depth_1));
     
     if edge_depth > 0.5 {
         return 1.0;
@@ -170,9 +828,377 @@ fn outline_at_scale(scale: f32, uv: vec2f) -> f32 {
     }
 }
 
+#endif
+
+#ifdef OUTLINES
+// Strokes around small meshes with a ToonOutlineLod fade out, checked a stroke's reach either side so the strokes
+// drawn on what's behind them go too
+fn outline_lod(uv: vec2f, pos: vec2f) -> f32 {
+#ifdef ENTITY_OVERRIDES
+    let reach = max(ceil(get_sampling_scale(uv) * 0.5), 1.0);
+    let across = min(mesh_outline_lod(pos - vec2f(reach, 0.0)), mesh_outline_lod(pos + vec2f(reach, 0.0)));
+    let down = min(mesh_outline_lod(pos - vec2f(0.0, reach)), mesh_outline_lod(pos + vec2f(0.0, reach)));
+    return min(mesh_outline_lod(pos), min(across, down));
+#else
+    return 1.0;
+#endif
+}
+
+// Depth of field spreads a stroke over its circle of confusion, which leaves about stroke width / CoC of its contrast
+fn depth_of_field_attenuation(uv: vec2f, pos: vec2f) -> f32 {
+    if settings.dof_max_coc <= 0.0 {
+        return 1.0;
+    }
+    let depth = view_depth(uv);
+    if depth <= 0.0 {
+        return 1.0;
+    }
+    let z = min(depth, settings.dof_max_depth);
+    let focus = settings.dof_focal_distance;
+    // Same circle of confusion as bevy's depth of field pass, in pixels
+    let coc = settings.dof_coc_scale * abs(z - focus) / (z * (focus - settings.dof_focal_length));
+    let coc_pixels = clamp(coc * f32(textureDimensions(screen_texture).y), 0.0, settings.dof_max_coc);
+    return saturate(get_sampling_scale(uv) / max(coc_pixels, 1.0));
+}
+
+// Triplanar noise at the surface behind this pixel, so the gaps stay put on the geometry as the camera moves
+fn line_breakup(uv: vec2f, pos: vec2f) -> f32 {
+    if settings.line_breakup <= 0.0 {
+        return 1.0;
+    }
+    let depth = prepass_depth(pos);
+    if depth <= 0.0 {
+        return 1.0;
+    }
+    let world_pos = position_ndc_to_world(uv_to_ndc(viewport_uv(uv)), depth) / settings.line_breakup_scale;
+    let normal = abs(prepass_normal(pos) * 2.0 - 1.0);
+    let weights = normal / max(normal.x + normal.y + normal.z, 1e-4);
+    let noise = textureSampleLevel(noise_texture, noise_sampler, world_pos.yz, 0.0).r * weights.x
+        + textureSampleLevel(noise_texture, noise_sampler, world_pos.xz, 0.0).r * weights.y
+        + textureSampleLevel(noise_texture, noise_sampler, world_pos.xy, 0.0).r * weights.z;
+    let contrasted = saturate((noise - 0.5) * settings.line_breakup_contrast + 0.5);
+    return 1.0 - settings.line_breakup * (1.0 - contrasted);
+}
+
+fn luminance(c: vec3f) -> f32 {
+    return dot(c, vec3f(0.2126, 0.7152, 0.0722));
+}
+
+// Screen-space direction strokes run in at this pixel. Taken from the structure tensor of the depth and colour
+// gradients, so it doesn't flip sign along an edge and dashes stay evenly spaced.
+fn stroke_tangent(uv: vec2f) -> vec2f {
+    // Thick strokes extend past where the gradients are, so look half a stroke away
+    let reach = max(get_sampling_scale(uv) * 0.5, 1.0) * texel_size();
+    let dx = vec2f(reach.x, 0.0);
+    let dy = vec2f(0.0, reach.y);
+    let depth_scale = 1.0 / max(view_depth(uv), 1e-4);
+    let depth = vec2f(
+        view_depth(clamp_to_viewport(uv + dx)) - view_depth(clamp_to_viewport(uv - dx)),
+        view_depth(clamp_to_viewport(uv + dy)) - view_depth(clamp_to_viewport(uv - dy)),
+    ) * depth_scale;
+    let colour = vec2f(
+        luminance(textureSample(screen_texture, texture_sampler, clamp_to_viewport(uv + dx)).rgb) - luminance(textureSample(screen_texture, texture_sampler, clamp_to_viewport(uv - dx)).rgb),
+        luminance(textureSample(screen_texture, texture_sampler, clamp_to_viewport(uv + dy)).rgb) - luminance(textureSample(screen_texture, texture_sampler, clamp_to_viewport(uv - dy)).rgb),
+    );
+    let tensor = vec3f(depth.x * depth.x + colour.x * colour.x, depth.y * depth.y + colour.y * colour.y, depth.x * depth.y + colour.x * colour.y);
+    let normal_angle = 0.5 * atan2(2.0 * tensor.z, tensor.x - tensor.y);
+    return vec2f(-sin(normal_angle), cos(normal_angle));
+}
+
+// 1 on the dashes and 0 in the gaps, measured along the stroke in pixels
+fn dash_mask(uv: vec2f, pos: vec2f, dashes: vec2f) -> f32 {
+    if dashes.x <= 0.0 || dashes.y <= 0.0 {
+        return 1.0;
+    }
+    let period = dashes.x + dashes.y;
+    let along = dot(pos, stroke_tangent(uv));
+    return select(0.0, 1.0, along - floor(along / period) * period < dashes.x);
+}
+
+// Dash length and gap of the camera's stroke_style, zero when solid
+fn style_dashes(uv: vec2f) -> vec2f {
+    switch settings.stroke_style {
+        case 1u: {
+            return vec2f(settings.dash_length, settings.dash_gap);
+        }
+        case 2u: {
+            return vec2f(max(get_sampling_scale(uv), 1.0), settings.dash_gap);
+        }
+        default: {
+            return vec2f(0.0);
+        }
+    }
+}
+
+fn stroke_dashes(uv: vec2f, pos: vec2f) -> f32 {
+#ifdef ENTITY_OVERRIDES
+    // Strokes straddle silhouettes, so a stroke next to a dashed mesh is dashed too
+    let reach = ceil(get_sampling_scale(uv) * 0.5);
+    var offsets = array(vec2f(reach, 0.0), vec2f(-reach, 0.0), vec2f(0.0, reach), vec2f(0.0, -reach));
+    var dashes = entity_override(pos).dashes;
+    for (var i = 0; i < 4 && dashes.x <= 0.0; i++) {
+        dashes = entity_override(pos + offsets[i]).dashes;
+    }
+    if dashes.x > 0.0 {
+        return dash_mask(uv, pos, dashes);
+    }
+#endif
+    return dash_mask(uv, pos, style_dashes(uv));
+}
+
+// Direction to the key light from a point in the world, lit from the camera when the scene has no light
+fn towards_key_light(world_pos: vec3f) -> vec3f {
+    if settings.key_light.w > 0.0 {
+        return normalize(settings.key_light.xyz - world_pos);
+    }
+    if any(settings.key_light.xyz != vec3f(0.0)) {
+        return normalize(settings.key_light.xyz);
+    }
+    return normalize(view.world_position - world_pos);
+}
+
+#ifdef ENTITY_OVERRIDES
+// Position of v among n steps from 0 to 1, as 0 to 1
+fn terrain_step(v: f32, n: f32) -> f32 {
+    return min(floor(saturate(v) * n), n - 1.0) / max(n - 1.0, 1.0);
+}
+
+// Height and slope tints on ToonTerrain meshes, see TerrainBands
+fn terrain_tint(uv: vec2f, c: vec3f) -> vec3f {
+    let height = (world_position(uv).y - settings.terrain_base_height) / settings.terrain_height_range;
+    let steepness = 1.0 - saturate(sampled_normal(uv).y * 2.0 - 1.0);
+    let ground = mix(settings.terrain_low_colour, settings.terrain_high_colour, terrain_step(height, settings.terrain_height_bands));
+    return c * mix(ground, settings.terrain_cliff_colour, terrain_step(steepness, settings.terrain_slope_bands));
+}
+
+// Strand highlight band on ToonHair meshes, the shifted tangent trick from Kajiya-Kay hair shading cut off into a hard band
+fn hair_highlight(uv: vec2f, c: vec3f) -> vec3f {
+    let pos = uv_to_pos(uv);
+    let hair = entity_override(pos);
+    if hair.hair_colour.a <= 0.0 || prepass_depth(pos) <= 0.0 {
+        return c;
+    }
+    let normal = normalize(prepass_normal(pos) * 2.0 - 1.0);
+    // Strands run down the mesh, there's no direction to them on surfaces facing straight up or down
+    let strand = vec3f(0.0, -1.0, 0.0) + normal * normal.y;
+    if dot(strand, strand) < 1e-4 {
+        return c;
+    }
+    let tangent = normalize(normalize(strand) + normal * hair.hair_band.x);
+    let world_pos = world_position(uv);
+    let half_vector = normalize(towards_key_light(world_pos) + normalize(view.world_position - world_pos));
+    let t_dot_h = dot(tangent, half_vector);
+    let sin_th = sqrt(max(1.0 - t_dot_h * t_dot_h, 0.0));
+    let edge = 1.0 - hair.hair_band.y;
+    let band = smoothstep(edge - 0.01, edge + 0.01, sin_th);
+    return mix(c, hair.hair_colour.rgb, band * hair.hair_colour.a);
+}
+#endif
+
+// Cool to warm shading by how much the surface faces the key light, with the scene colour as the albedo
+fn gooch_colour(uv: vec2f, c: vec3f) -> vec3f {
+    let pos = uv_to_pos(uv);
+    if prepass_depth(pos) <= 0.0 {
+        return c;
+    }
+#ifdef ENTITY_OVERRIDES
+    if (entity_override(pos).flags & 1u) != 0u {
+        return c;
+    }
+#endif
+    let normal = normalize(prepass_normal(pos) * 2.0 - 1.0);
+    let t = dot(normal, towards_key_light(world_position(uv))) * 0.5 + 0.5;
+    let cool = settings.gooch_cool + settings.gooch_alpha * c;
+    let warm = settings.gooch_warm + settings.gooch_beta * c;
+    return mix(cool, warm, t);
+}
+
+// SimpletoonSettings::sky, the gradient from the horizon up to the zenith seen through this pixel, cut into bands
+fn sky_gradient(uv: vec2f) -> vec4f {
+    // Reverse-Z puts the near plane at 1, the ray from the camera through it gives the height of the sky
+    let near = position_ndc_to_world(uv_to_ndc(viewport_uv(uv)), 1.0);
+    var height = saturate(normalize(near - view.world_position).y);
+    let bands = settings.sky_bands;
+    if bands > 0.0 {
+        // Steps from the horizon colour to the zenith's, both of which get a band of their own
+        height = min(floor(height * bands) / max(bands - 1.0, 1.0), 1.0);
+    }
+    let segments = f32(settings.sky - 1u);
+    let i = min(u32(height * segments), settings.sky - 2u);
+    return mix(settings.sky_stops[i], settings.sky_stops[i + 1u], height * segments - f32(i));
+}
+
+// Grid lines every blueprint_grid_spacing pixels from the viewport's corner, anti-aliased over a pixel
+fn blueprint_grid(pos: vec2f) -> vec4f {
+    let spacing = settings.blueprint_grid_spacing;
+    let cell = pos - view.viewport.xy;
+    let offset = cell - floor(cell / spacing + 0.5) * spacing;
+    let distance = min(abs(offset.x), abs(offset.y));
+    let line = 1.0 - smoothstep(settings.blueprint_line_width * 0.5 - 0.5, settings.blueprint_line_width * 0.5 + 0.5, distance);
+    return mix(settings.blueprint_background, settings.blueprint_grid_colour, line);
+}
+
+// Black or white, whichever has the higher contrast ratio against the scene around the stroke
+fn high_contrast_colour(uv: vec2f) -> vec3f {
+    let reach = (ceil(get_sampling_scale(uv) * 0.5) + 1.0) * texel_size();
+    var around = 0.0;
+    around += luminance(textureSample(screen_texture, texture_sampler, clamp_to_viewport(uv + vec2f(reach.x, reach.y))).rgb);
+    around += luminance(textureSample(screen_texture, texture_sampler, clamp_to_viewport(uv + vec2f(-reach.x, reach.y))).rgb);
+    around += luminance(textureSample(screen_texture, texture_sampler, clamp_to_viewport(uv + vec2f(reach.x, -reach.y))).rgb);
+    around += luminance(textureSample(screen_texture, texture_sampler, clamp_to_viewport(uv - reach)).rgb);
+    let l = saturate(around * 0.25);
+    let against_black = (l + 0.05) / 0.05;
+    let against_white = 1.05 / (l + 0.05);
+    return select(vec3f(1.0), vec3f(0.0), against_black > against_white);
+}
+#endif
+
+fn stroke_amount(uv: vec2f, pos: vec2f) -> f32 {
+#ifdef OUTLINES
+#ifdef EDGE_MASK_INPUT
+    return textureLoad(edge_mask_texture, vec2i(pos), 0).r * depth_of_field_attenuation(uv, pos) * line_breakup(uv, pos) * stroke_dashes(uv, pos) * screen_edge_fade(pos) * outline_mask(pos) * outline_lod(uv, pos);
+#else
+    return outline_at_scale(get_sampling_scale(uv), uv) * depth_of_field_attenuation(uv, pos) * line_breakup(uv, pos) * stroke_dashes(uv, pos) * screen_edge_fade(pos) * outline_mask(pos) * outline_lod(uv, pos);
+#endif
+#else
+    return 0.0;
+#endif
+}
+
+// Antialiases the edges of strokes without touching the scene: pixels next to a change in stroke coverage take a
+// share of their neighbours', which leaves the inside of strokes and everything away from them alone. Each neighbour
+// costs a full stroke lookup, a texel read with the edge mask
+fn smooth_stroke(uv: vec2f, pos: vec2f, o: f32) -> f32 {
+    if settings.stroke_smoothing == 0u {
+        return o;
+    }
+    let texel = texel_size();
+    var neighbours = 0.0;
+    for (var i = 0u; i < 4u; i++) {
+        let step = select(vec2f(0.0, 1.0), vec2f(1.0, 0.0), i < 2u) * select(-1.0, 1.0, (i & 1u) == 0u);
+        let neighbour_uv = clamp_to_viewport(uv + step * texel);
+        neighbours += stroke_amount(neighbour_uv, uv_to_pos(neighbour_uv));
+    }
+    return o * 0.5 + neighbours * 0.125;
+}
+
+#ifdef OUTLINES
+// Strokes written to an intermediate mask, kept thin when the dilation passes will grow them
+@fragment
+fn edge_mask(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let scale = select(get_sampling_scale(in.uv), 1.0, settings.stroke_dilation != 0u);
+    return vec4f(outline_at_scale(scale, in.uv) * outline_mask(in.position.xy), 0.0, 0.0, 1.0);
+}
+
+#ifdef EDGE_MASK_INPUT
+fn edge_mask_at(pos: vec2i) -> f32 {
+    let min_pos = vec2i(view.viewport.xy);
+    let max_pos = vec2i(view.viewport.xy + view.viewport.zw) - 1;
+    return textureLoad(edge_mask_texture, clamp(pos, min_pos, max_pos), 0).r;
+}
+
+// A small junction-aware close: bridges 1px breaks in strokes and fills the missing pixel where two strokes meet at a corner
+@fragment
+fn cleanup_edges(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let pos = vec2i(in.position.xy);
+    let centre = edge_mask_at(pos);
+    if centre > 0.5 {
+        return vec4f(centre, 0.0, 0.0, 1.0);
+    }
+    let l = edge_mask_at(pos + vec2i(-1, 0));
+    let r = edge_mask_at(pos + vec2i(1, 0));
+    let u = edge_mask_at(pos + vec2i(0, -1));
+    let d = edge_mask_at(pos + vec2i(0, 1));
+    let gap = max(
+        max(min(l, r), min(u, d)),
+        max(min(edge_mask_at(pos + vec2i(-1, -1)), edge_mask_at(pos + vec2i(1, 1))), min(edge_mask_at(pos + vec2i(1, -1)), edge_mask_at(pos + vec2i(-1, 1)))),
+    );
+    // Both strokes have to carry on past their neighbour, otherwise every step of a diagonal line would be filled in
+    let l2 = min(l, edge_mask_at(pos + vec2i(-2, 0)));
+    let r2 = min(r, edge_mask_at(pos + vec2i(2, 0)));
+    let u2 = min(u, edge_mask_at(pos + vec2i(0, -2)));
+    let d2 = min(d, edge_mask_at(pos + vec2i(0, 2)));
+    let corner = min(max(l2, r2), max(u2, d2));
+    return vec4f(max(max(gap, corner), centre), 0.0, 0.0, 1.0);
+}
+
+// One axis of a separable max filter over the edge mask
+@fragment
+fn dilate(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let pos = vec2i(in.position.xy);
+    let radius = clamp(i32(round((get_sampling_scale(in.uv) - 1.0) * 0.5)), 0, 16);
+    let min_pos = vec2i(view.viewport.xy);
+    let max_pos = vec2i(view.viewport.xy + view.viewport.zw) - 1;
+
+    var edge = 0.0;
+    for (var i = -radius; i <= radius; i++) {
+#ifdef DILATE_VERTICAL
+        let offset = vec2i(0, i);
+#else
+        let offset = vec2i(i, 0);
+#endif
+        edge = max(edge, textureLoad(edge_mask_texture, clamp(pos + offset, min_pos, max_pos), 0).r);
+    }
+    return vec4f(edge, 0.0, 0.0, 1.0);
+}
+#endif
+
+// Runs before bloom so strokes brighter than 1.0 glow
+@fragment
+fn emissive_strokes(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let source = textureSample(screen_texture, texture_sampler, in.uv);
+    let o = outline_at_scale(get_sampling_scale(in.uv), in.uv) * screen_edge_fade(in.position.xy) * outline_lod(in.uv, in.position.xy) * stroke_pulse();
+    let stroke = vec4f(cycle_stroke_hue(settings.stroke_colour.rgb) * settings.emissive_strokes, settings.stroke_colour.a);
+    return select(source, mix(source, stroke, o), inside_viewport(in.position.xy));
+}
+#endif
+
+fn radial_hash(n: f32, seed: f32) -> f32 {
+    return fract(sin(n * 12.9898 + seed * 78.233) * 43758.5453);
+}
+
+// x = distance from centre (a viewport uv) in half viewport diagonals, y = how far round it in sectors
+fn radial_position(pos: vec2f, centre: vec2f, sectors: f32) -> vec2f {
+    let to_pixel = pos - (view.viewport.xy + centre * view.viewport.zw);
+    let radius = length(to_pixel) / (0.5 * length(view.viewport.zw));
+    return vec2f(radius, (atan2(to_pixel.y, to_pixel.x) / 6.28318531 + 0.5) * max(sectors, 1.0));
+}
+
+// Inverted black and white with action lines bursting out from settings.impact_centre, see ImpactFrame
+fn impact_frame(pos: vec2f, c: vec4f) -> vec4f {
+    let ink = dot(c.rgb, vec3f(0.2126, 0.7152, 0.0722)) > 0.5;
+    let radial = radial_position(pos, settings.impact_centre, settings.impact_lines);
+    let sector = floor(radial.y);
+    // Each line starts a little further out or in and is a little thicker or thinner than its neighbours
+    let start = settings.impact_inner_radius * mix(0.7, 1.3, radial_hash(sector, settings.impact_seed));
+    let width = mix(0.05, 0.35, radial_hash(sector + 0.5, settings.impact_seed)) * saturate((radial.x - start) / max(1.0 - start, 1e-4));
+    let line = radial.x > start && abs(fract(radial.y) - 0.5) < width;
+    return vec4f(vec3f(select(1.0, 0.0, ink || line)), c.a);
+}
+
+// Coverage of the ToonSpeedLines line at this pixel, if any
+fn speed_line(pos: vec2f) -> f32 {
+    let radial = radial_position(pos, settings.speed_line_centre, settings.speed_line_count);
+    let sector = floor(radial.y);
+    let seed = settings.speed_line_seed;
+    let start = settings.speed_line_inner_radius + settings.speed_line_jitter * (radial_hash(sector, seed) * 2.0 - 1.0);
+    let along = (radial.x - start) / max(settings.speed_line_length, 1e-4);
+    if along <= 0.0 || along >= 1.0 {
+        return 0.0;
+    }
+    // Off centre within its sector so the lines don't look evenly spaced
+    let offset = (radial_hash(sector + 0.25, seed) - 0.5) * (1.0 - settings.speed_line_width);
+    let width = settings.speed_line_width * mix(0.5, 1.0, radial_hash(sector + 0.5, seed)) * along;
+    let across = abs(fract(radial.y) - 0.5 - offset) / max(width * 0.5, 1e-4);
+    return saturate(1.0 - across);
+}
+
 @fragment
 fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
 
+    let source = textureSample(screen_texture, texture_sampler, in.uv);
+
+#ifdef OUTLINES
     var o1mix = 1.0;
     var o2mix = 1.0;
     var o3mix = 1.0;
@@ -190,9 +1216,52 @@ fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
     var o1 = outline_at_scale(1.0, in.uv) * o1mix;
     var o2 = outline_at_scale(2.0, in.uv) * o2mix;
     var o3 = outline_at_scale(3.0, in.uv) * o3mix;
-    var o = outline_at_scale(settings.stroke_size, in.uv);//max(o1, max(o2, o3));
+#endif
+    var o = smooth_stroke(in.uv, in.position.xy, stroke_amount(in.uv, in.position.xy));//max(o1, max(o2, o3));
+    // Emissive strokes were pulsed when they were drawn
+    if settings.emissive_strokes <= 0.0 {
+        o *= stroke_pulse();
+    }
 
-    var c = mix(toon_colour(in.uv), settings.stroke_colour, o);
+    // Emissive strokes are already in the scene (and bloomed), so keep them rather than painting over them
+    var stroke_colour = select(settings.stroke_colour, source, settings.emissive_strokes > 0.0);
+    if settings.emissive_strokes <= 0.0 {
+        stroke_colour = vec4f(cycle_stroke_hue(stroke_colour.rgb), stroke_colour.a);
+    }
+#ifdef OUTLINES
+    if settings.high_contrast_strokes != 0u && settings.emissive_strokes <= 0.0 {
+        stroke_colour = vec4f(high_contrast_colour(in.uv), stroke_colour.a);
+    }
+#endif
+#ifdef ENTITY_OVERRIDES
+    let entity = entity_override(in.position.xy);
+    if (entity.flags & 128u) != 0u && settings.emissive_strokes <= 0.0 {
+        stroke_colour = entity.stroke_colour;
+    }
+#endif
+    var shaded = toon_colour(in.uv);
+#ifdef OUTLINES
+#ifdef ENTITY_OVERRIDES
+    shaded = vec4f(hair_highlight(in.uv, shaded.rgb), shaded.a);
+    if settings.terrain != 0u && (entity_override(in.position.xy).flags & 32u) != 0u {
+        shaded = vec4f(terrain_tint(in.uv, shaded.rgb), shaded.a);
+    }
+#endif
+#endif
+    // ToonShading::LineArt, nothing of the scene is left but its strokes
+    if settings.shading == 2u {
+        shaded = settings.line_art_background;
+    }
+    var c = mix(shaded, stroke_colour, o);
+#ifdef OUTLINES
+    // Line art keeps its flat background where nothing was drawn as well
+    if settings.sky != 0u && d <= 0.0 && settings.shading != 2u {
+        c = mix(sky_gradient(in.uv), stroke_colour, o);
+    }
+    if settings.blueprint != 0u && d <= 0.0 && settings.shading != 2u {
+        c = mix(blueprint_grid(in.position.xy), stroke_colour, o);
+    }
+#endif
     //0.8752 -> 0.87515 == 1.0 -> 0.0
     //0.00005 -> 0.0
     //1.0 -> 0.0
@@ -206,5 +1275,42 @@ fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
     //    c = vec4f(0.0, 0.0, 1.0, 1.0);
     //}
 
-    return vec4f(c);
+    var toon = mix(source, c, saturate(settings.effect_strength));
+    if settings.preserve_alpha != 0u {
+        // Strokes spilling over a silhouette onto see-through pixels stay visible
+        toon.a = max(source.a, o * stroke_colour.a * saturate(settings.effect_strength));
+    }
+#ifdef ENTITY_OVERRIDES
+    // ToonPassThrough meshes are put back over the result with their authored colours
+    if (entity_override(in.position.xy).flags & 4u) != 0u {
+        toon = vec4f(source.rgb, toon.a);
+    }
+#endif
+    // ToonVfxExclusion, particles keep the colours they were drawn with
+    let vfx_max_pos = vec2i(textureDimensions(vfx_mask_texture)) - 1;
+    let keep = textureLoad(vfx_mask_texture, clamp(vec2i(in.position.xy), vec2i(0), vfx_max_pos), 0).r;
+    toon = vec4f(mix(source.rgb, toon.rgb, keep), toon.a);
+    // ToonCompare, for tuning against the scene underneath
+    if settings.compare != 0u && (in.position.x - view.viewport.x) / view.viewport.z > settings.compare_split {
+        toon = source;
+    }
+    if settings.speed_lines != 0u {
+        let colour = settings.speed_line_colour;
+        toon = vec4f(mix(toon.rgb, colour.rgb, speed_line(in.position.xy) * colour.a), toon.a);
+    }
+    if settings.impact != 0u {
+        toon = impact_frame(in.position.xy, toon);
+    }
+#ifdef OUTLINES
+    // Anything that isn't geometry or a stroke is see-through in a ToonScreenshot
+    if settings.transparent_background != 0u {
+        toon.a = max(select(0.0, 1.0, d > 0.0), o * settings.stroke_colour.a);
+    }
+#endif
+    // An edge hint for an antialiasing pass after this one, a ToonScreenshot needs the alpha for itself
+    if settings.edge_alpha != 0u && settings.transparent_background == 0u {
+        toon.a = o * saturate(settings.effect_strength);
+    }
+    // Other cameras may share this target, leave everything outside our viewport alone
+    return select(source, toon, inside_viewport(in.position.xy));
 }
\ No newline at end of file
@@ -0,0 +1,20 @@
+@group(0) @binding(0) var toon_texture: texture_2d<f32>;
+// Counts of pixels per bin of luminance, cleared before every dispatch
+@group(0) @binding(1) var<storage, read_write> histogram: array<atomic<u32>, 64>;
+
+// Must match band_histogram.rs
+const BINS: u32 = 64u;
+// Only every 4th pixel each way is counted
+const STRIDE: u32 = 4u;
+
+@compute @workgroup_size(8, 8, 1)
+fn histogram_bands(@builtin(global_invocation_id) id: vec3u) {
+    let dims = textureDimensions(toon_texture);
+    let pos = id.xy * STRIDE;
+    if any(pos >= dims) {
+        return;
+    }
+    let luminance = dot(textureLoad(toon_texture, pos, 0).rgb, vec3f(0.2126, 0.7152, 0.0722));
+    let bin = u32(clamp(luminance * f32(BINS), 0.0, f32(BINS - 1u)));
+    atomicAdd(&histogram[bin], 1u);
+}
@@ -0,0 +1,34 @@
+#import bevy_render::view::View
+
+@group(0) @binding(0) var depth_texture: texture_depth_2d;
+@group(0) @binding(1) var<uniform> view: View;
+// Counts of drawn pixels per bin of view depth, cleared before every dispatch
+@group(0) @binding(2) var<storage, read_write> histogram: array<atomic<u32>, 32>;
+
+// Must match auto_thresholds.rs
+const BINS: u32 = 32u;
+const MIN_OCTAVE: f32 = -4.0;
+const BINS_PER_OCTAVE: f32 = 2.0;
+// Only every 4th pixel each way is counted, plenty for a histogram
+const STRIDE: u32 = 4u;
+
+@compute @workgroup_size(8, 8, 1)
+fn histogram_depth(@builtin(global_invocation_id) id: vec3u) {
+    let dims = textureDimensions(depth_texture);
+    let pos = id.xy * STRIDE;
+    if any(pos >= dims) {
+        return;
+    }
+    let depth = textureLoad(depth_texture, pos, 0);
+    if depth <= 0.0 {
+        return;
+    }
+    let uv = (vec2f(pos) + 0.5) / vec2f(dims);
+    let view_pos = view.view_from_clip * vec4f(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, depth, 1.0);
+    let distance = -view_pos.z / view_pos.w;
+    if distance <= 0.0 {
+        return;
+    }
+    let bin = u32(clamp((log2(distance) - MIN_OCTAVE) * BINS_PER_OCTAVE, 0.0, f32(BINS - 1u)));
+    atomicAdd(&histogram[bin], 1u);
+}
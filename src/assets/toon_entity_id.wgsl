@@ -0,0 +1,12 @@
+#import bevy_pbr::forward_io::VertexOutput
+
+// Index into the post process' override buffer, 0 means no overrides
+struct ToonEntityId {
+    id: u32,
+}
+@group(2) @binding(0) var<uniform> entity_id: ToonEntityId;
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) u32 {
+    return entity_id.id;
+}
@@ -0,0 +1,55 @@
+#import bevy_pbr::{
+    forward_io::{VertexOutput, FragmentOutput},
+    mesh_functions::get_world_from_local,
+    pbr_fragment::pbr_input_from_standard_material,
+    pbr_functions::{alpha_discard, main_pass_post_lighting_processing},
+}
+
+// Mirrors ToonFaceShadow's uniform fields
+struct ToonFaceShadow {
+    forward: vec3f, // mesh space
+    right: vec3f, // mesh space, the side the shadow map is painted lit from
+    shadow_colour: vec4f,
+    softness: f32,
+    light: vec4f, // xyz towards a directional light (w = 0) or a light's position (w = 1), zero without a light
+}
+@group(2) @binding(100) var<uniform> face: ToonFaceShadow;
+@group(2) @binding(101) var shadow_map: texture_2d<f32>;
+@group(2) @binding(102) var shadow_sampler: sampler;
+
+// How lit the face is here, from the painted threshold against the light's angle around the head
+fn face_lit(in: VertexOutput) -> f32 {
+#ifdef VERTEX_UVS_A
+    if all(face.light == vec4f(0.0)) {
+        return 1.0;
+    }
+    let world_from_local = get_world_from_local(in.instance_index);
+    let forward = normalize((world_from_local * vec4f(face.forward, 0.0)).xyz);
+    let right = normalize((world_from_local * vec4f(face.right, 0.0)).xyz);
+    let towards_light = select(face.light.xyz, face.light.xyz - in.world_position.xyz, face.light.w > 0.0);
+    // Only the angle around the head counts, a light straight above counts as in front
+    var around = vec2f(dot(towards_light, right), dot(towards_light, forward));
+    around = select(vec2f(0.0, 1.0), normalize(around), dot(around, around) > 1e-8);
+    // 0 with the light in front, 1 behind
+    let threshold = acos(clamp(around.y, -1.0, 1.0)) / 3.14159265;
+    // The map is painted for light from the right, mirror it for light from the left
+    let uv = select(in.uv, vec2f(1.0 - in.uv.x, in.uv.y), around.x < 0.0);
+    let painted = textureSample(shadow_map, shadow_sampler, uv).r;
+    return smoothstep(threshold - face.softness, threshold + face.softness, painted);
+#else
+    return 1.0;
+#endif
+}
+
+@fragment
+fn fragment(in: VertexOutput, @builtin(front_facing) is_front: bool) -> FragmentOutput {
+    let lit = face_lit(in);
+    var pbr_input = pbr_input_from_standard_material(in, is_front);
+    pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
+
+    var out: FragmentOutput;
+    let base_color = pbr_input.material.base_color;
+    out.color = vec4f(base_color.rgb * mix(face.shadow_colour.rgb, vec3f(1.0), lit), base_color.a);
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+    return out;
+}
@@ -0,0 +1,120 @@
+#import bevy_pbr::{
+    clustered_forward as clustering,
+    forward_io::{VertexOutput, FragmentOutput},
+    lighting::getDistanceAttenuation,
+    mesh_types::MESH_FLAGS_SHADOW_RECEIVER_BIT,
+    mesh_view_bindings as view_bindings,
+    mesh_view_types::{POINT_LIGHT_FLAGS_SHADOWS_ENABLED_BIT, POINT_LIGHT_FLAGS_SPOT_LIGHT_Y_NEGATIVE, DIRECTIONAL_LIGHT_FLAGS_SHADOWS_ENABLED_BIT},
+    pbr_fragment::pbr_input_from_standard_material,
+    pbr_functions::{alpha_discard, main_pass_post_lighting_processing},
+    pbr_types::PbrInput,
+    shadows,
+}
+
+// Mirrors ToonLightBands' uniform fields
+struct ToonLightBands {
+    bands: u32,
+    // How much of the top of each band eases into the next, 0 for hard steps
+    softness: f32,
+}
+@group(2) @binding(100) var<uniform> light_bands: ToonLightBands;
+
+// Steps a single light's contribution, measured in exposed lighting where 1 lights a surface to its full colour
+fn band(light: vec3f) -> vec3f {
+    let amount = max(max(light.r, light.g), light.b);
+    if amount <= 0.0 {
+        return vec3f(0.0);
+    }
+    let bands = f32(max(light_bands.bands, 1u));
+    let scaled = amount * bands;
+    // Rounded rather than floored, so a pool ends where the light has half a band left rather than a whole one
+    var banded = floor(scaled + 0.5);
+    if light_bands.softness > 0.0 {
+        // Ease into the next band over the top of this one
+        banded += smoothstep(1.0 - light_bands.softness, 1.0, fract(scaled + 0.5));
+    }
+    return light * (banded / scaled);
+}
+
+fn spot_attenuation(light_id: u32, towards_light: vec3f) -> f32 {
+    let light = &view_bindings::clusterable_objects.data[light_id];
+    var spot_dir = vec3f((*light).light_custom_data.x, 0.0, (*light).light_custom_data.y);
+    spot_dir.y = sqrt(max(0.0, 1.0 - spot_dir.x * spot_dir.x - spot_dir.z * spot_dir.z));
+    if ((*light).flags & POINT_LIGHT_FLAGS_SPOT_LIGHT_Y_NEGATIVE) != 0u {
+        spot_dir.y = -spot_dir.y;
+    }
+    let attenuation = saturate(dot(-spot_dir, towards_light) * (*light).light_custom_data.z + (*light).light_custom_data.w);
+    return attenuation * attenuation;
+}
+
+// A point or spot light's diffuse lighting here, before it is banded
+fn clustered_light(pbr_input: PbrInput, light_id: u32, spot: bool) -> vec3f {
+    let light = &view_bindings::clusterable_objects.data[light_id];
+    let to_light = (*light).position_radius.xyz - pbr_input.world_position.xyz;
+    let towards_light = normalize(to_light);
+    var attenuation = getDistanceAttenuation(dot(to_light, to_light), (*light).color_inverse_square_range.w);
+    var shadow = 1.0;
+    let receives_shadows = (pbr_input.flags & MESH_FLAGS_SHADOW_RECEIVER_BIT) != 0u
+        && ((*light).flags & POINT_LIGHT_FLAGS_SHADOWS_ENABLED_BIT) != 0u;
+    if spot {
+        attenuation *= spot_attenuation(light_id, towards_light);
+        if receives_shadows {
+            shadow = shadows::fetch_spot_shadow(light_id, pbr_input.world_position, pbr_input.world_normal, (*light).shadow_map_near_z);
+        }
+    } else if receives_shadows {
+        shadow = shadows::fetch_point_shadow(light_id, pbr_input.world_position, pbr_input.world_normal);
+    }
+    let n_dot_l = saturate(dot(pbr_input.N, towards_light));
+    return (*light).color_inverse_square_range.rgb * attenuation * n_dot_l * shadow;
+}
+
+// Every light's diffuse lighting, each banded on its own before they are added up
+fn banded_lighting(pbr_input: PbrInput) -> vec3f {
+    // Lambertian, matching the brightness of StandardMaterial's diffuse
+    let scale = view_bindings::view.exposure / 3.14159265;
+    let view_z = dot(vec4f(
+        view_bindings::view.view_from_world[0].z,
+        view_bindings::view.view_from_world[1].z,
+        view_bindings::view.view_from_world[2].z,
+        view_bindings::view.view_from_world[3].z
+    ), pbr_input.world_position);
+    var lighting = vec3f(0.0);
+
+    let cluster_index = clustering::fragment_cluster_index(pbr_input.frag_coord.xy, view_z, pbr_input.is_orthographic);
+    let ranges = clustering::unpack_clusterable_object_index_ranges(cluster_index);
+    for (var i = ranges.first_point_light_index_offset; i < ranges.first_spot_light_index_offset; i += 1u) {
+        lighting += band(clustered_light(pbr_input, clustering::get_clusterable_object_id(i), false) * scale);
+    }
+    for (var i = ranges.first_spot_light_index_offset; i < ranges.first_reflection_probe_index_offset; i += 1u) {
+        lighting += band(clustered_light(pbr_input, clustering::get_clusterable_object_id(i), true) * scale);
+    }
+
+    for (var i = 0u; i < view_bindings::lights.n_directional_lights; i += 1u) {
+        let light = &view_bindings::lights.directional_lights[i];
+        var shadow = 1.0;
+        if (pbr_input.flags & MESH_FLAGS_SHADOW_RECEIVER_BIT) != 0u
+            && ((*light).flags & DIRECTIONAL_LIGHT_FLAGS_SHADOWS_ENABLED_BIT) != 0u {
+            shadow = shadows::fetch_directional_shadow(i, pbr_input.world_position, pbr_input.world_normal, view_z);
+        }
+        let n_dot_l = saturate(dot(pbr_input.N, (*light).direction_to_light));
+        lighting += band((*light).color.rgb * n_dot_l * shadow * scale);
+    }
+
+    // Ambient light is flat already, banding it would only darken it
+    return lighting + view_bindings::lights.ambient_color.rgb * pbr_input.diffuse_occlusion * view_bindings::view.exposure;
+}
+
+@fragment
+fn fragment(in: VertexOutput, @builtin(front_facing) is_front: bool) -> FragmentOutput {
+    var pbr_input = pbr_input_from_standard_material(in, is_front);
+    pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
+
+    var out: FragmentOutput;
+    let base_color = pbr_input.material.base_color;
+    let emissive = pbr_input.material.emissive;
+    let diffuse = base_color.rgb * (1.0 - pbr_input.material.metallic);
+    let emissive_light = emissive.rgb * base_color.a * mix(1.0, view_bindings::view.exposure, emissive.a);
+    out.color = vec4f(diffuse * banded_lighting(pbr_input) + emissive_light, base_color.a);
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+    return out;
+}
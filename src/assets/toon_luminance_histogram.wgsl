@@ -0,0 +1,23 @@
+@group(0) @binding(0) var scene_texture: texture_2d<f32>;
+// Counts of pixels per bin of log luminance, cleared before every dispatch
+@group(0) @binding(1) var<storage, read_write> histogram: array<atomic<u32>, 32>;
+
+// Must match band_anchor.rs
+const BINS: u32 = 32u;
+const MIN_STOP: f32 = -10.0;
+const BINS_PER_STOP: f32 = 2.0;
+// Only every 4th pixel each way is counted, plenty for an average
+const STRIDE: u32 = 4u;
+
+@compute @workgroup_size(8, 8, 1)
+fn histogram_luminance(@builtin(global_invocation_id) id: vec3u) {
+    let dims = textureDimensions(scene_texture);
+    let pos = id.xy * STRIDE;
+    if any(pos >= dims) {
+        return;
+    }
+    let luminance = dot(textureLoad(scene_texture, pos, 0).rgb, vec3f(0.2126, 0.7152, 0.0722));
+    let stop = log2(max(luminance, exp2(MIN_STOP)));
+    let bin = u32(clamp((stop - MIN_STOP) * BINS_PER_STOP, 0.0, f32(BINS - 1u)));
+    atomicAdd(&histogram[bin], 1u);
+}
@@ -0,0 +1,10 @@
+#define_import_path bevy_simpletoon::outline_mask
+
+// For custom materials' prepass fragment shaders. Bevy's prepass writes 1 to the normal target's alpha, which the
+// toon pass reads as "outline here"; pass the prepass normal through this to keep strokes off the fragment, e.g. for
+// foliage cards or hair cutouts where whole-entity exclusion is too coarse.
+//
+//     out.normal = mask_outlines(out.normal);
+fn mask_outlines(normal: vec4f) -> vec4f {
+    return vec4f(normal.xyz, 0.0);
+}
@@ -0,0 +1,27 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+@group(0) @binding(0) var screen_texture: texture_2d<f32>;
+@group(0) @binding(1) var opaque_texture: texture_2d<f32>; // The scene before transparent geometry was drawn
+struct ToonTransparentSettings {
+    colour_banding: f32,
+    effect_strength: f32,
+}
+@group(0) @binding(2) var<uniform> settings: ToonTransparentSettings;
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let pos = vec2i(in.position.xy);
+    let c = textureLoad(screen_texture, pos, 0);
+    let opaque = textureLoad(opaque_texture, pos, 0);
+
+    // Only pixels the transparent pass drew over differ from the snapshot
+    let diff = c.rgb - opaque.rgb;
+    let i = length(c.rgb);
+    if dot(diff, diff) < 1e-7 || i <= 0.0 {
+        return c;
+    }
+
+    let new_i = floor(i * settings.colour_banding) / settings.colour_banding;
+    let banded = normalize(c.rgb) * new_i;
+    return vec4f(mix(c.rgb, banded, saturate(settings.effect_strength)), c.a);
+}
@@ -0,0 +1,31 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+@group(0) @binding(0) var screen_texture: texture_2d<f32>;
+@group(0) @binding(1) var snapshot_texture: texture_2d<f32>; // The scene before the transparent pass
+struct ToonVfxExclusion {
+    threshold: f32,
+    spread: f32,
+}
+@group(0) @binding(2) var<uniform> settings: ToonVfxExclusion;
+
+// 1 where the transparent pass changed the pixel by more than the threshold
+fn vfx(pos: vec2i) -> f32 {
+    let max_pos = vec2i(textureDimensions(screen_texture)) - 1;
+    let clamped = clamp(pos, vec2i(0), max_pos);
+    let change = abs(textureLoad(screen_texture, clamped, 0).rgb - textureLoad(snapshot_texture, clamped, 0).rgb);
+    return step(settings.threshold, max(change.r, max(change.g, change.b)));
+}
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let pos = vec2i(in.position.xy);
+    let spread = i32(clamp(settings.spread, 0.0, 8.0));
+    var covered = 0.0;
+    for (var y = -spread; y <= spread; y++) {
+        for (var x = -spread; x <= spread; x++) {
+            covered = max(covered, vfx(pos + vec2i(x, y)));
+        }
+    }
+    // What the toon pass keeps of its own result
+    return vec4f(1.0 - covered);
+}
@@ -0,0 +1,237 @@
+use bevy::{
+    asset::embedded_asset,
+    core_pipeline::{
+        core_3d::graph::{Core3d, Node3d},
+        prepass::ViewPrepassTextures,
+    },
+    ecs::{query::QueryItem, system::SystemParamItem},
+    prelude::*,
+    render::{
+        render_graph::{NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner},
+        render_resource::{
+            binding_types::{storage_buffer_sized, texture_depth_2d, uniform_buffer},
+            *,
+        },
+        renderer::{RenderContext, RenderDevice},
+        view::{ViewUniform, ViewUniformOffset, ViewUniforms},
+        RenderApp,
+    },
+};
+
+use crate::histogram::{GpuHistogram, GpuHistogramPlugin, HistogramBuffer, HistogramPipeline};
+use crate::plugin::{BindGroupKey, SimpletoonBindGroupCache, SimpletoonSettings};
+
+/// Bins are half an octave of view depth wide, from 2^MIN_OCTAVE metres up, must match toon_depth_histogram.wgsl.
+const BINS: usize = 32;
+const MIN_OCTAVE: f32 = -4.0;
+const BINS_PER_OCTAVE: f32 = 2.0;
+
+/// Add to a toon camera to retune its depth thresholds every frame from how far away the scene is, so close-ups and
+/// far vistas keep a similar line density without tuning every shot by hand.
+///
+/// A compute pass bins the depth prepass into a histogram, which is read back a frame or two later. The camera's
+/// `depth_threshold` is this `depth_threshold` scaled by the median depth against `reference_depth`, and its
+/// `depth_threshold_depth_mul` this one scaled by the square root of the depth spread (90th over 10th percentile)
+/// against `reference_spread`. Needs `Msaa::Off`, and does nothing on adapters using the compatibility pipelines.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ToonAutoThresholds {
+    pub depth_threshold: f32,
+    pub depth_threshold_depth_mul: f32,
+    /// Median depth in metres at which `depth_threshold` is used as it is.
+    pub reference_depth: f32,
+    /// Ratio of the 90th to the 10th percentile depth at which `depth_threshold_depth_mul` is used as it is.
+    pub reference_spread: f32,
+    /// How quickly the thresholds follow the scene, higher is faster. 0 stops them changing.
+    pub response: f32,
+}
+
+impl ToonAutoThresholds {
+    /// Starts from `settings`' own thresholds.
+    pub fn new(settings: &SimpletoonSettings) -> Self {
+        Self {
+            depth_threshold: settings.depth_threshold,
+            depth_threshold_depth_mul: settings.depth_threshold_depth_mul,
+            reference_depth: 5.0,
+            reference_spread: 4.0,
+            response: 4.0,
+        }
+    }
+}
+
+impl Default for ToonAutoThresholds {
+    fn default() -> Self {
+        Self::new(&SimpletoonSettings::default())
+    }
+}
+
+impl GpuHistogram for ToonAutoThresholds {
+    const NAME: &'static str = "depth_histogram";
+    const BINS: usize = BINS;
+    const SHADER: &'static str = "embedded://bevy_simpletoon/assets/toon_depth_histogram.wgsl";
+    const ENTRY_POINT: &'static str = "histogram_depth";
+    type Param = (Query<'static, 'static, (&'static ToonAutoThresholds, &'static mut SimpletoonSettings)>, Res<'static, Time>);
+
+    fn layout_entries() -> Vec<BindGroupLayoutEntry> {
+        BindGroupLayoutEntries::sequential(
+            ShaderStages::COMPUTE,
+            (
+                texture_depth_2d(),
+                uniform_buffer::<ViewUniform>(true),
+                storage_buffer_sized(false, None),
+            ),
+        )
+        .to_vec()
+    }
+
+    fn apply(camera: Entity, counts: &[u32], (cameras, time): &mut SystemParamItem<Self::Param>) {
+        let Ok((auto, mut settings)) = cameras.get_mut(camera) else {
+            return;
+        };
+        let total: u32 = counts.iter().sum();
+        if total == 0 {
+            return;
+        }
+        let median = percentile(counts, total, 0.5);
+        let spread = percentile(counts, total, 0.9) / percentile(counts, total, 0.1);
+        let depth_threshold = auto.depth_threshold * median / auto.reference_depth.max(0.001);
+        let depth_threshold_depth_mul = auto.depth_threshold_depth_mul * (spread / auto.reference_spread.max(1.0)).sqrt();
+
+        // Eases towards the new thresholds at the same rate whatever the frame rate
+        let t = 1.0 - (-auto.response.max(0.0) * time.delta_secs()).exp();
+        let depth_threshold = settings.depth_threshold.lerp(depth_threshold, t);
+        let depth_threshold_depth_mul = settings.depth_threshold_depth_mul.lerp(depth_threshold_depth_mul, t);
+        // Unchanged settings skip the uniform upload, so only write them when they move
+        if (depth_threshold, depth_threshold_depth_mul) != (settings.depth_threshold, settings.depth_threshold_depth_mul) {
+            settings.depth_threshold = depth_threshold;
+            settings.depth_threshold_depth_mul = depth_threshold_depth_mul;
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct SimpletoonDepthHistogramLabel;
+
+pub(crate) struct SimpletoonAutoThresholdsPlugin;
+
+impl Plugin for SimpletoonAutoThresholdsPlugin {
+    fn build(&self, app: &mut App) {
+        embedded_asset!(app, "assets/toon_depth_histogram.wgsl");
+        app.add_plugins(GpuHistogramPlugin::<ToonAutoThresholds>::default());
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .add_render_graph_node::<ViewNodeRunner<DepthHistogramNode>>(Core3d, SimpletoonDepthHistogramLabel)
+            .add_render_graph_edges(Core3d, (Node3d::EndPrepasses, SimpletoonDepthHistogramLabel, Node3d::StartMainPass));
+    }
+}
+
+/// Depth in metres below which `fraction` of the counted pixels lie, from the middle of its bin. A `fraction` of 0 is
+/// the nearest bin with any pixels in, and without any pixels it's the furthest bin.
+fn percentile(counts: &[u32], total: u32, fraction: f32) -> f32 {
+    let target = ((total as f32 * fraction).ceil() as u32).max(1);
+    let mut seen = 0;
+    let bin = counts
+        .iter()
+        .position(|count| {
+            seen += count;
+            seen >= target
+        })
+        .unwrap_or(counts.len().saturating_sub(1));
+    2f32.powf(MIN_OCTAVE + (bin as f32 + 0.5) / BINS_PER_OCTAVE)
+}
+
+#[derive(Default)]
+struct DepthHistogramNode;
+
+impl ViewNode for DepthHistogramNode {
+    type ViewQuery = (&'static HistogramBuffer<ToonAutoThresholds>, &'static ViewPrepassTextures, &'static ViewUniformOffset);
+
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (histogram, prepass_textures, view_offset): QueryItem<Self::ViewQuery>,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let histogram_pipeline = world.resource::<HistogramPipeline<ToonAutoThresholds>>();
+        let Some(pipeline) = histogram_pipeline.pipeline(world) else {
+            return Ok(());
+        };
+        let (Some(depth), Some(view_buffer), Some(buffer)) = (
+            prepass_textures.depth.as_ref(),
+            world.resource::<ViewUniforms>().uniforms.buffer(),
+            histogram.gpu_buffer(world),
+        ) else {
+            return Ok(());
+        };
+        if depth.texture.texture.sample_count() > 1 {
+            warn_once!("ToonAutoThresholds needs Msaa::Off on the camera, the thresholds are left as they are");
+            return Ok(());
+        }
+
+        let depth_view = &depth.texture.default_view;
+        let key = BindGroupKey::DepthHistogram {
+            depth: depth_view.id(),
+            view: view_buffer.id(),
+            histogram: buffer.id(),
+        };
+        let bind_group = world.resource::<SimpletoonBindGroupCache>().get_or_create(key, || {
+            world.resource::<RenderDevice>().create_bind_group(
+                "simpletoon_depth_histogram_bind_group",
+                &histogram_pipeline.layout,
+                &BindGroupEntries::sequential((
+                    depth_view,
+                    BufferBinding {
+                        buffer: view_buffer,
+                        offset: 0,
+                        size: Some(ViewUniform::min_size()),
+                    },
+                    buffer.as_entire_binding(),
+                )),
+            )
+        });
+
+        histogram_pipeline.dispatch(render_context, pipeline, &bind_group, &[view_offset.offset], buffer, depth.texture.texture.size());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bin_depth(bin: usize) -> f32 {
+        2f32.powf(MIN_OCTAVE + (bin as f32 + 0.5) / BINS_PER_OCTAVE)
+    }
+
+    #[test]
+    fn percentile_without_pixels() {
+        assert_eq!(percentile(&[], 0, 0.5), bin_depth(0));
+        assert_eq!(percentile(&[0; BINS], 0, 0.5), bin_depth(BINS - 1));
+        assert_eq!(percentile(&[0; BINS], 0, 0.0), bin_depth(BINS - 1));
+    }
+
+    #[test]
+    fn percentile_ends() {
+        let mut counts = [0; BINS];
+        counts[3] = 10;
+        counts[7] = 20;
+        counts[20] = 10;
+        assert_eq!(percentile(&counts, 40, 0.0), bin_depth(3));
+        assert_eq!(percentile(&counts, 40, 1.0), bin_depth(20));
+        assert_eq!(percentile(&counts, 40, 0.25), bin_depth(3));
+        assert_eq!(percentile(&counts, 40, 0.5), bin_depth(7));
+        assert_eq!(percentile(&counts, 40, 0.9), bin_depth(20));
+    }
+
+    #[test]
+    fn percentile_single_bin() {
+        let mut counts = [0; BINS];
+        counts[12] = 100;
+        for fraction in [0.0, 0.1, 0.5, 0.9, 1.0] {
+            assert_eq!(percentile(&counts, 100, fraction), bin_depth(12), "fraction {fraction}");
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use std::f32::consts::TAU;
+
+use bevy::{
+    asset::RenderAssetUsages,
+    ecs::query::QueryItem,
+    prelude::*,
+    render::{
+        camera::{RenderTarget, ScalingMode},
+        extract_component::ExtractComponent,
+        primitives::Aabb,
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+        view::screenshot::{Screenshot, ScreenshotCaptured},
+    },
+};
+
+use crate::plugin::SimpletoonSettings;
+use crate::screenshot::ToonTransparentBackground;
+
+/// Spawn to render `target` from `angles` directions around it with the toon effect, packed into a sprite sheet,
+/// e.g. for pre-rendered isometric sprites.
+///
+/// A camera orbits the target's bounds at `elevation`, one frame per angle starting from +Z and going counter-clockwise
+/// seen from above. Frames are laid out left to right, top to bottom, `columns` to a row, with a transparent background.
+/// When done the baker's camera is despawned and [`ToonBakeFinished`] is added to this entity.
+///
+/// The camera sees the whole scene, so bake models away from other geometry or give them their own lights.
+#[derive(Component, Clone)]
+pub struct ToonBaker {
+    pub target: Entity,
+    pub angles: u32,
+    pub frame_size: UVec2,
+    pub columns: u32,
+    /// Radians above the horizon the camera looks down from.
+    pub elevation: f32,
+    /// Space around the target's bounds, as a fraction of their size.
+    pub margin: f32,
+    /// Orthographic like most isometric games, or a perspective camera.
+    pub orthographic: bool,
+    /// Frames to render before the first capture, so the toon pipelines have compiled.
+    pub warmup_frames: u32,
+    pub settings: SimpletoonSettings,
+}
+
+impl ToonBaker {
+    pub fn new(target: Entity, angles: u32, frame_size: UVec2) -> Self {
+        Self {
+            target,
+            angles,
+            frame_size,
+            columns: angles.max(1),
+            elevation: 30f32.to_radians(),
+            margin: 0.1,
+            orthographic: true,
+            warmup_frames: 10,
+            settings: SimpletoonSettings::default(),
+        }
+    }
+
+    fn rows(&self) -> u32 {
+        self.angles.div_ceil(self.columns.max(1))
+    }
+}
+
+/// The sprite sheet a [`ToonBaker`] produced.
+#[derive(Component, Clone)]
+pub struct ToonBakeFinished {
+    pub image: Handle<Image>,
+    pub layout: Handle<TextureAtlasLayout>,
+}
+
+/// How far a [`ToonBaker`] has got.
+#[derive(Component)]
+pub(crate) struct ToonBakeProgress {
+    camera: Entity,
+    frame_image: Handle<Image>,
+    atlas: Handle<Image>,
+    /// Frames still to render before capturing.
+    warmup: u32,
+    /// The angle being captured, and whether its screenshot is still on its way.
+    next: u32,
+    pending: bool,
+}
+
+/// Has the baker's camera render with a transparent background, like a pending [`crate::screenshot::ToonScreenshot`].
+#[derive(Component, Clone, Copy)]
+pub(crate) struct ToonBakeCamera;
+
+impl ExtractComponent for ToonBakeCamera {
+    type QueryData = ();
+    type QueryFilter = ();
+    type Out = ToonTransparentBackground;
+
+    fn extract_component(_: QueryItem<'_, Self::QueryData>) -> Option<Self::Out> {
+        Some(ToonTransparentBackground)
+    }
+}
+
+fn new_image(size: UVec2, usage: TextureUsages) -> Image {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0; 4],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.texture_descriptor.usage = usage;
+    image
+}
+
+pub(crate) fn start_toon_bakers(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    bakers: Query<(Entity, &ToonBaker), Without<ToonBakeProgress>>,
+) {
+    for (entity, baker) in &bakers {
+        let frame_image = images.add(new_image(
+            baker.frame_size,
+            TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_SRC | TextureUsages::COPY_DST | TextureUsages::RENDER_ATTACHMENT,
+        ));
+        let atlas_size = baker.frame_size * UVec2::new(baker.columns.max(1), baker.rows());
+        let atlas = images.add(new_image(atlas_size, TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST));
+        let camera = commands
+            .spawn((
+                Camera3d::default(),
+                Camera {
+                    target: RenderTarget::Image(frame_image.clone().into()),
+                    clear_color: ClearColorConfig::Custom(Color::NONE),
+                    ..default()
+                },
+                Msaa::Off,
+                baker.settings,
+                ToonBakeCamera,
+            ))
+            .id();
+        commands.entity(entity).insert(ToonBakeProgress {
+            camera,
+            frame_image,
+            atlas,
+            warmup: baker.warmup_frames,
+            next: 0,
+            pending: false,
+        });
+    }
+}
+
+/// World-space bounds of everything under `root` with a mesh, as a centre and radius.
+fn bounds(root: Entity, children: &Query<&Children>, meshes: &Query<(&Aabb, &GlobalTransform)>) -> Option<(Vec3, f32)> {
+    let (mut min, mut max) = (Vec3::MAX, Vec3::MIN);
+    for entity in std::iter::once(root).chain(children.iter_descendants(root)) {
+        let Ok((aabb, transform)) = meshes.get(entity) else {
+            continue;
+        };
+        for corner in 0..8 {
+            let sign = Vec3::new((corner & 1) as f32, ((corner >> 1) & 1) as f32, ((corner >> 2) & 1) as f32) * 2.0 - 1.0;
+            let point = transform.transform_point(Vec3::from(aabb.center) + Vec3::from(aabb.half_extents) * sign);
+            (min, max) = (min.min(point), max.max(point));
+        }
+    }
+    (min.cmple(max).all()).then(|| ((min + max) * 0.5, (max - min).length() * 0.5))
+}
+
+pub(crate) fn run_toon_bakers(
+    mut commands: Commands,
+    mut bakers: Query<(Entity, &ToonBaker, &mut ToonBakeProgress), Without<ToonBakeFinished>>,
+    mut cameras: Query<(&mut Transform, &mut Projection)>,
+    children: Query<&Children>,
+    meshes: Query<(&Aabb, &GlobalTransform)>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+) {
+    for (entity, baker, mut progress) in &mut bakers {
+        if progress.pending {
+            continue;
+        }
+        if progress.next >= baker.angles {
+            commands.entity(progress.camera).despawn();
+            let layout = layouts.add(TextureAtlasLayout::from_grid(baker.frame_size, baker.columns.max(1), baker.rows(), None, None));
+            commands.entity(entity).insert(ToonBakeFinished { image: progress.atlas.clone(), layout });
+            continue;
+        }
+        let Ok((mut transform, mut projection)) = cameras.get_mut(progress.camera) else {
+            continue;
+        };
+        // Meshes get their bounds once they are loaded, until then keep waiting
+        let Some((centre, radius)) = bounds(baker.target, &children, &meshes) else {
+            continue;
+        };
+
+        let radius = radius * (1.0 + baker.margin);
+        let aspect = baker.frame_size.x as f32 / baker.frame_size.y.max(1) as f32;
+        let angle = progress.next as f32 / baker.angles as f32 * TAU;
+        let direction = Quat::from_rotation_y(angle) * Quat::from_rotation_x(-baker.elevation) * Vec3::Z;
+        let distance = if baker.orthographic {
+            *projection = Projection::Orthographic(OrthographicProjection {
+                scaling_mode: ScalingMode::Fixed { width: radius * 2.0 * aspect.max(1.0), height: radius * 2.0 / aspect.min(1.0) },
+                near: 0.0,
+                far: radius * 4.0,
+                ..OrthographicProjection::default_3d()
+            });
+            radius * 2.0
+        } else {
+            let fov = PerspectiveProjection::default().fov;
+            *projection = Projection::Perspective(PerspectiveProjection { fov, aspect_ratio: aspect, ..default() });
+            // Narrow frames are limited by the horizontal field of view instead
+            let half_fov = ((fov * 0.5).tan() * aspect.min(1.0)).atan();
+            radius / half_fov.sin()
+        };
+        *transform = Transform::from_translation(centre + direction * distance).looking_at(centre, Vec3::Y);
+
+        if progress.warmup > 0 {
+            progress.warmup -= 1;
+            continue;
+        }
+        progress.pending = true;
+        let (frame, columns) = (progress.next, baker.columns.max(1));
+        let (frame_size, atlas) = (baker.frame_size, progress.atlas.clone());
+        commands.spawn(Screenshot::image(progress.frame_image.clone())).observe(
+            move |trigger: Trigger<ScreenshotCaptured>, mut images: ResMut<Assets<Image>>, mut bakers: Query<&mut ToonBakeProgress>| {
+                let (Some(captured), Some(atlas)) = (trigger.0.data.as_ref(), images.get_mut(&atlas)) else {
+                    return;
+                };
+                let atlas_width = atlas.width() as usize;
+                let Some(data) = atlas.data.as_mut() else {
+                    return;
+                };
+                let cell = UVec2::new(frame % columns, frame / columns) * frame_size;
+                let row_bytes = frame_size.x as usize * 4;
+                for (y, row) in captured.chunks_exact(row_bytes).enumerate() {
+                    let start = ((cell.y as usize + y) * atlas_width + cell.x as usize) * 4;
+                    data[start..start + row_bytes].copy_from_slice(row);
+                }
+                if let Ok(mut progress) = bakers.get_mut(entity) {
+                    progress.next += 1;
+                    progress.pending = false;
+                }
+            },
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use bevy::{
+    asset::embedded_asset,
+    core_pipeline::core_3d::graph::{Core3d, Node3d},
+    ecs::{query::QueryItem, system::SystemParamItem},
+    prelude::*,
+    render::{
+        render_graph::{NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner},
+        render_resource::{
+            binding_types::{storage_buffer_sized, texture_2d},
+            *,
+        },
+        renderer::{RenderContext, RenderDevice},
+        view::ViewTarget,
+        RenderApp,
+    },
+};
+
+use crate::histogram::{GpuHistogram, GpuHistogramPlugin, HistogramBuffer, HistogramPipeline};
+use crate::plugin::{BindGroupKey, SimpletoonBindGroupCache, SimpletoonSettings};
+
+/// Bins are half a stop of luminance wide, from 2^MIN_STOP up, must match toon_luminance_histogram.wgsl.
+const BINS: usize = 32;
+const MIN_STOP: f32 = -10.0;
+const BINS_PER_STOP: f32 = 2.0;
+
+/// Add to a toon camera to move its colour band boundaries with the scene's average brightness, like auto exposure
+/// does for the picture, so bands don't crawl across every surface as the camera pans from a dark interior out into
+/// daylight.
+///
+/// A compute pass bins the scene's luminance into a histogram after the main pass, which is read back a frame or two
+/// later. The camera's `band_exposure` eases towards `(reference_luminance / average) ^ strength`, the average being
+/// the geometric mean of the histogram. Does nothing on adapters using the compatibility pipelines.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ToonBandAnchor {
+    /// Average luminance at which the bands are left where `colour_banding` puts them.
+    pub reference_luminance: f32,
+    /// How much of the change in brightness the bands follow, from 0 to 1.
+    pub strength: f32,
+    /// How quickly the bands follow the scene, higher is faster. 0 stops them moving.
+    pub response: f32,
+}
+
+impl Default for ToonBandAnchor {
+    fn default() -> Self {
+        Self {
+            reference_luminance: 0.18,
+            strength: 0.75,
+            response: 2.0,
+        }
+    }
+}
+
+impl GpuHistogram for ToonBandAnchor {
+    const NAME: &'static str = "luminance_histogram";
+    const BINS: usize = BINS;
+    const SHADER: &'static str = "embedded://bevy_simpletoon/assets/toon_luminance_histogram.wgsl";
+    const ENTRY_POINT: &'static str = "histogram_luminance";
+    type Param = (Query<'static, 'static, (&'static ToonBandAnchor, &'static mut SimpletoonSettings)>, Res<'static, Time>);
+
+    fn layout_entries() -> Vec<BindGroupLayoutEntry> {
+        BindGroupLayoutEntries::sequential(
+            ShaderStages::COMPUTE,
+            (texture_2d(TextureSampleType::Float { filterable: false }), storage_buffer_sized(false, None)),
+        )
+        .to_vec()
+    }
+
+    fn apply(camera: Entity, counts: &[u32], (cameras, time): &mut SystemParamItem<Self::Param>) {
+        let Ok((anchor, mut settings)) = cameras.get_mut(camera) else {
+            return;
+        };
+        let total: u32 = counts.iter().sum();
+        if total == 0 {
+            return;
+        }
+        let mean_stop = counts
+            .iter()
+            .enumerate()
+            .map(|(bin, count)| (MIN_STOP + (bin as f32 + 0.5) / BINS_PER_STOP) * *count as f32)
+            .sum::<f32>()
+            / total as f32;
+        let stops = (anchor.reference_luminance.max(1e-4).log2() - mean_stop) * anchor.strength.clamp(0.0, 1.0);
+        let band_exposure = 2f32.powf(stops.clamp(-4.0, 4.0));
+
+        // Eases in stops, at the same rate whatever the frame rate
+        let t = 1.0 - (-anchor.response.max(0.0) * time.delta_secs()).exp();
+        let band_exposure = 2f32.powf(settings.band_exposure.log2().lerp(band_exposure.log2(), t));
+        // Unchanged settings skip the uniform upload, so only write them when they move
+        if band_exposure != settings.band_exposure {
+            settings.band_exposure = band_exposure;
+        }
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct SimpletoonLuminanceHistogramLabel;
+
+pub(crate) struct SimpletoonBandAnchorPlugin;
+
+impl Plugin for SimpletoonBandAnchorPlugin {
+    fn build(&self, app: &mut App) {
+        embedded_asset!(app, "assets/toon_luminance_histogram.wgsl");
+        app.add_plugins(GpuHistogramPlugin::<ToonBandAnchor>::default());
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .add_render_graph_node::<ViewNodeRunner<LuminanceHistogramNode>>(Core3d, SimpletoonLuminanceHistogramLabel)
+            .add_render_graph_edges(Core3d, (Node3d::EndMainPass, SimpletoonLuminanceHistogramLabel, Node3d::Tonemapping));
+    }
+}
+
+#[derive(Default)]
+struct LuminanceHistogramNode;
+
+impl ViewNode for LuminanceHistogramNode {
+    type ViewQuery = (&'static HistogramBuffer<ToonBandAnchor>, &'static ViewTarget);
+
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (histogram, view_target): QueryItem<Self::ViewQuery>,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let histogram_pipeline = world.resource::<HistogramPipeline<ToonBandAnchor>>();
+        let (Some(pipeline), Some(buffer)) = (histogram_pipeline.pipeline(world), histogram.gpu_buffer(world)) else {
+            return Ok(());
+        };
+
+        let source = view_target.main_texture_view();
+        let key = BindGroupKey::LuminanceHistogram {
+            source: source.id(),
+            histogram: buffer.id(),
+        };
+        let bind_group = world.resource::<SimpletoonBindGroupCache>().get_or_create(key, || {
+            world.resource::<RenderDevice>().create_bind_group(
+                "simpletoon_luminance_histogram_bind_group",
+                &histogram_pipeline.layout,
+                &BindGroupEntries::sequential((source, buffer.as_entire_binding())),
+            )
+        });
+
+        histogram_pipeline.dispatch(render_context, pipeline, &bind_group, &[], buffer, view_target.main_texture().size());
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+use bevy::{
+    asset::embedded_asset,
+    core_pipeline::core_3d::graph::Core3d,
+    ecs::{query::QueryItem, system::SystemParamItem},
+    prelude::*,
+    render::{
+        render_graph::{NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner},
+        render_resource::{
+            binding_types::{storage_buffer_sized, texture_2d},
+            *,
+        },
+        renderer::{RenderContext, RenderDevice},
+        view::ViewTarget,
+        RenderApp,
+    },
+};
+
+use crate::histogram::{GpuHistogram, GpuHistogramPlugin, HistogramBuffer, HistogramPipeline};
+use crate::plugin::{BindGroupKey, SimpletoonBindGroupCache};
+
+/// Add to a toon camera to read back how its pixels spread across brightness once they have been banded, e.g. to
+/// see how many land in each band while tuning `colour_banding`, `banding_range` and `band_exposure`.
+///
+/// `counts` is filled in a frame or two later and kept up to date after that. Each of its [`ToonBandHistogram::BINS`]
+/// entries counts the pixels whose luminance falls in an equal slice of 0 to 1, anything brighter in the last one.
+/// Only every 4th pixel each way is counted. Does nothing with [`crate::plugin::SimpletoonPlugin::detached`], whose
+/// toon pass the histogram can't be ordered after, nor on adapters using the compatibility pipelines.
+#[derive(Component, Clone, Debug, Default)]
+pub struct ToonBandHistogram {
+    pub counts: Vec<u32>,
+}
+
+impl ToonBandHistogram {
+    pub const BINS: usize = 64;
+
+    /// The fraction of counted pixels in each bin, empty until the first readback.
+    pub fn fractions(&self) -> Vec<f32> {
+        let total = self.counts.iter().sum::<u32>().max(1) as f32;
+        self.counts.iter().map(|count| *count as f32 / total).collect()
+    }
+}
+
+impl GpuHistogram for ToonBandHistogram {
+    const NAME: &'static str = "band_histogram";
+    const BINS: usize = ToonBandHistogram::BINS;
+    const SHADER: &'static str = "embedded://bevy_simpletoon/assets/toon_band_histogram.wgsl";
+    const ENTRY_POINT: &'static str = "histogram_bands";
+    type Param = Query<'static, 'static, &'static mut ToonBandHistogram>;
+
+    fn layout_entries() -> Vec<BindGroupLayoutEntry> {
+        BindGroupLayoutEntries::sequential(
+            ShaderStages::COMPUTE,
+            (texture_2d(TextureSampleType::Float { filterable: false }), storage_buffer_sized(false, None)),
+        )
+        .to_vec()
+    }
+
+    fn apply(camera: Entity, counts: &[u32], histograms: &mut SystemParamItem<Self::Param>) {
+        if let Ok(mut histogram) = histograms.get_mut(camera) {
+            histogram.counts = counts.to_vec();
+        }
+    }
+}
+
+/// Counts the toon pass' output for [`ToonBandHistogram`], after [`crate::plugin::SimpletoonPostProcessLabel`].
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct SimpletoonBandHistogramLabel;
+
+pub(crate) struct SimpletoonBandHistogramPlugin {
+    /// Leaves the node out of `Core3d`, where nothing would order it after a detached toon pass.
+    pub(crate) detached: bool,
+}
+
+impl Plugin for SimpletoonBandHistogramPlugin {
+    fn build(&self, app: &mut App) {
+        embedded_asset!(app, "assets/toon_band_histogram.wgsl");
+        app.add_plugins(GpuHistogramPlugin::<ToonBandHistogram>::default());
+
+        if self.detached {
+            return;
+        }
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.add_render_graph_node::<ViewNodeRunner<BandHistogramNode>>(Core3d, SimpletoonBandHistogramLabel);
+    }
+}
+
+#[derive(Default)]
+struct BandHistogramNode;
+
+impl ViewNode for BandHistogramNode {
+    type ViewQuery = (&'static HistogramBuffer<ToonBandHistogram>, &'static ViewTarget);
+
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (histogram, view_target): QueryItem<Self::ViewQuery>,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let histogram_pipeline = world.resource::<HistogramPipeline<ToonBandHistogram>>();
+        let (Some(pipeline), Some(buffer)) = (histogram_pipeline.pipeline(world), histogram.gpu_buffer(world)) else {
+            return Ok(());
+        };
+
+        let source = view_target.main_texture_view();
+        let key = BindGroupKey::BandHistogram {
+            source: source.id(),
+            histogram: buffer.id(),
+        };
+        let bind_group = world.resource::<SimpletoonBindGroupCache>().get_or_create(key, || {
+            world.resource::<RenderDevice>().create_bind_group(
+                "simpletoon_band_histogram_bind_group",
+                &histogram_pipeline.layout,
+                &BindGroupEntries::sequential((source, buffer.as_entire_binding())),
+            )
+        });
+
+        histogram_pipeline.dispatch(render_context, pipeline, &bind_group, &[], buffer, view_target.main_texture().size());
+        Ok(())
+    }
+}
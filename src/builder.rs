@@ -0,0 +1,259 @@
+use bevy::{
+    prelude::*,
+    render::render_graph::{InternedRenderLabel, RenderLabel},
+};
+
+use crate::plugin::{SimpletoonPlugin, SimpletoonSettings, StrokeSpace, StrokeStyle};
+use crate::quality::ToonQuality;
+use crate::shader_defs::NormalEncoding;
+
+/// Where [`SimpletoonPluginBuilder::order`] puts the toon pass in the `Core3d` graph.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum ToonPassOrder {
+    /// Between tonemapping and FXAA, like [`SimpletoonPlugin::default`].
+    #[default]
+    AfterTonemapping,
+    /// Between bloom and `DepthOfField`, like [`SimpletoonPlugin::before_depth_of_field`].
+    BeforeDepthOfField,
+    /// Between the main pass and `VolumetricFog`, like [`SimpletoonPlugin::before_volumetric_fog`].
+    BeforeVolumetricFog,
+    /// After the first node and ahead of the second, like [`SimpletoonPlugin::with_edges`].
+    Between(InternedRenderLabel, InternedRenderLabel),
+    /// Not in the graph at all, like [`SimpletoonPlugin::detached`].
+    Detached,
+}
+
+impl ToonPassOrder {
+    /// [`ToonPassOrder::Between`] from any two render labels, e.g. `ToonPassOrder::between(Node3d::Tonemapping, MyCrtLabel)`.
+    pub fn between(before: impl RenderLabel, after: impl RenderLabel) -> Self {
+        Self::Between(before.intern(), after.intern())
+    }
+}
+
+/// Cargo features the app relies on, so [`SimpletoonPluginBuilder::build`] can fail loudly when one was left out of
+/// the dependency instead of the strokes or bands silently never showing up.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ToonFeatures {
+    pub outlines: bool,
+    pub banding: bool,
+}
+
+/// Builds a [`SimpletoonPlugin`], checking the options fit together when [`SimpletoonPluginBuilder::build`] is called.
+///
+/// ```ignore
+/// SimpletoonPlugin::builder()
+///     .order(ToonPassOrder::between(Node3d::Tonemapping, MyCrtLabel))
+///     .quality(ToonQuality::Medium)
+///     .features(ToonFeatures { outlines: true, ..default() })
+///     .build()
+/// ```
+#[derive(Default)]
+pub struct SimpletoonPluginBuilder {
+    order: ToonPassOrder,
+    quality: Option<ToonQuality>,
+    features: ToonFeatures,
+    label: Option<&'static str>,
+    precompile: Vec<(SimpletoonSettings, bool)>,
+    block_until_compiled: bool,
+    normal_encoding: NormalEncoding,
+    #[cfg(feature = "dev-hot-reload")]
+    shader_path: Option<&'static str>,
+}
+
+impl SimpletoonPlugin {
+    /// Starts a [`SimpletoonPluginBuilder`], for setting several options that are checked against each other.
+    pub fn builder() -> SimpletoonPluginBuilder {
+        SimpletoonPluginBuilder::default()
+    }
+}
+
+impl SimpletoonPluginBuilder {
+    /// Where the pass goes in the `Core3d` graph, between tonemapping and FXAA by default.
+    pub fn order(mut self, order: ToonPassOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// The [`ToonQuality`] the app starts with, instead of [`ToonQuality::Custom`].
+    pub fn quality(mut self, quality: ToonQuality) -> Self {
+        self.quality = Some(quality);
+        self
+    }
+
+    /// The cargo features the app needs, [`SimpletoonPluginBuilder::build`] panics if any is missing.
+    pub fn features(mut self, features: ToonFeatures) -> Self {
+        self.features = features;
+        self
+    }
+
+    /// Builds a [`SimpletoonPlugin::labeled`] pass instead.
+    pub fn labeled(mut self, label: &'static str) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    /// See [`SimpletoonPlugin::precompile`].
+    pub fn precompile(mut self, settings: &SimpletoonSettings, hdr: bool) -> Self {
+        self.precompile.push((*settings, hdr));
+        self
+    }
+
+    /// See [`SimpletoonPlugin::block_until_compiled`].
+    pub fn block_until_compiled(mut self) -> Self {
+        self.block_until_compiled = true;
+        self
+    }
+
+    /// See [`SimpletoonPlugin::normal_encoding`].
+    pub fn normal_encoding(mut self, encoding: NormalEncoding) -> Self {
+        self.normal_encoding = encoding;
+        self
+    }
+
+    /// See [`SimpletoonPlugin::shader_path`].
+    #[cfg(feature = "dev-hot-reload")]
+    pub fn shader_path(mut self, path: &'static str) -> Self {
+        self.shader_path = Some(path);
+        self
+    }
+
+    /// Panics if the options can't work together, or need a cargo feature `bevy_simpletoon` was built without.
+    pub fn build(self) -> SimpletoonPlugin {
+        if self.features.outlines && !cfg!(feature = "outlines") {
+            panic!("SimpletoonPlugin::builder() needs outlines, but bevy_simpletoon was built without its \"outlines\" feature");
+        }
+        if self.features.banding && !cfg!(feature = "banding") {
+            panic!("SimpletoonPlugin::builder() needs banding, but bevy_simpletoon was built without its \"banding\" feature");
+        }
+        if self.block_until_compiled && self.precompile.is_empty() {
+            panic!("SimpletoonPlugin::builder().block_until_compiled() has nothing to wait for without .precompile(..)");
+        }
+        if let ToonPassOrder::Between(before, after) = self.order
+            && before == after
+        {
+            panic!("SimpletoonPlugin::builder() can't run the pass both after and before {before:?}");
+        }
+        if let Some(label) = self.label {
+            // These are shared by every pass, so only the unlabeled plugin sets them up
+            let shared = [
+                (!self.precompile.is_empty(), "precompile(..)"),
+                (self.block_until_compiled, "block_until_compiled()"),
+                (self.quality.is_some(), "quality(..)"),
+                (self.normal_encoding != NormalEncoding::default(), "normal_encoding(..)"),
+                (matches!(self.order, ToonPassOrder::Detached), "order(ToonPassOrder::Detached)"),
+                (matches!(self.order, ToonPassOrder::BeforeDepthOfField), "order(ToonPassOrder::BeforeDepthOfField)"),
+                (matches!(self.order, ToonPassOrder::BeforeVolumetricFog), "order(ToonPassOrder::BeforeVolumetricFog)"),
+            ];
+            if let Some((_, option)) = shared.iter().find(|(set, _)| *set) {
+                panic!("SimpletoonPlugin::builder().labeled(\"{label}\") can't use {option}, set it on the plugin without a label");
+            }
+        }
+
+        let mut plugin = match self.order {
+            ToonPassOrder::AfterTonemapping => SimpletoonPlugin::default(),
+            ToonPassOrder::BeforeDepthOfField => SimpletoonPlugin::before_depth_of_field(),
+            ToonPassOrder::BeforeVolumetricFog => SimpletoonPlugin::before_volumetric_fog(),
+            ToonPassOrder::Between(before, after) => SimpletoonPlugin::with_edges(before, after),
+            ToonPassOrder::Detached => SimpletoonPlugin::detached(),
+        };
+        plugin.label = self.label;
+        plugin.quality = self.quality;
+        plugin.precompile = self.precompile;
+        plugin.block_until_compiled = self.block_until_compiled;
+        plugin.normal_encoding = self.normal_encoding;
+        #[cfg(feature = "dev-hot-reload")]
+        {
+            plugin.shader_path = self.shader_path;
+        }
+        plugin
+    }
+}
+
+/// Builds [`SimpletoonSettings`] in the units they're easiest to think in, starting from the defaults, e.g.
+/// `SimpletoonSettings::builder().bands(4).stroke_px(2.0).stroke_colour(Color::BLACK).build()`.
+///
+/// Band counts, pixels, degrees and percentages are converted to what the shader compares against, anything without a
+/// method here can still be set on the built settings.
+#[derive(Clone, Copy, Default)]
+pub struct SimpletoonSettingsBuilder(SimpletoonSettings);
+
+impl SimpletoonSettings {
+    pub fn builder() -> SimpletoonSettingsBuilder {
+        SimpletoonSettingsBuilder::default()
+    }
+}
+
+impl SimpletoonSettingsBuilder {
+    /// Starts from these settings instead of the defaults.
+    pub fn from_settings(settings: SimpletoonSettings) -> Self {
+        Self(settings)
+    }
+
+    /// How many colour bands the scene is split into.
+    pub fn bands(mut self, bands: u32) -> Self {
+        self.0.colour_banding = bands.max(1) as f32;
+        self
+    }
+
+    /// Strokes this many pixels wide wherever they are.
+    pub fn stroke_px(mut self, pixels: f32) -> Self {
+        self.0.stroke_size = pixels;
+        self.0.stroke_space = StrokeSpace::Screen;
+        self
+    }
+
+    /// Strokes about this many world units wide, so closer objects get thicker lines.
+    pub fn stroke_world(mut self, width: f32) -> Self {
+        self.0.stroke_size = width;
+        self.0.stroke_space = StrokeSpace::World;
+        self
+    }
+
+    pub fn stroke_colour(mut self, colour: impl Into<Srgba>) -> Self {
+        self.0.stroke_colour = colour.into();
+        self
+    }
+
+    /// Creases sharper than this many degrees are stroked.
+    pub fn crease_degrees(mut self, degrees: f32) -> Self {
+        // Prepass normals are stored halved, so two normals this far apart differ by sin(angle / 2)
+        self.0.normal_threshold = (degrees.to_radians() * 0.5).sin();
+        self
+    }
+
+    /// Depth edges get harsher on surfaces turned further than this many degrees from the camera.
+    pub fn grazing_degrees(mut self, degrees: f32) -> Self {
+        self.0.depth_normal_threshold = 1.0 - degrees.to_radians().cos();
+        self
+    }
+
+    /// How much of the toon result is shown, 0 leaves the scene untouched and 100 is the full effect.
+    pub fn strength_percent(mut self, percent: f32) -> Self {
+        self.0.effect_strength = percent / 100.0;
+        self
+    }
+
+    /// How much of the strokes is broken up by noise for a hand-inked look, 0 for solid strokes.
+    pub fn line_breakup_percent(mut self, percent: f32) -> Self {
+        self.0.line_breakup = percent / 100.0;
+        self
+    }
+
+    /// Draws dashed strokes with dashes and gaps this many pixels long.
+    pub fn dashes_px(mut self, length: f32, gap: f32) -> Self {
+        self.0.stroke_style = StrokeStyle::Dashed;
+        self.0.dash_length = length;
+        self.0.dash_gap = gap;
+        self
+    }
+
+    pub fn build(self) -> SimpletoonSettings {
+        self.0
+    }
+}
+
+impl From<SimpletoonSettingsBuilder> for SimpletoonSettings {
+    fn from(builder: SimpletoonSettingsBuilder) -> Self {
+        builder.build()
+    }
+}
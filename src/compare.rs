@@ -0,0 +1,28 @@
+use bevy::{ecs::query::QueryItem, prelude::*, render::extract_component::ExtractComponent};
+
+/// Insert on a toon camera to compare the toon effect with the scene underneath while tuning its settings: left of
+/// `split` is drawn with the effect and right of it without. `split` is a fraction of the viewport's width.
+///
+/// The split is made inside the toon pass, so unlike removing [`crate::plugin::SimpletoonSettings`] the camera keeps
+/// its pipelines and textures. Strokes drawn before bloom with `emissive_strokes` are already in the scene and show
+/// on both sides.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ToonCompare {
+    pub split: f32,
+}
+
+impl Default for ToonCompare {
+    fn default() -> Self {
+        Self { split: 0.5 }
+    }
+}
+
+impl ExtractComponent for ToonCompare {
+    type QueryData = &'static ToonCompare;
+    type QueryFilter = ();
+    type Out = ToonCompare;
+
+    fn extract_component(compare: QueryItem<'_, Self::QueryData>) -> Option<Self::Out> {
+        Some(*compare)
+    }
+}
@@ -0,0 +1,9 @@
+use bevy::{prelude::*, render::extract_component::ExtractComponent};
+
+/// The texture [`SimpletoonSettings::detail`](crate::plugin::SimpletoonSettings::detail) reads on this camera, red
+/// being how much detail to keep, so focal areas of the composition can be painted in.
+///
+/// It's sampled with filtering, so a small image blurred in a paint program does fine. Laid over the viewport or the
+/// world as the settings' [`DetailProjection`](crate::plugin::DetailProjection) says.
+#[derive(Component, Clone, ExtractComponent)]
+pub struct ToonDetailTexture(pub Handle<Image>);
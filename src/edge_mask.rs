@@ -0,0 +1,37 @@
+use bevy::{
+    asset::RenderAssetUsages,
+    prelude::*,
+    render::{
+        extract_component::ExtractComponent,
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+    },
+};
+
+/// Add to a toon camera to have its stroke mask copied into the wrapped image every frame, so other materials and plugins can sample it.
+///
+/// The mask is a single channel where 1.0 is a stroke. The image should be [`ToonEdgeMask::FORMAT`]
+/// and the size of the camera's render target, [`ToonEdgeMask::new_image`] creates a suitable one.
+/// Nothing is copied when the crate is built without the `outlines` feature.
+#[derive(Component, Clone, ExtractComponent)]
+pub struct ToonEdgeMask(pub Handle<Image>);
+
+impl ToonEdgeMask {
+    pub const FORMAT: TextureFormat = TextureFormat::R8Unorm;
+
+    /// An empty GPU-only image the edge mask can be copied into.
+    pub fn new_image(size: UVec2) -> Image {
+        let mut image = Image::new_uninit(
+            Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            Self::FORMAT,
+            RenderAssetUsages::RENDER_WORLD,
+        );
+        image.texture_descriptor.usage =
+            TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::COPY_SRC;
+        image
+    }
+}
@@ -0,0 +1,332 @@
+use bevy::{
+    ecs::entity::EntityHashMap,
+    prelude::*,
+    render::gpu_readback::{Readback, ReadbackComplete},
+};
+
+use crate::edge_mask::ToonEdgeMask;
+use crate::edge_query::ToonEdgeQuery;
+
+/// Add to a toon camera with a [`ToonEdgeMask`] to have its strokes read back and traced into polylines in [`ToonEdgePolylines`],
+/// e.g. to export frames as SVG line art or drive gameplay from silhouettes.
+///
+/// Strokes are thinned to their centre lines, chained into polylines and simplified with Douglas-Peucker.
+/// Tracing runs on the CPU as the readback arrives, a frame or two after rendering, so prefer
+/// [`ToonEdgeReadback::once`] over reading back every frame at high resolutions.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ToonEdgeReadback {
+    /// How far in pixels the simplified polylines may stray from the traced strokes.
+    pub tolerance: f32,
+    /// Keep reading back every frame, otherwise this component is removed after the first result and has to be added again.
+    pub every_frame: bool,
+}
+
+impl ToonEdgeReadback {
+    pub fn once(tolerance: f32) -> Self {
+        Self { tolerance, every_frame: false }
+    }
+
+    pub fn every_frame(tolerance: f32) -> Self {
+        Self { tolerance, every_frame: true }
+    }
+}
+
+/// The latest polylines traced for each camera with [`ToonEdgeReadback`].
+#[derive(Resource, Default)]
+pub struct ToonEdgePolylines {
+    cameras: EntityHashMap<TracedEdges>,
+}
+
+/// Polylines in pixels of the edge mask image, with the origin at its top left corner.
+#[derive(Clone, Default, Debug)]
+pub struct TracedEdges {
+    pub size: UVec2,
+    pub polylines: Vec<Vec<Vec2>>,
+}
+
+impl ToonEdgePolylines {
+    pub fn get(&self, camera: Entity) -> Option<&TracedEdges> {
+        self.cameras.get(&camera)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &TracedEdges)> {
+        self.cameras.iter().map(|(camera, edges)| (*camera, edges))
+    }
+}
+
+type NewReadback = (With<ToonEdgeReadback>, Or<(Added<ToonEdgeReadback>, Changed<ToonEdgeMask>)>);
+
+/// Reads back the edge mask of cameras that have just had [`ToonEdgeReadback`] added, or a new mask image.
+pub(crate) fn start_edge_readbacks(
+    mut commands: Commands,
+    cameras: Query<(Entity, &ToonEdgeMask), NewReadback>,
+    mut removed: RemovedComponents<ToonEdgeReadback>,
+    mut polylines: ResMut<ToonEdgePolylines>,
+    queries: Query<(), With<ToonEdgeQuery>>,
+) {
+    for (camera, mask) in &cameras {
+        commands.entity(camera).insert(Readback::texture(mask.0.clone()));
+    }
+    for camera in removed.read() {
+        match commands.get_entity(camera) {
+            // A ToonEdgeQuery shares the readback
+            Ok(mut entity) => {
+                if !queries.contains(camera) {
+                    entity.remove::<Readback>();
+                }
+            }
+            // A one-off readback keeps its result, only despawned cameras are forgotten
+            Err(_) => {
+                polylines.cameras.remove(&camera);
+            }
+        }
+    }
+}
+
+pub(crate) fn trace_edge_readback(
+    trigger: Trigger<ReadbackComplete>,
+    mut commands: Commands,
+    cameras: Query<(&ToonEdgeReadback, &ToonEdgeMask)>,
+    images: Res<Assets<Image>>,
+    mut polylines: ResMut<ToonEdgePolylines>,
+) {
+    let camera = trigger.target();
+    let Ok((readback, mask)) = cameras.get(camera) else {
+        return;
+    };
+    let Some(image) = images.get(&mask.0) else {
+        return;
+    };
+    let size = image.size();
+    if size.y == 0 {
+        return;
+    }
+    // Rows are padded to the copy alignment
+    let stride = trigger.0.len() / size.y as usize;
+    let mut strokes = vec![false; (size.x * size.y) as usize];
+    for (y, row) in trigger.0.chunks_exact(stride).enumerate() {
+        for (x, value) in row[..size.x as usize].iter().enumerate() {
+            strokes[y * size.x as usize + x] = *value > 127;
+        }
+    }
+
+    thin(&mut strokes, size);
+    let traced = trace(&mut strokes, size)
+        .into_iter()
+        .map(|polyline| simplify(&polyline, readback.tolerance))
+        .collect();
+    polylines.cameras.insert(camera, TracedEdges { size, polylines: traced });
+
+    if !readback.every_frame {
+        commands.entity(camera).remove::<ToonEdgeReadback>();
+    }
+}
+
+/// The 8 neighbours of a pixel, clockwise from the one above.
+const NEIGHBOURS: [IVec2; 8] = [
+    IVec2::new(0, -1),
+    IVec2::new(1, -1),
+    IVec2::new(1, 0),
+    IVec2::new(1, 1),
+    IVec2::new(0, 1),
+    IVec2::new(-1, 1),
+    IVec2::new(-1, 0),
+    IVec2::new(-1, -1),
+];
+
+fn pixel(pixels: &[bool], size: UVec2, pos: IVec2) -> bool {
+    pos.cmpge(IVec2::ZERO).all() && pos.cmplt(size.as_ivec2()).all() && pixels[(pos.y * size.x as i32 + pos.x) as usize]
+}
+
+/// Zhang-Suen thinning, strips stroke pixels from the outside in until only 1px centre lines are left.
+fn thin(pixels: &mut [bool], size: UVec2) {
+    let mut removed = Vec::new();
+    loop {
+        let mut changed = false;
+        for step in 0..2 {
+            for y in 0..size.y as i32 {
+                for x in 0..size.x as i32 {
+                    let pos = IVec2::new(x, y);
+                    if !pixel(pixels, size, pos) {
+                        continue;
+                    }
+                    let n = NEIGHBOURS.map(|offset| pixel(pixels, size, pos + offset));
+                    let count = n.iter().filter(|set| **set).count();
+                    let transitions = (0..8).filter(|i| !n[*i] && n[(i + 1) % 8]).count();
+                    let (a, b) = match step {
+                        0 => (n[0] && n[2] && n[4], n[2] && n[4] && n[6]),
+                        _ => (n[0] && n[2] && n[6], n[0] && n[4] && n[6]),
+                    };
+                    if (2..=6).contains(&count) && transitions == 1 && !a && !b {
+                        removed.push((y * size.x as i32 + x) as usize);
+                    }
+                }
+            }
+            changed |= !removed.is_empty();
+            for index in removed.drain(..) {
+                pixels[index] = false;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+}
+
+/// Chains the thinned pixels into polylines, starting from line ends so open strokes aren't split in the middle.
+fn trace(pixels: &mut [bool], size: UVec2) -> Vec<Vec<Vec2>> {
+    let neighbour_count = |pixels: &[bool], pos: IVec2| NEIGHBOURS.iter().filter(|offset| pixel(pixels, size, pos + **offset)).count();
+    let positions = (0..size.y as i32).flat_map(|y| (0..size.x as i32).map(move |x| IVec2::new(x, y)));
+    let mut starts: Vec<IVec2> = positions.clone().filter(|pos| pixel(pixels, size, *pos) && neighbour_count(pixels, *pos) == 1).collect();
+    // Closed loops have no ends, any of their pixels will do
+    starts.extend(positions.filter(|pos| pixel(pixels, size, *pos)));
+
+    let skeleton = pixels.to_vec();
+    // Where a stroke runs into one that was already traced, join it to that one instead of leaving a 1px gap
+    let junction = |polyline: &[Vec2], pos: IVec2| {
+        NEIGHBOURS
+            .iter()
+            .map(|offset| pos + *offset)
+            .find(|next| pixel(&skeleton, size, *next) && !polyline.iter().rev().take(3).any(|point| *point == next.as_vec2() + 0.5))
+            .map(|next| next.as_vec2() + 0.5)
+    };
+
+    let mut polylines = Vec::new();
+    for start in starts {
+        if !pixel(pixels, size, start) {
+            continue;
+        }
+        let mut polyline = Vec::new();
+        let mut pos = start;
+        loop {
+            pixels[(pos.y * size.x as i32 + pos.x) as usize] = false;
+            polyline.push(pos.as_vec2() + 0.5);
+            // Straight neighbours first, so corners aren't cut
+            let next = [0, 2, 4, 6, 1, 3, 5, 7].into_iter().map(|i| pos + NEIGHBOURS[i]).find(|next| pixel(pixels, size, *next));
+            match next {
+                Some(next) => pos = next,
+                None => break,
+            }
+        }
+        // A loop is closed by joining its end back to the start, joining the start as well would trace a segment twice
+        let end = junction(&polyline, pos);
+        let closed = end == Some(start.as_vec2() + 0.5);
+        polyline.extend(end);
+        polyline.reverse();
+        if !closed {
+            polyline.extend(junction(&polyline, start));
+        }
+        if polyline.len() > 1 {
+            polylines.push(polyline);
+        }
+    }
+    polylines
+}
+
+/// Douglas-Peucker, drops points closer than `tolerance` to the line between the points kept around them.
+fn simplify(points: &[Vec2], tolerance: f32) -> Vec<Vec2> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+    let (first, last) = (points[0], points[points.len() - 1]);
+    let line = last - first;
+    let distance = |point: Vec2| match line.try_normalize() {
+        Some(direction) => (point - first).perp_dot(direction).abs(),
+        None => point.distance(first),
+    };
+    let (index, furthest) = points[1..points.len() - 1]
+        .iter()
+        .enumerate()
+        .map(|(i, point)| (i + 1, distance(*point)))
+        .fold((0, 0.0), |best, candidate| if candidate.1 > best.1 { candidate } else { best });
+    if furthest <= tolerance {
+        return vec![first, last];
+    }
+    let mut simplified = simplify(&points[..=index], tolerance);
+    simplified.pop();
+    simplified.extend(simplify(&points[index..], tolerance));
+    simplified
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stroke pixels from rows of `#` and `.`.
+    fn mask(rows: &[&str]) -> (Vec<bool>, UVec2) {
+        let size = UVec2::new(rows[0].len() as u32, rows.len() as u32);
+        (rows.iter().flat_map(|row| row.chars().map(|c| c == '#')).collect(), size)
+    }
+
+    fn centre(x: i32, y: i32) -> Vec2 {
+        IVec2::new(x, y).as_vec2() + 0.5
+    }
+
+    #[test]
+    fn one_pixel_line() {
+        let (mut pixels, size) = mask(&["........", ".######.", "........"]);
+        let before = pixels.clone();
+        thin(&mut pixels, size);
+        assert_eq!(pixels, before, "a line that is already 1px wide was thinned");
+
+        let polylines = trace(&mut pixels, size);
+        assert_eq!(polylines.len(), 1);
+        let polyline = &polylines[0];
+        assert_eq!(polyline.len(), 6);
+        let ends = [polyline[0], polyline[5]];
+        assert!(ends.contains(&centre(1, 1)) && ends.contains(&centre(6, 1)), "{polyline:?}");
+        assert!(pixels.iter().all(|set| !set), "traced pixels are cleared");
+    }
+
+    #[test]
+    fn thick_line_thins_to_one_pixel() {
+        let (mut pixels, size) = mask(&["..........", ".########.", ".########.", ".########.", ".........."]);
+        thin(&mut pixels, size);
+        for x in 3..7 {
+            let column = (0..size.y).filter(|y| pixels[(y * size.x + x) as usize]).count();
+            assert_eq!(column, 1, "column {x} is {column}px wide");
+        }
+    }
+
+    #[test]
+    fn l_shape() {
+        let (mut pixels, size) = mask(&[".......", ".#.....", ".#.....", ".#.....", ".#####.", "......."]);
+        let polylines = trace(&mut pixels, size);
+        assert_eq!(polylines.len(), 1, "{polylines:?}");
+        assert_eq!(polylines[0].len(), 8);
+
+        // Both legs are straight, only the corner is left between the ends
+        let simplified = simplify(&polylines[0], 0.5);
+        assert_eq!(simplified.len(), 3, "{simplified:?}");
+        assert_eq!(simplified[1], centre(1, 4));
+        assert!([simplified[0], simplified[2]].contains(&centre(1, 1)));
+        assert!([simplified[0], simplified[2]].contains(&centre(5, 4)));
+    }
+
+    #[test]
+    fn closed_loop() {
+        let (mut pixels, size) = mask(&[".......", ".#####.", ".#...#.", ".#...#.", ".#...#.", ".#####.", "......."]);
+        let ring = pixels.iter().filter(|set| **set).count();
+        let polylines = trace(&mut pixels, size);
+        assert_eq!(polylines.len(), 1, "a loop has no ends to split it at: {polylines:?}");
+        let polyline = &polylines[0];
+        for y in 1..6 {
+            for x in 1..6 {
+                if x == 1 || x == 5 || y == 1 || y == 5 {
+                    assert!(polyline.contains(&centre(x, y)), "({x}, {y}) is missing from {polyline:?}");
+                }
+            }
+        }
+        assert!(polyline.len() > ring, "the loop isn't closed: {polyline:?}");
+        assert_eq!(polyline.first(), polyline.last(), "{polyline:?}");
+    }
+
+    #[test]
+    fn simplify_tolerance() {
+        let bump = [Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.4), Vec2::new(2.0, 0.0)];
+        assert_eq!(simplify(&bump, 0.5), vec![bump[0], bump[2]]);
+        assert_eq!(simplify(&bump, 0.3), bump.to_vec());
+        assert_eq!(simplify(&bump, 0.4), vec![bump[0], bump[2]], "points exactly at the tolerance are dropped");
+        assert_eq!(simplify(&bump[..2], 10.0), bump[..2].to_vec(), "two points are always kept");
+    }
+}
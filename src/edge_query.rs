@@ -0,0 +1,100 @@
+use bevy::{
+    prelude::*,
+    render::gpu_readback::{Readback, ReadbackComplete},
+};
+
+use crate::edge_mask::ToonEdgeMask;
+use crate::edge_polylines::ToonEdgeReadback;
+
+/// Add to a toon camera with a [`ToonEdgeMask`] to keep a copy of its strokes in the main world, so gameplay code can
+/// ask whether a point on screen is on an outline, e.g. to snap a drawing cursor to the lines.
+///
+/// The mask is read back every frame and arrives a frame or two after it was rendered, [`ToonEdgeQuery::sample`]
+/// returns `None` until the first one has. Positions are in pixels of the edge mask image, with the origin at its top
+/// left corner, so scale window cursor positions by the window's scale factor first.
+#[derive(Component, Clone, Default, Debug)]
+pub struct ToonEdgeQuery {
+    size: UVec2,
+    strokes: Vec<bool>,
+}
+
+impl ToonEdgeQuery {
+    /// Whether `screen_pos` is on a stroke, `None` before the first readback or outside the mask.
+    pub fn sample(&self, screen_pos: Vec2) -> Option<bool> {
+        let pos = screen_pos.floor().as_ivec2();
+        if pos.cmplt(IVec2::ZERO).any() || pos.cmpge(self.size.as_ivec2()).any() {
+            return None;
+        }
+        Some(self.strokes[(pos.y * self.size.x as i32 + pos.x) as usize])
+    }
+
+    /// The centre of the stroke pixel nearest to `screen_pos` within `radius` pixels, for snapping to the lines.
+    pub fn nearest(&self, screen_pos: Vec2, radius: f32) -> Option<Vec2> {
+        let min = (screen_pos - radius).floor().as_ivec2().max(IVec2::ZERO);
+        let max = (screen_pos + radius).ceil().as_ivec2().min(self.size.as_ivec2() - 1);
+        let mut nearest = None;
+        let mut nearest_distance = radius * radius;
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let centre = Vec2::new(x as f32, y as f32) + 0.5;
+                let distance = centre.distance_squared(screen_pos);
+                if distance <= nearest_distance && self.strokes[(y * self.size.x as i32 + x) as usize] {
+                    nearest = Some(centre);
+                    nearest_distance = distance;
+                }
+            }
+        }
+        nearest
+    }
+
+    /// The size of the mask the latest readback came from, zero before the first.
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+}
+
+type NewQuery = (With<ToonEdgeQuery>, Or<(Added<ToonEdgeQuery>, Changed<ToonEdgeMask>)>);
+
+/// Starts reading back the edge mask of cameras that have just had [`ToonEdgeQuery`] added, or a new mask image.
+pub(crate) fn start_edge_queries(
+    mut commands: Commands,
+    cameras: Query<(Entity, &ToonEdgeMask), NewQuery>,
+    mut removed: RemovedComponents<ToonEdgeQuery>,
+    readbacks: Query<(), With<ToonEdgeReadback>>,
+) {
+    for (camera, mask) in &cameras {
+        commands.entity(camera).insert(Readback::texture(mask.0.clone()));
+    }
+    for camera in removed.read() {
+        // The polyline tracing shares the readback
+        if let Ok(mut entity) = commands.get_entity(camera)
+            && !readbacks.contains(camera)
+        {
+            entity.remove::<Readback>();
+        }
+    }
+}
+
+pub(crate) fn store_edge_query_readback(
+    trigger: Trigger<ReadbackComplete>,
+    mut cameras: Query<(&mut ToonEdgeQuery, &ToonEdgeMask)>,
+    images: Res<Assets<Image>>,
+) {
+    let Ok((mut query, mask)) = cameras.get_mut(trigger.target()) else {
+        return;
+    };
+    let Some(image) = images.get(&mask.0) else {
+        return;
+    };
+    let size = image.size();
+    if size.y == 0 {
+        return;
+    }
+    // Rows are padded to the copy alignment
+    let stride = trigger.0.len() / size.y as usize;
+    query.strokes.clear();
+    for row in trigger.0.chunks_exact(stride) {
+        query.strokes.extend(row[..size.x as usize].iter().map(|value| *value > 127));
+    }
+    query.size = size;
+}
@@ -0,0 +1,921 @@
+use std::ops::Range;
+
+use bevy::{
+    asset::embedded_asset,
+    core_pipeline::{
+        core_3d::{
+            graph::{Core3d, Node3d},
+            CORE_3D_DEPTH_FORMAT,
+        },
+        oit::OrderIndependentTransparencySettings,
+        prepass::{DeferredPrepass, DepthPrepass, MotionVectorPrepass, NormalPrepass},
+    },
+    ecs::{
+        query::{QueryItem, ROQueryItem},
+        system::{
+            lifetimeless::{Read, SRes},
+            SystemParamItem,
+        },
+    },
+    math::FloatOrd,
+    pbr::{
+        wireframe::Mesh3dWireframe,
+        DrawMesh, MeshPipeline, MeshPipelineKey, RenderMeshInstanceFlags, RenderMeshInstances,
+        SetMeshBindGroup, SetMeshViewBindGroup,
+    },
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+    render::{
+        camera::ExtractedCamera,
+        extract_component::{ExtractComponent, ExtractComponentPlugin},
+        mesh::{MeshVertexBufferLayoutRef, RenderMesh},
+        render_asset::RenderAssets,
+        render_graph::{
+            NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner,
+        },
+        render_phase::{
+            sort_phase_system, AddRenderCommand, CachedRenderPipelinePhaseItem, DrawFunctionId,
+            DrawFunctions, PhaseItem, PhaseItemExtraIndex, RenderCommand, RenderCommandResult,
+            SetItemPipeline, SortedPhaseItem, SortedRenderPhasePlugin, TrackedRenderPass,
+            ViewSortedRenderPhases,
+        },
+        render_resource::{binding_types::uniform_buffer, *},
+        renderer::{RenderContext, RenderDevice, RenderQueue},
+        sync_world::MainEntity,
+        texture::CachedTexture,
+        primitives::Aabb,
+        view::{ExtractedView, RenderVisibleEntities, RetainedViewEntity, ViewDepthTexture},
+        Extract, Render, RenderApp, RenderDebugFlags, RenderSet,
+    },
+};
+
+use crate::plugin::{PostProcessPipeline, SimpletoonSettings, SimpletoonSystems};
+use crate::texture_pool::ToonTexturePool;
+
+/// Keeps smooth colours on this mesh while the rest of the scene is banded, e.g. for in-world screens, text and photos.
+///
+/// Needs `Msaa::Off` on the camera, and does nothing on adapters using the compatibility pipelines (see the README).
+#[derive(Component, Clone, Copy, Default, ExtractComponent)]
+pub struct NoToonBanding;
+
+/// Gives this mesh an ID of its own, so `SimpletoonSettings::id_edges` strokes where it meets other meshes even
+/// when it lies flush with them, like a poster on a wall or a decal. Meshes with any other override have an ID too.
+///
+/// Has the same requirements as [`NoToonBanding`].
+#[derive(Component, Clone, Copy, Default, ExtractComponent)]
+pub struct ToonOutlineId;
+
+/// Leaves this mesh exactly as it was rendered, without banding, strokes or any other toon effect drawn over it,
+/// e.g. for character eyes or emissive screens that look dead once banded.
+///
+/// Strokes along its outline are still drawn on the meshes around it. Has the same requirements as [`NoToonBanding`].
+#[derive(Component, Clone, Copy, Default, ExtractComponent)]
+pub struct ToonPassThrough;
+
+/// Bands this mesh's colours into its own number of bands instead of `SimpletoonSettings::colour_banding`, e.g. 2 for
+/// classic cel shaded characters in a more finely banded environment.
+///
+/// Has the same requirements as [`NoToonBanding`], which wins if a mesh has both.
+#[derive(Component, Clone, Copy, ExtractComponent)]
+pub struct ToonBands(pub u32);
+
+/// Draws the anime style highlight band across this mesh, for hair.
+///
+/// The band is a strand highlight lit by the [`crate::key_light::ToonKeyLight`], with strands taken to run down the
+/// mesh since the post process has no mesh tangents. `shift` moves the band along the strands, `width` (0-1) sets
+/// how wide it is and `strength` how much of `colour` is mixed in. Needs the `outlines` feature for normals, and
+/// has the same requirements as [`NoToonBanding`].
+#[derive(Component, Clone, Copy, ExtractComponent)]
+pub struct ToonHair {
+    pub colour: Color,
+    pub strength: f32,
+    pub shift: f32,
+    pub width: f32,
+}
+
+impl Default for ToonHair {
+    fn default() -> Self {
+        Self {
+            colour: Color::WHITE,
+            strength: 0.8,
+            shift: 0.0,
+            width: 0.05,
+        }
+    }
+}
+
+/// Added to `SimpletoonSettings::normal_threshold` on this mesh, so animated characters whose normals shift
+/// from frame to frame can use a looser threshold than static geometry.
+///
+/// Has the same requirements as [`NoToonBanding`].
+#[derive(Component, Clone, Copy, Default, ExtractComponent)]
+pub struct ToonNormalThresholdBias(pub f32);
+
+/// Draws this mesh's strokes dashed, `length` and `gap` in pixels, e.g. so teams can be told apart without relying
+/// on stroke colour. Strokes are dashed along the screen-space direction they run in.
+///
+/// Has the same requirements as [`NoToonBanding`].
+#[derive(Component, Clone, Copy, ExtractComponent)]
+pub struct ToonStrokeDashes {
+    pub length: f32,
+    pub gap: f32,
+}
+
+/// Skips colour edges on this mesh, leaving depth and normal edges to outline it.
+///
+/// Meshes drawn as wireframes by bevy's `WireframePlugin` get this automatically, either through `Wireframe` or
+/// `WireframeConfig::global`, since their lines would otherwise be picked up as colour edges all over the mesh.
+/// Has the same requirements as [`NoToonBanding`].
+#[derive(Component, Clone, Copy, Default)]
+pub struct NoToonColourEdges;
+
+impl ExtractComponent for NoToonColourEdges {
+    type QueryData = ();
+    type QueryFilter = Or<(With<NoToonColourEdges>, With<Mesh3dWireframe>)>;
+    type Out = Self;
+
+    fn extract_component(_: QueryItem<'_, Self::QueryData>) -> Option<Self::Out> {
+        Some(Self)
+    }
+}
+
+/// Draws meshes with per-entity toon overrides into the entity ID texture, after the opaque pass so it can depth test against it.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct SimpletoonEntityIdLabel;
+
+pub(crate) const ENTITY_ID_FORMAT: TextureFormat = TextureFormat::R32Uint;
+
+/// Overrides for one entity, as the post process reads them from its storage buffer.
+#[derive(Clone, Copy, Default, ShaderType)]
+pub(crate) struct ToonOverride {
+    flags: u32,
+    normal_threshold_bias: f32,
+    /// Dash length and gap in pixels, zero for solid strokes.
+    dashes: Vec2,
+    /// Colour bands, zero for the camera's.
+    bands: f32,
+    /// Hair highlight shift and width.
+    hair_band: Vec2,
+    /// Hair highlight colour with its strength in alpha, zero for meshes that aren't hair.
+    hair_colour: Vec4,
+    /// Stroke colour, only used with [`ToonOverride::STROKE_COLOUR`].
+    stroke_colour: Vec4,
+    /// Stroke width in pixels, zero for the camera's.
+    stroke_size: f32,
+    /// World space bounding sphere for [`ToonOutlineLod`], a zero radius for meshes without one.
+    lod_bounds: Vec4,
+    /// [`ToonOutlineLod::min_size`] and [`ToonOutlineLod::fade`].
+    lod_size: Vec2,
+}
+
+impl ToonOverride {
+    const NO_BANDING: u32 = 1;
+    const NO_COLOUR_EDGES: u32 = 2;
+    const PASS_THROUGH: u32 = 4;
+    const DEPTH_EDGES_ONLY: u32 = 8;
+    const SILHOUETTE_ONLY: u32 = 16;
+    const TERRAIN: u32 = 32;
+    const DECAL: u32 = 64;
+    const STROKE_COLOUR: u32 = 128;
+    const IMPOSTER: u32 = 256;
+    const HULL: u32 = 512;
+}
+
+/// The ID a mesh writes into the entity ID texture, 0 is left for pixels without overrides.
+#[derive(Clone, Copy, ShaderType)]
+struct EntityIdUniform {
+    id: u32,
+}
+
+/// Render entities that get an ID.
+type WithOverrides = Or<(
+    With<NoToonBanding>,
+    With<ToonNormalThresholdBias>,
+    With<NoToonColourEdges>,
+    With<ToonStrokeDashes>,
+    With<ToonBands>,
+    With<ToonHair>,
+    With<ToonPassThrough>,
+    With<ToonOutlineId>,
+    With<FoliageEdgeMode>,
+    With<ToonTerrain>,
+    With<ExtractedToonDecal>,
+    With<ToonOverrides>,
+    With<ExtractedOutlineLod>,
+    With<ToonImposter>,
+    With<ToonHullOutline>,
+)>;
+
+/// Keeps alpha-cutout foliage from turning into a storm of tiny strokes, by dropping the normal and colour edges
+/// its leaf cards produce.
+///
+/// Has the same requirements as [`NoToonBanding`].
+#[derive(Component, Clone, Copy, Default, PartialEq, Eq, Debug, ExtractComponent)]
+pub enum FoliageEdgeMode {
+    /// Only depth edges where the mesh meets something else, so a tree is outlined as one shape.
+    #[default]
+    SilhouetteOnly,
+    /// Depth edges anywhere on the mesh, including between its own leaf cards.
+    DepthOnly,
+}
+
+/// Outlines this billboard or imposter quad along its alpha cutout instead of the whole quad, so billboard trees and
+/// crowd imposters get strokes like the meshes around them. Only the texels its material kept are given its ID, and
+/// strokes are drawn wherever that ID meets another, including where the quad stands on the ground.
+///
+/// The material needs `AlphaMode::Mask`, blended quads don't write the depth the kept texels are found by. Has the
+/// same requirements as [`NoToonBanding`].
+#[derive(Component, Clone, Copy, Default, ExtractComponent)]
+pub struct ToonImposter;
+
+/// Marks the inverted hull mesh of a hull outline, so the post process leaves the outline to it instead of drawing a
+/// second, screen-space line alongside. No strokes are drawn on or next to the hull's pixels, and it keeps the colour
+/// it was drawn with rather than being banded.
+///
+/// Put it on the hull, not the mesh it outlines, whose interior creases are still stroked. The hull has to be drawn
+/// with its front faces culled, `cull_mode: Some(Face::Front)` on its material, as its ID is drawn that way and only
+/// where the opaque pass kept the hull, so just the visible rim is marked. Has the same requirements as
+/// [`NoToonBanding`].
+#[derive(Component, Clone, Copy, Default, ExtractComponent)]
+pub struct ToonHullOutline;
+
+/// Tints this mesh by height and slope with the camera's `SimpletoonSettings::terrain` bands, for terrain.
+///
+/// Has the same requirements as [`NoToonBanding`].
+#[derive(Component, Clone, Copy, Default, ExtractComponent)]
+pub struct ToonTerrain;
+
+/// Marks a bevy `ForwardDecal`, so the border of its alpha fade isn't picked up as a colour edge. Parent the decal
+/// to the mesh it is stuck on and it is banded like that mesh, with its [`ToonBands`] or [`NoToonBanding`].
+///
+/// Has the same requirements as [`NoToonBanding`].
+#[derive(Component, Clone, Copy, Default)]
+pub struct ToonDecal;
+
+/// The render world side of [`ToonDecal`].
+#[derive(Component, Clone, Copy)]
+pub struct ExtractedToonDecal {
+    /// The main world entity the decal is parented to.
+    surface: Option<Entity>,
+}
+
+impl ExtractComponent for ToonDecal {
+    type QueryData = Option<&'static ChildOf>;
+    type QueryFilter = With<ToonDecal>;
+    type Out = ExtractedToonDecal;
+
+    fn extract_component(child_of: QueryItem<'_, Self::QueryData>) -> Option<Self::Out> {
+        Some(ExtractedToonDecal {
+            surface: child_of.map(ChildOf::parent),
+        })
+    }
+}
+
+/// Every per-entity override in one component, for stroke colours and widths or for tools that build overrides from
+/// data. Components like [`ToonBands`] and [`NoToonBanding`] are shorthands for its fields and win where a mesh has
+/// both.
+///
+/// Whichever way they are set, a mesh's overrides are written to one storage buffer at the ID the mesh draws into
+/// the entity ID texture, where the post process looks them up per pixel. Has the same requirements as
+/// [`NoToonBanding`].
+#[derive(Component, Clone, Copy, Default, Debug, ExtractComponent)]
+pub struct ToonOverrides {
+    /// Replaces `SimpletoonSettings::stroke_colour` for strokes drawn over this mesh.
+    pub stroke_colour: Option<Color>,
+    /// Replaces `SimpletoonSettings::stroke_size` for strokes drawn over this mesh, always in pixels.
+    pub stroke_size: Option<f32>,
+    /// Like [`ToonBands`].
+    pub bands: Option<u32>,
+    /// Like [`NoToonBanding`].
+    pub no_banding: bool,
+    /// Like [`NoToonColourEdges`].
+    pub no_colour_edges: bool,
+    /// Like [`ToonPassThrough`].
+    pub pass_through: bool,
+}
+
+/// Fades this mesh's strokes out as it gets smaller on screen, so small distant props don't turn into solid blobs of
+/// stroke colour. Strokes are gone once the mesh's bounding sphere is `min_size` pixels across, and fully drawn again
+/// from `min_size + fade` pixels.
+///
+/// Has the same requirements as [`NoToonBanding`].
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ToonOutlineLod {
+    /// Screen size in pixels, across the bounding sphere, at and below which the mesh has no strokes.
+    pub min_size: f32,
+    /// How many pixels of screen size past `min_size` the strokes take to fade back in, 0 switches them on at once.
+    pub fade: f32,
+}
+
+impl Default for ToonOutlineLod {
+    fn default() -> Self {
+        Self { min_size: 12.0, fade: 12.0 }
+    }
+}
+
+/// The render world side of [`ToonOutlineLod`], with the mesh's bounding sphere in world space.
+#[derive(Component, Clone, Copy)]
+pub struct ExtractedOutlineLod {
+    lod: ToonOutlineLod,
+    bounds: Vec4,
+}
+
+impl ExtractComponent for ToonOutlineLod {
+    type QueryData = (&'static ToonOutlineLod, &'static Aabb, &'static GlobalTransform);
+    type QueryFilter = ();
+    type Out = ExtractedOutlineLod;
+
+    fn extract_component((lod, aabb, transform): QueryItem<'_, Self::QueryData>) -> Option<Self::Out> {
+        let radius = Vec3::from(aabb.half_extents).length() * transform.scale().abs().max_element();
+        Some(ExtractedOutlineLod {
+            lod: *lod,
+            bounds: transform.transform_point(aabb.center.into()).extend(radius),
+        })
+    }
+}
+
+/// Offset of an entity's [`EntityIdUniform`].
+#[derive(Component)]
+struct EntityIdOffset(u32);
+
+/// Per-entity overrides for every mesh that has any, along with what the post process binds when there are none.
+#[derive(Resource)]
+pub(crate) struct SimpletoonEntityOverrides {
+    ids: DynamicUniformBuffer<EntityIdUniform>,
+    id_bind_group: Option<BindGroup>,
+    pub(crate) overrides: StorageBuffer<Vec<ToonOverride>>,
+    pub(crate) fallback_texture: TextureView,
+}
+
+impl SimpletoonEntityOverrides {
+    /// Whether any entity has overrides this frame, the ID pass is skipped otherwise.
+    fn is_active(&self) -> bool {
+        self.overrides.get().len() > 1
+    }
+}
+
+/// The entity ID texture of a view, only present while some entity has overrides.
+#[derive(Component)]
+pub(crate) struct SimpletoonEntityIdTexture(pub(crate) CachedTexture);
+
+pub(crate) struct SimpletoonEntityIdPlugin;
+
+impl Plugin for SimpletoonEntityIdPlugin {
+    fn build(&self, app: &mut App) {
+        embedded_asset!(app, "assets/toon_entity_id.wgsl");
+        app.add_plugins((
+            ExtractComponentPlugin::<NoToonBanding>::default(),
+            ExtractComponentPlugin::<ToonNormalThresholdBias>::default(),
+            ExtractComponentPlugin::<NoToonColourEdges>::default(),
+            ExtractComponentPlugin::<ToonStrokeDashes>::default(),
+            ExtractComponentPlugin::<ToonBands>::default(),
+            ExtractComponentPlugin::<ToonHair>::default(),
+            ExtractComponentPlugin::<ToonPassThrough>::default(),
+            ExtractComponentPlugin::<ToonOutlineId>::default(),
+            ExtractComponentPlugin::<FoliageEdgeMode>::default(),
+            ExtractComponentPlugin::<ToonTerrain>::default(),
+            ExtractComponentPlugin::<ToonDecal>::default(),
+            ExtractComponentPlugin::<ToonOverrides>::default(),
+            ExtractComponentPlugin::<ToonOutlineLod>::default(),
+            ExtractComponentPlugin::<ToonImposter>::default(),
+            ExtractComponentPlugin::<ToonHullOutline>::default(),
+        ))
+        .add_plugins(SortedRenderPhasePlugin::<EntityId3d, MeshPipeline>::new(RenderDebugFlags::default()));
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app
+            .init_resource::<SpecializedMeshPipelines<EntityIdPipeline>>()
+            .init_resource::<DrawFunctions<EntityId3d>>()
+            .add_render_command::<EntityId3d, DrawEntityId>()
+            .add_systems(ExtractSchedule, extract_entity_id_phases.in_set(SimpletoonSystems::Extract))
+            .add_systems(
+                Render,
+                (
+                    (
+                        queue_entity_id_meshes.in_set(RenderSet::QueueMeshes),
+                        sort_phase_system::<EntityId3d>.in_set(RenderSet::PhaseSort),
+                    )
+                        .in_set(SimpletoonSystems::Queue),
+                    (
+                        prepare_entity_overrides.in_set(RenderSet::PrepareResources),
+                        prepare_entity_id_textures.in_set(RenderSet::PrepareResources),
+                    )
+                        .in_set(SimpletoonSystems::Prepare),
+                ),
+            )
+            .add_render_graph_node::<ViewNodeRunner<EntityIdNode>>(Core3d, SimpletoonEntityIdLabel)
+            .add_render_graph_edges(
+                Core3d,
+                (
+                    Node3d::MainOpaquePass,
+                    SimpletoonEntityIdLabel,
+                    Node3d::MainTransmissivePass,
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app
+            .init_resource::<EntityIdPipeline>()
+            .init_resource::<SimpletoonEntityOverrides>();
+    }
+}
+
+#[derive(Resource)]
+struct EntityIdPipeline {
+    mesh_pipeline: MeshPipeline,
+    id_layout: BindGroupLayout,
+    shader: Handle<Shader>,
+}
+
+impl FromWorld for EntityIdPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let id_layout = world.resource::<RenderDevice>().create_bind_group_layout(
+            "simpletoon_entity_id_bind_group_layout",
+            &BindGroupLayoutEntries::single(
+                ShaderStages::FRAGMENT,
+                uniform_buffer::<EntityIdUniform>(true),
+            ),
+        );
+
+        Self {
+            mesh_pipeline: MeshPipeline::from_world(world),
+            id_layout,
+            shader: world.load_asset("embedded://bevy_simpletoon/assets/toon_entity_id.wgsl"),
+        }
+    }
+}
+
+impl FromWorld for SimpletoonEntityOverrides {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        // wgpu zero-initialises textures, so views without an ID texture read "no overrides" everywhere
+        let fallback_texture = render_device
+            .create_texture(&TextureDescriptor {
+                label: Some("simpletoon_entity_id_fallback"),
+                size: Extent3d::default(),
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: ENTITY_ID_FORMAT,
+                usage: TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&TextureViewDescriptor::default());
+
+        let mut overrides = StorageBuffer::from(vec![ToonOverride::default()]);
+        overrides.set_label(Some("simpletoon_entity_overrides"));
+        overrides.write_buffer(render_device, world.resource::<RenderQueue>());
+
+        Self {
+            ids: DynamicUniformBuffer::default(),
+            id_bind_group: None,
+            overrides,
+            fallback_texture,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct EntityIdPipelineKey {
+    mesh_key: MeshPipelineKey,
+    /// Only where the opaque pass drew this mesh, see [`ToonImposter`].
+    imposter: bool,
+    /// Back faces only and where the opaque pass drew them, see [`ToonHullOutline`].
+    hull: bool,
+}
+
+impl SpecializedMeshPipeline for EntityIdPipeline {
+    type Key = EntityIdPipelineKey;
+
+    fn specialize(
+        &self,
+        key: Self::Key,
+        layout: &MeshVertexBufferLayoutRef,
+    ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
+        // Bevy's own mesh pipeline takes care of skinning and morph targets, only the fragment stage is ours
+        let mut descriptor = self.mesh_pipeline.specialize(key.mesh_key, layout)?;
+        descriptor.label = Some("simpletoon_entity_id_pipeline".into());
+        descriptor.layout.push(self.id_layout.clone());
+        descriptor.fragment = Some(FragmentState {
+            shader: self.shader.clone(),
+            // The vertex output struct depends on the same defs as the vertex stage
+            shader_defs: descriptor.vertex.shader_defs.clone(),
+            entry_point: "fragment".into(),
+            targets: vec![Some(ColorTargetState {
+                format: ENTITY_ID_FORMAT,
+                blend: None,
+                write_mask: ColorWrites::ALL,
+            })],
+        });
+        // Test against the opaque pass' depth without changing it. Imposters and hulls pass only where their own depth
+        // is, which leaves out the texels an alpha mask discarded and the parts of a hull behind its mesh
+        if key.hull {
+            descriptor.primitive.cull_mode = Some(Face::Front);
+        }
+        descriptor.depth_stencil = Some(DepthStencilState {
+            format: CORE_3D_DEPTH_FORMAT,
+            depth_write_enabled: false,
+            depth_compare: match key.imposter || key.hull {
+                true => CompareFunction::Equal,
+                false => CompareFunction::GreaterEqual,
+            },
+            stencil: StencilState::default(),
+            bias: DepthBiasState::default(),
+        });
+        Ok(descriptor)
+    }
+}
+
+type DrawEntityId = (
+    SetItemPipeline,
+    SetMeshViewBindGroup<0>,
+    SetMeshBindGroup<1>,
+    SetEntityIdBindGroup<2>,
+    DrawMesh,
+);
+
+struct SetEntityIdBindGroup<const I: usize>;
+
+impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetEntityIdBindGroup<I> {
+    type Param = SRes<SimpletoonEntityOverrides>;
+    type ViewQuery = ();
+    type ItemQuery = Read<EntityIdOffset>;
+
+    fn render<'w>(
+        _item: &P,
+        _view: ROQueryItem<'w, Self::ViewQuery>,
+        offset: Option<ROQueryItem<'w, Self::ItemQuery>>,
+        overrides: SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let (Some(offset), Some(bind_group)) = (offset, overrides.into_inner().id_bind_group.as_ref()) else {
+            return RenderCommandResult::Skip;
+        };
+        pass.set_bind_group(I, bind_group, &[offset.0]);
+        RenderCommandResult::Success
+    }
+}
+
+/// Meshes drawn into the entity ID texture, back to front so the nearest one wins where they overlap.
+struct EntityId3d {
+    sort_key: FloatOrd,
+    entity: (Entity, MainEntity),
+    pipeline: CachedRenderPipelineId,
+    draw_function: DrawFunctionId,
+    batch_range: Range<u32>,
+    extra_index: PhaseItemExtraIndex,
+    indexed: bool,
+}
+
+impl PhaseItem for EntityId3d {
+    fn entity(&self) -> Entity {
+        self.entity.0
+    }
+
+    fn main_entity(&self) -> MainEntity {
+        self.entity.1
+    }
+
+    fn draw_function(&self) -> DrawFunctionId {
+        self.draw_function
+    }
+
+    fn batch_range(&self) -> &Range<u32> {
+        &self.batch_range
+    }
+
+    fn batch_range_mut(&mut self) -> &mut Range<u32> {
+        &mut self.batch_range
+    }
+
+    fn extra_index(&self) -> PhaseItemExtraIndex {
+        self.extra_index.clone()
+    }
+
+    fn batch_range_and_extra_index_mut(&mut self) -> (&mut Range<u32>, &mut PhaseItemExtraIndex) {
+        (&mut self.batch_range, &mut self.extra_index)
+    }
+}
+
+impl SortedPhaseItem for EntityId3d {
+    type SortKey = FloatOrd;
+
+    fn sort_key(&self) -> Self::SortKey {
+        self.sort_key
+    }
+
+    fn sort(items: &mut [Self]) {
+        items.sort_by_key(SortedPhaseItem::sort_key);
+    }
+
+    fn indexed(&self) -> bool {
+        self.indexed
+    }
+}
+
+impl CachedRenderPipelinePhaseItem for EntityId3d {
+    fn cached_pipeline(&self) -> CachedRenderPipelineId {
+        self.pipeline
+    }
+}
+
+fn extract_entity_id_phases(
+    mut phases: ResMut<ViewSortedRenderPhases<EntityId3d>>,
+    cameras: Extract<Query<(Entity, &Camera), With<SimpletoonSettings>>>,
+    mut live_entities: Local<HashSet<RetainedViewEntity>>,
+) {
+    live_entities.clear();
+    for (main_entity, camera) in &cameras {
+        if !camera.is_active {
+            continue;
+        }
+        let retained_view_entity = RetainedViewEntity::new(main_entity.into(), None, 0);
+        phases.insert_or_clear(retained_view_entity);
+        live_entities.insert(retained_view_entity);
+    }
+    phases.retain(|view, _| live_entities.contains(view));
+}
+
+type QueuedView = (
+    &'static ExtractedView,
+    &'static RenderVisibleEntities,
+    &'static Msaa,
+    Has<DepthPrepass>,
+    Has<NormalPrepass>,
+    Has<MotionVectorPrepass>,
+    Has<DeferredPrepass>,
+    Has<OrderIndependentTransparencySettings>,
+);
+
+#[allow(clippy::too_many_arguments)]
+fn queue_entity_id_meshes(
+    draw_functions: Res<DrawFunctions<EntityId3d>>,
+    mut pipelines: ResMut<SpecializedMeshPipelines<EntityIdPipeline>>,
+    pipeline_cache: Res<PipelineCache>,
+    entity_id_pipeline: Res<EntityIdPipeline>,
+    render_meshes: Res<RenderAssets<RenderMesh>>,
+    render_mesh_instances: Res<RenderMeshInstances>,
+    mut phases: ResMut<ViewSortedRenderPhases<EntityId3d>>,
+    views: Query<QueuedView>,
+    has_overrides: Query<(Has<ToonImposter>, Has<ToonHullOutline>), WithOverrides>,
+) {
+    let draw_function = draw_functions.read().id::<DrawEntityId>();
+    for (view, visible_entities, msaa, depth_prepass, normal_prepass, motion_vector_prepass, deferred_prepass, oit) in &views {
+        // The ID texture isn't multisampled, so it can't share a multisampled depth buffer
+        let Some(phase) = phases.get_mut(&view.retained_view_entity).filter(|_| *msaa == Msaa::Off) else {
+            continue;
+        };
+
+        // The view bind group layout has to match the one bevy made for this view
+        let mut view_key = MeshPipelineKey::from_hdr(view.hdr);
+        view_key.set(MeshPipelineKey::DEPTH_PREPASS, depth_prepass);
+        view_key.set(MeshPipelineKey::NORMAL_PREPASS, normal_prepass);
+        view_key.set(MeshPipelineKey::MOTION_VECTOR_PREPASS, motion_vector_prepass);
+        view_key.set(MeshPipelineKey::DEFERRED_PREPASS, deferred_prepass);
+        view_key.set(MeshPipelineKey::OIT_ENABLED, oit);
+
+        let rangefinder = view.rangefinder3d();
+        for (render_entity, visible_entity) in visible_entities.iter::<Mesh3d>() {
+            let Ok((imposter, hull)) = has_overrides.get(*render_entity) else {
+                continue;
+            };
+            let Some(mesh_instance) = render_mesh_instances.render_mesh_queue_data(*visible_entity) else {
+                continue;
+            };
+            let Some(mesh) = render_meshes.get(mesh_instance.mesh_asset_id) else {
+                continue;
+            };
+
+            let mut mesh_key = view_key | MeshPipelineKey::from_bits_retain(mesh.key_bits.bits());
+            if motion_vector_prepass {
+                mesh_key.set(MeshPipelineKey::HAS_PREVIOUS_SKIN, mesh_instance.flags.contains(RenderMeshInstanceFlags::HAS_PREVIOUS_SKIN));
+                mesh_key.set(MeshPipelineKey::HAS_PREVIOUS_MORPH, mesh_instance.flags.contains(RenderMeshInstanceFlags::HAS_PREVIOUS_MORPH));
+            }
+
+            let key = EntityIdPipelineKey { mesh_key, imposter, hull };
+            let pipeline = match pipelines.specialize(&pipeline_cache, &entity_id_pipeline, key, &mesh.layout) {
+                Ok(id) => id,
+                Err(err) => {
+                    error!("{err}");
+                    continue;
+                }
+            };
+            phase.add(EntityId3d {
+                sort_key: FloatOrd(rangefinder.distance_translation(&mesh_instance.translation)),
+                entity: (*render_entity, *visible_entity),
+                pipeline,
+                draw_function,
+                batch_range: 0..1,
+                extra_index: PhaseItemExtraIndex::None,
+                indexed: mesh.indexed(),
+            });
+        }
+    }
+}
+
+type EntityOverrides = (
+    Entity,
+    &'static MainEntity,
+    Has<NoToonBanding>,
+    Has<NoToonColourEdges>,
+    Option<&'static ToonNormalThresholdBias>,
+    Option<&'static ToonStrokeDashes>,
+    Option<&'static ToonBands>,
+    Option<&'static ToonHair>,
+    Has<ToonPassThrough>,
+    Option<&'static FoliageEdgeMode>,
+    Has<ToonTerrain>,
+    Option<&'static ExtractedToonDecal>,
+    Option<&'static ToonOverrides>,
+    Option<&'static ExtractedOutlineLod>,
+    // Nested, the flat tuple is at the length queries allow
+    (Has<ToonImposter>, Has<ToonHullOutline>),
+);
+
+/// Gives every entity with overrides an ID and writes its overrides at that index.
+fn prepare_entity_overrides(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    entity_id_pipeline: Res<EntityIdPipeline>,
+    mut entity_overrides: ResMut<SimpletoonEntityOverrides>,
+    entities: Query<EntityOverrides, WithOverrides>,
+) {
+    let entity_overrides = entity_overrides.as_mut();
+    entity_overrides.ids.clear();
+    let overrides = entity_overrides.overrides.get_mut();
+    overrides.truncate(1);
+    let mut surfaces = HashMap::new();
+    let mut decals = Vec::new();
+
+    for (entity, main_entity, no_banding, no_colour_edges, normal_threshold_bias, dashes, bands, hair, pass_through, foliage, terrain, decal, toon_overrides, lod, (imposter, hull)) in &entities {
+        let toon_overrides = toon_overrides.copied().unwrap_or_default();
+        let mut flags = 0;
+        if no_banding || toon_overrides.no_banding {
+            flags |= ToonOverride::NO_BANDING;
+        }
+        if no_colour_edges || toon_overrides.no_colour_edges {
+            flags |= ToonOverride::NO_COLOUR_EDGES;
+        }
+        if pass_through || toon_overrides.pass_through {
+            flags |= ToonOverride::PASS_THROUGH;
+        }
+        if toon_overrides.stroke_colour.is_some() {
+            flags |= ToonOverride::STROKE_COLOUR;
+        }
+        flags |= match foliage {
+            Some(FoliageEdgeMode::SilhouetteOnly) => ToonOverride::DEPTH_EDGES_ONLY | ToonOverride::SILHOUETTE_ONLY,
+            Some(FoliageEdgeMode::DepthOnly) => ToonOverride::DEPTH_EDGES_ONLY,
+            None => 0,
+        };
+        if terrain {
+            flags |= ToonOverride::TERRAIN;
+        }
+        if imposter {
+            flags |= ToonOverride::IMPOSTER;
+        }
+        if hull {
+            flags |= ToonOverride::HULL | ToonOverride::NO_BANDING;
+        }
+        if let Some(decal) = decal {
+            flags |= ToonOverride::DECAL;
+            decals.extend(decal.surface.map(|surface| (overrides.len(), surface)));
+        }
+        surfaces.insert(main_entity.id(), overrides.len());
+        let offset = entity_overrides.ids.push(&EntityIdUniform { id: overrides.len() as u32 });
+        overrides.push(ToonOverride {
+            flags,
+            normal_threshold_bias: normal_threshold_bias.map_or(0.0, |bias| bias.0),
+            dashes: dashes.map_or(Vec2::ZERO, |dashes| Vec2::new(dashes.length, dashes.gap).max(Vec2::ZERO)),
+            bands: bands.map(|bands| bands.0).or(toon_overrides.bands).map_or(0.0, |bands| bands.max(1) as f32),
+            hair_band: hair.map_or(Vec2::ZERO, |hair| Vec2::new(hair.shift, hair.width.clamp(0.0, 1.0))),
+            hair_colour: hair.map_or(Vec4::ZERO, |hair| hair.colour.to_linear().to_vec3().extend(hair.strength.clamp(0.0, 1.0))),
+            stroke_colour: toon_overrides.stroke_colour.map_or(Vec4::ZERO, |colour| colour.to_linear().to_vec4()),
+            stroke_size: toon_overrides.stroke_size.map_or(0.0, |size| size.max(0.0)),
+            lod_bounds: lod.map_or(Vec4::ZERO, |lod| lod.bounds),
+            lod_size: lod.map_or(Vec2::ZERO, |lod| Vec2::new(lod.lod.min_size, lod.lod.fade).max(Vec2::ZERO)),
+        });
+        commands.entity(entity).insert(EntityIdOffset(offset));
+    }
+    // Decals band like the mesh they are stuck on, unless they have bands of their own
+    for (decal, surface) in decals {
+        let Some(&surface) = surfaces.get(&surface) else {
+            continue;
+        };
+        let surface = overrides[surface];
+        let decal = &mut overrides[decal];
+        decal.flags |= surface.flags & ToonOverride::NO_BANDING;
+        if decal.bands == 0.0 {
+            decal.bands = surface.bands;
+        }
+    }
+
+    entity_overrides.ids.write_buffer(&render_device, &render_queue);
+    entity_overrides.overrides.write_buffer(&render_device, &render_queue);
+    entity_overrides.id_bind_group = entity_overrides.ids.binding().map(|binding| {
+        render_device.create_bind_group(
+            "simpletoon_entity_id_bind_group",
+            &entity_id_pipeline.id_layout,
+            &BindGroupEntries::single(binding),
+        )
+    });
+}
+
+fn prepare_entity_id_textures(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    mut texture_pool: ResMut<ToonTexturePool>,
+    entity_overrides: Res<SimpletoonEntityOverrides>,
+    post_process_pipeline: Res<PostProcessPipeline>,
+    phases: Res<ViewSortedRenderPhases<EntityId3d>>,
+    views: Query<(Entity, &ExtractedCamera, &ExtractedView, &Msaa)>,
+) {
+    for (entity, camera, view, msaa) in &views {
+        let has_phase = phases.contains_key(&view.retained_view_entity);
+        // Storage buffers are what the compatibility pipelines do without, so no overrides there
+        let size = camera.physical_target_size.filter(|_| {
+            has_phase && entity_overrides.is_active() && !post_process_pipeline.compatible && *msaa == Msaa::Off
+        });
+        let Some(size) = size else {
+            commands.entity(entity).remove::<SimpletoonEntityIdTexture>();
+            continue;
+        };
+        let texture = texture_pool.get(
+            &render_device,
+            TextureDescriptor {
+                label: Some("simpletoon_entity_id"),
+                size: Extent3d {
+                    width: size.x,
+                    height: size.y,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: ENTITY_ID_FORMAT,
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+        );
+        commands.entity(entity).insert(SimpletoonEntityIdTexture(texture));
+    }
+}
+
+#[derive(Default)]
+struct EntityIdNode;
+
+impl ViewNode for EntityIdNode {
+    type ViewQuery = (
+        &'static ExtractedCamera,
+        &'static ExtractedView,
+        &'static ViewDepthTexture,
+        &'static SimpletoonEntityIdTexture,
+    );
+
+    fn run<'w>(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext<'w>,
+        (camera, view, depth, id_texture): QueryItem<'w, Self::ViewQuery>,
+        world: &'w World,
+    ) -> Result<(), NodeRunError> {
+        let Some(phase) = world
+            .resource::<ViewSortedRenderPhases<EntityId3d>>()
+            .get(&view.retained_view_entity)
+        else {
+            return Ok(());
+        };
+
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("simpletoon_entity_id_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &id_texture.0.default_view,
+                resolve_target: None,
+                ops: Operations {
+                    load: LoadOp::Clear(Default::default()),
+                    store: StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(depth.get_attachment(StoreOp::Store)),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        if let Some(viewport) = camera.viewport.as_ref() {
+            render_pass.set_camera_viewport(viewport);
+        }
+
+        if let Err(err) = phase.render(&mut render_pass, world, graph.view_entity()) {
+            error!("Error encountered while rendering the simpletoon entity ID phase {err:?}");
+        }
+
+        Ok(())
+    }
+}
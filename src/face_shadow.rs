@@ -0,0 +1,84 @@
+use bevy::{
+    asset::embedded_asset,
+    pbr::{ExtendedMaterial, MaterialExtension},
+    prelude::*,
+    render::render_resource::{AsBindGroup, ShaderRef},
+};
+
+use crate::key_light::{find_key_light, KeyLight, ToonKeyLight};
+
+/// A [`StandardMaterial`] for anime style faces, shaded by a painted shadow map instead of real lighting.
+pub type ToonFaceMaterial = ExtendedMaterial<StandardMaterial, ToonFaceShadow>;
+
+/// Shades a face from a painted shadow threshold map in its UVs, so the shadow falls across it the way the artist drew
+/// it as the [`ToonKeyLight`] moves around the head.
+///
+/// Each texel of `shadow_map` holds how far the light may swing round from the front before that texel falls into
+/// shadow, 0 for always shadowed and 1 for always lit. The map is painted for light from `right` and mirrored for
+/// light from the other side. Only the light's angle around the head counts, not its height or colour: the face is
+/// drawn like [`StandardMaterial::unlit`], tinted by `shadow_colour` where it is shadowed.
+#[derive(Asset, AsBindGroup, Reflect, Debug, Clone)]
+pub struct ToonFaceShadow {
+    /// The way the face looks, in mesh space.
+    #[uniform(100)]
+    pub forward: Vec3,
+    /// The face's right, in mesh space.
+    #[uniform(100)]
+    pub right: Vec3,
+    #[uniform(100)]
+    pub shadow_colour: LinearRgba,
+    /// How far either side of the threshold the shadow edge is blended over, 0 for a hard edge.
+    #[uniform(100)]
+    pub softness: f32,
+    /// Kept up to date from the key light.
+    #[uniform(100)]
+    light: Vec4,
+    #[texture(101)]
+    #[sampler(102)]
+    pub shadow_map: Handle<Image>,
+}
+
+impl ToonFaceShadow {
+    /// A face looking along +Z, with its right along -X, as glTF models face.
+    pub fn new(shadow_map: Handle<Image>) -> Self {
+        Self {
+            forward: Vec3::Z,
+            right: Vec3::NEG_X,
+            shadow_colour: LinearRgba::rgb(0.7, 0.6, 0.65),
+            softness: 0.01,
+            light: Vec4::ZERO,
+            shadow_map,
+        }
+    }
+}
+
+impl MaterialExtension for ToonFaceShadow {
+    fn fragment_shader() -> ShaderRef {
+        "embedded://bevy_simpletoon/assets/toon_face_shadow.wgsl".into()
+    }
+}
+
+pub(crate) struct SimpletoonFaceShadowPlugin;
+
+impl Plugin for SimpletoonFaceShadowPlugin {
+    fn build(&self, app: &mut App) {
+        embedded_asset!(app, "assets/toon_face_shadow.wgsl");
+        app.add_plugins(MaterialPlugin::<ToonFaceMaterial>::default())
+            .add_systems(PostUpdate, update_toon_face_shadows.after(TransformSystem::TransformPropagate));
+    }
+}
+
+fn update_toon_face_shadows(
+    marked: Query<KeyLight, With<ToonKeyLight>>,
+    directional: Query<(&GlobalTransform, &DirectionalLight)>,
+    mut materials: ResMut<Assets<ToonFaceMaterial>>,
+) {
+    let light = find_key_light(&marked, &directional).position;
+    // Every material written is uploaded again, so leave them alone until the light moves
+    if materials.iter().all(|(_, material)| material.extension.light == light) {
+        return;
+    }
+    for (_, material) in materials.iter_mut() {
+        material.extension.light = light;
+    }
+}
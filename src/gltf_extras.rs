@@ -0,0 +1,352 @@
+use bevy::{
+    gltf::{GltfExtras, GltfSceneExtras},
+    prelude::*,
+};
+use serde::Deserialize;
+
+use crate::plugin::{
+    BandSpacing, ChannelBanding, ColourEdgeSpace, NormalSampling, SimpletoonSettings, StrokeAnimation, StrokeDepthOfField, StrokeSpace, StrokeStyle, ToonShading,
+};
+
+/// The `simpletoon` object artists can put in a glTF camera's or scene's extras, e.g.
+/// `{ "simpletoon": { "colour_banding": 4, "stroke_colour": [0, 0, 0, 1] } }`.
+///
+/// Any field left out keeps its current value.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct SimpletoonExtras {
+    pub depth_threshold: Option<f32>,
+    pub depth_threshold_depth_mul: Option<f32>,
+    pub depth_normal_threshold: Option<f32>,
+    pub depth_normal_threshold_mul_along: Option<f32>,
+    pub depth_normal_threshold_mul_across: Option<f32>,
+    pub normal_threshold: Option<f32>,
+    pub colour_threshold: Option<f32>,
+    pub stroke_size: Option<f32>,
+    pub colour_banding: Option<f32>,
+    /// Linear RGBA, like glTF's own material colours.
+    pub stroke_colour: Option<[f32; 4]>,
+    pub effect_strength: Option<f32>,
+    pub stroke_space: Option<StrokeSpaceExtras>,
+    pub stroke_dilation: Option<bool>,
+    pub stroke_cleanup: Option<bool>,
+    pub stroke_smoothing: Option<bool>,
+    pub high_contrast_strokes: Option<bool>,
+    pub id_edges: Option<bool>,
+    pub id_edge_depth: Option<f32>,
+    pub shadow_edges: Option<bool>,
+    pub shadow_edge_threshold: Option<f32>,
+    pub stroke_style: Option<StrokeStyleExtras>,
+    pub dash_length: Option<f32>,
+    pub dash_gap: Option<f32>,
+    pub normal_sampling: Option<NormalSamplingExtras>,
+    pub shading: Option<ToonShadingExtras>,
+    pub gooch_warm: Option<[f32; 3]>,
+    pub gooch_cool: Option<[f32; 3]>,
+    pub gooch_alpha: Option<f32>,
+    pub gooch_beta: Option<f32>,
+    pub line_art_background: Option<[f32; 4]>,
+    pub normal_detail_threshold: Option<f32>,
+    /// Turns the banded sky gradient on with its default colours, or off.
+    pub sky: Option<bool>,
+    /// Turns the blueprint grid background on with its default colours, or off.
+    pub blueprint: Option<bool>,
+    /// Bands red, green and blue separately with these counts, keeping the default channel offsets.
+    pub channel_bands: Option<[f32; 3]>,
+    /// Turns terrain tint bands on with their default colours, or off.
+    pub terrain: Option<bool>,
+    /// Pulses the strokes with this amplitude and frequency, keeping any hue cycle.
+    pub stroke_pulse: Option<[f32; 2]>,
+    /// Cycles the strokes' hue this many times a second, keeping any pulse.
+    pub stroke_hue_cycle: Option<f32>,
+    pub ssao_banding: Option<bool>,
+    pub emissive_strokes: Option<f32>,
+    pub colour_edge_space: Option<ColourEdgeSpaceExtras>,
+    pub chroma_threshold: Option<f32>,
+    pub stroke_depth_of_field: Option<StrokeDepthOfFieldExtras>,
+    pub banding_range: Option<[f32; 2]>,
+    pub band_spacing: Option<BandSpacingExtras>,
+    pub band_exposure: Option<f32>,
+    pub emissive_range: Option<[f32; 2]>,
+    pub screen_edge_margin: Option<f32>,
+    pub preserve_alpha: Option<bool>,
+    pub edge_alpha: Option<bool>,
+    pub line_breakup: Option<f32>,
+    pub line_breakup_scale: Option<f32>,
+    pub line_breakup_contrast: Option<f32>,
+    pub kernel_radius: Option<u32>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum StrokeSpaceExtras {
+    Screen,
+    World,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ColourEdgeSpaceExtras {
+    Rgb,
+    Luma,
+    Oklab,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum StrokeDepthOfFieldExtras {
+    Sharp,
+    Attenuate,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum StrokeStyleExtras {
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum BandSpacingExtras {
+    Linear,
+    Perceptual,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum NormalSamplingExtras {
+    Prepass,
+    RejectSilhouettes,
+    Depth,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ToonShadingExtras {
+    Banded,
+    Gooch,
+    LineArt,
+}
+
+#[derive(Deserialize)]
+struct Extras {
+    simpletoon: Option<SimpletoonExtras>,
+}
+
+impl SimpletoonExtras {
+    /// Reads the `simpletoon` object out of a glTF extras string, if it has one.
+    pub fn parse(extras: &str) -> Result<Option<Self>, serde_json::Error> {
+        serde_json::from_str::<Extras>(extras).map(|extras| extras.simpletoon)
+    }
+
+    pub fn apply(&self, settings: &mut SimpletoonSettings) {
+        let set = |target: &mut f32, value: Option<f32>| {
+            if let Some(value) = value {
+                *target = value;
+            }
+        };
+        set(&mut settings.depth_threshold, self.depth_threshold);
+        set(
+            &mut settings.depth_threshold_depth_mul,
+            self.depth_threshold_depth_mul,
+        );
+        set(
+            &mut settings.depth_normal_threshold,
+            self.depth_normal_threshold,
+        );
+        set(
+            &mut settings.depth_normal_threshold_mul_along,
+            self.depth_normal_threshold_mul_along,
+        );
+        set(
+            &mut settings.depth_normal_threshold_mul_across,
+            self.depth_normal_threshold_mul_across,
+        );
+        set(&mut settings.normal_threshold, self.normal_threshold);
+        set(&mut settings.colour_threshold, self.colour_threshold);
+        set(&mut settings.stroke_size, self.stroke_size);
+        set(&mut settings.colour_banding, self.colour_banding);
+        set(&mut settings.effect_strength, self.effect_strength);
+        set(&mut settings.emissive_strokes, self.emissive_strokes);
+        set(&mut settings.chroma_threshold, self.chroma_threshold);
+        set(&mut settings.line_breakup, self.line_breakup);
+        set(&mut settings.line_breakup_scale, self.line_breakup_scale);
+        set(&mut settings.line_breakup_contrast, self.line_breakup_contrast);
+        if let Some(kernel_radius) = self.kernel_radius {
+            settings.kernel_radius = kernel_radius;
+        }
+        if let Some(banding_range) = self.banding_range {
+            settings.banding_range = Vec2::from_array(banding_range);
+        }
+        if let Some(emissive_range) = self.emissive_range {
+            settings.emissive_range = Vec2::from_array(emissive_range);
+        }
+        if let Some(stroke_colour) = self.stroke_colour {
+            settings.stroke_colour = LinearRgba::from_f32_array(stroke_colour).into();
+        }
+        if let Some(stroke_space) = self.stroke_space {
+            settings.stroke_space = match stroke_space {
+                StrokeSpaceExtras::Screen => StrokeSpace::Screen,
+                StrokeSpaceExtras::World => StrokeSpace::World,
+            };
+        }
+        if let Some(colour_edge_space) = self.colour_edge_space {
+            settings.colour_edge_space = match colour_edge_space {
+                ColourEdgeSpaceExtras::Rgb => ColourEdgeSpace::Rgb,
+                ColourEdgeSpaceExtras::Luma => ColourEdgeSpace::Luma,
+                ColourEdgeSpaceExtras::Oklab => ColourEdgeSpace::Oklab,
+            };
+        }
+        if let Some(stroke_depth_of_field) = self.stroke_depth_of_field {
+            settings.stroke_depth_of_field = match stroke_depth_of_field {
+                StrokeDepthOfFieldExtras::Sharp => StrokeDepthOfField::Sharp,
+                StrokeDepthOfFieldExtras::Attenuate => StrokeDepthOfField::Attenuate,
+            };
+        }
+        if let Some(stroke_dilation) = self.stroke_dilation {
+            settings.stroke_dilation = stroke_dilation;
+        }
+        if let Some(stroke_cleanup) = self.stroke_cleanup {
+            settings.stroke_cleanup = stroke_cleanup;
+        }
+        if let Some(stroke_smoothing) = self.stroke_smoothing {
+            settings.stroke_smoothing = stroke_smoothing;
+        }
+        if let Some(high_contrast_strokes) = self.high_contrast_strokes {
+            settings.high_contrast_strokes = high_contrast_strokes;
+        }
+        if let Some(id_edges) = self.id_edges {
+            settings.id_edges = id_edges;
+        }
+        set(&mut settings.id_edge_depth, self.id_edge_depth);
+        if let Some(shadow_edges) = self.shadow_edges {
+            settings.shadow_edges = shadow_edges;
+        }
+        set(&mut settings.shadow_edge_threshold, self.shadow_edge_threshold);
+        if let Some(stroke_style) = self.stroke_style {
+            settings.stroke_style = match stroke_style {
+                StrokeStyleExtras::Solid => StrokeStyle::Solid,
+                StrokeStyleExtras::Dashed => StrokeStyle::Dashed,
+                StrokeStyleExtras::Dotted => StrokeStyle::Dotted,
+            };
+        }
+        if let Some(dash_length) = self.dash_length {
+            settings.dash_length = dash_length;
+        }
+        if let Some(dash_gap) = self.dash_gap {
+            settings.dash_gap = dash_gap;
+        }
+        if let Some(band_spacing) = self.band_spacing {
+            settings.band_spacing = match band_spacing {
+                BandSpacingExtras::Linear => BandSpacing::Linear,
+                BandSpacingExtras::Perceptual => BandSpacing::Perceptual,
+            };
+        }
+        if let Some(normal_sampling) = self.normal_sampling {
+            settings.normal_sampling = match normal_sampling {
+                NormalSamplingExtras::Prepass => NormalSampling::Prepass,
+                NormalSamplingExtras::RejectSilhouettes => NormalSampling::RejectSilhouettes,
+                NormalSamplingExtras::Depth => NormalSampling::Depth,
+            };
+        }
+        if let Some(shading) = self.shading {
+            settings.shading = match shading {
+                ToonShadingExtras::Banded => ToonShading::Banded,
+                ToonShadingExtras::Gooch => ToonShading::Gooch,
+                ToonShadingExtras::LineArt => ToonShading::LineArt,
+            };
+        }
+        if let Some(gooch_warm) = self.gooch_warm {
+            settings.gooch_warm = Vec3::from_array(gooch_warm);
+        }
+        if let Some(gooch_cool) = self.gooch_cool {
+            settings.gooch_cool = Vec3::from_array(gooch_cool);
+        }
+        set(&mut settings.gooch_alpha, self.gooch_alpha);
+        set(&mut settings.gooch_beta, self.gooch_beta);
+        if let Some(line_art_background) = self.line_art_background {
+            settings.line_art_background = Vec4::from_array(line_art_background);
+        }
+        set(&mut settings.screen_edge_margin, self.screen_edge_margin);
+        set(&mut settings.band_exposure, self.band_exposure);
+        if let Some(threshold) = self.normal_detail_threshold {
+            settings.normal_detail_threshold = Some(threshold);
+        }
+        if let Some(sky) = self.sky {
+            settings.sky = sky.then(|| settings.sky.unwrap_or_default());
+        }
+        if let Some(blueprint) = self.blueprint {
+            settings.blueprint = blueprint.then(|| settings.blueprint.unwrap_or_default());
+        }
+        if let Some(terrain) = self.terrain {
+            settings.terrain = terrain.then(|| settings.terrain.unwrap_or_default());
+        }
+        if let Some(channel_bands) = self.channel_bands {
+            let channel_banding = settings.channel_banding.unwrap_or_default();
+            settings.channel_banding = Some(ChannelBanding { bands: Vec3::from_array(channel_bands), ..channel_banding });
+        }
+        if let Some([pulse_amplitude, pulse_frequency]) = self.stroke_pulse {
+            let stroke_animation = settings.stroke_animation.unwrap_or_default();
+            settings.stroke_animation = Some(StrokeAnimation { pulse_amplitude, pulse_frequency, ..stroke_animation });
+        }
+        if let Some(hue_cycle) = self.stroke_hue_cycle {
+            let stroke_animation = settings.stroke_animation.unwrap_or(StrokeAnimation { pulse_amplitude: 0.0, ..default() });
+            settings.stroke_animation = Some(StrokeAnimation { hue_cycle, ..stroke_animation });
+        }
+        if let Some(ssao_banding) = self.ssao_banding {
+            settings.ssao_banding = ssao_banding;
+        }
+        if let Some(preserve_alpha) = self.preserve_alpha {
+            settings.preserve_alpha = preserve_alpha;
+        }
+        if let Some(edge_alpha) = self.edge_alpha {
+            settings.edge_alpha = edge_alpha;
+        }
+    }
+}
+
+type ExtrasCamera = (
+    Entity,
+    Option<Ref<'static, GltfExtras>>,
+    Option<&'static mut SimpletoonSettings>,
+);
+
+/// Camera extras set up that camera, scene extras apply to every toon camera.
+pub(crate) fn apply_gltf_extras(
+    mut commands: Commands,
+    scenes: Query<&GltfSceneExtras, Changed<GltfSceneExtras>>,
+    mut cameras: Query<ExtrasCamera, With<Camera3d>>,
+) {
+    let scene_extras: Vec<_> = scenes
+        .iter()
+        .filter_map(|extras| parse_or_warn(&extras.value))
+        .collect();
+
+    for (entity, extras, settings) in &mut cameras {
+        let camera_extras = extras
+            .filter(|extras| extras.is_changed())
+            .and_then(|extras| parse_or_warn(&extras.value));
+        match (settings, camera_extras) {
+            (Some(mut settings), camera_extras) => {
+                for extras in scene_extras.iter().chain(camera_extras.as_ref()) {
+                    extras.apply(&mut settings);
+                }
+            }
+            (None, Some(camera_extras)) => {
+                let mut settings = SimpletoonSettings::default();
+                camera_extras.apply(&mut settings);
+                commands.entity(entity).insert(settings);
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+fn parse_or_warn(extras: &str) -> Option<SimpletoonExtras> {
+    SimpletoonExtras::parse(extras)
+        .inspect_err(|err| warn!("Could not read simpletoon glTF extras: {err}"))
+        .ok()
+        .flatten()
+}
@@ -0,0 +1,228 @@
+use std::marker::PhantomData;
+
+use bevy::{
+    asset::RenderAssetUsages,
+    ecs::{
+        query::QueryItem,
+        system::{StaticSystemParam, SystemParam, SystemParamItem},
+    },
+    prelude::*,
+    render::{
+        extract_component::{ExtractComponent, ExtractComponentPlugin},
+        gpu_readback::{Readback, ReadbackComplete},
+        render_asset::RenderAssets,
+        render_resource::*,
+        renderer::{RenderContext, RenderDevice},
+        storage::{GpuShaderStorageBuffer, ShaderStorageBuffer},
+        RenderApp,
+    },
+};
+
+use crate::plugin::{PostProcessPipeline, SimpletoonSystems};
+
+/// Pixels each way per histogram sample, times the 8x8 workgroup, must match the histogram shaders.
+const STRIDE: u32 = 4;
+const WORKGROUP_SIZE: u32 = 8;
+
+/// A camera component whose view is counted into a histogram by a compute pass, and what is done with the counts
+/// once they're read back a frame or two later.
+///
+/// [`GpuHistogramPlugin`] keeps a [`HistogramBuffer`] and its readback on every camera with the component, and sets up
+/// [`HistogramPipeline`]. The node binding the view and dispatching the pass is left to the feature.
+pub(crate) trait GpuHistogram: Component {
+    /// Labels the histogram's pipeline, bind groups and passes, e.g. `"band_histogram"`.
+    const NAME: &'static str;
+    const BINS: usize;
+    /// The `embedded://` path of the shader, embedded by the feature's own plugin.
+    const SHADER: &'static str;
+    const ENTRY_POINT: &'static str;
+    /// What [`GpuHistogram::apply`] needs from the main world.
+    type Param: SystemParam + 'static;
+
+    /// The bindings of the histogram pass, the counts buffer among them.
+    fn layout_entries() -> Vec<BindGroupLayoutEntry>;
+
+    /// Uses the latest counts of `camera`'s histogram.
+    fn apply(camera: Entity, counts: &[u32], param: &mut SystemParamItem<Self::Param>);
+}
+
+/// The histogram buffer of a camera with `T`, and the entity reading it back.
+#[derive(Component)]
+pub(crate) struct HistogramBuffer<T: GpuHistogram> {
+    buffer: Handle<ShaderStorageBuffer>,
+    readback: Entity,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T: GpuHistogram> Clone for HistogramBuffer<T> {
+    fn clone(&self) -> Self {
+        Self {
+            buffer: self.buffer.clone(),
+            readback: self.readback,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T: GpuHistogram> ExtractComponent for HistogramBuffer<T> {
+    type QueryData = &'static Self;
+    type QueryFilter = ();
+    type Out = Self;
+
+    fn extract_component(histogram: QueryItem<'_, Self::QueryData>) -> Option<Self> {
+        Some(histogram.clone())
+    }
+}
+
+impl<T: GpuHistogram> HistogramBuffer<T> {
+    /// The counts buffer, once it has been uploaded.
+    pub(crate) fn gpu_buffer<'w>(&self, world: &'w World) -> Option<&'w Buffer> {
+        world.resource::<RenderAssets<GpuShaderStorageBuffer>>().get(&self.buffer).map(|buffer| &buffer.buffer)
+    }
+}
+
+/// On the entity reading back a camera's histogram, readbacks on the camera itself are the edge mask's.
+#[derive(Component)]
+struct HistogramOf(Entity);
+
+pub(crate) struct GpuHistogramPlugin<T>(PhantomData<fn() -> T>);
+
+impl<T> Default for GpuHistogramPlugin<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: GpuHistogram> Plugin for GpuHistogramPlugin<T> {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ExtractComponentPlugin::<HistogramBuffer<T>>::default())
+            .add_systems(Last, (stop_histograms::<T>, start_histograms::<T>).chain().before(SimpletoonSystems::Settings));
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.init_resource::<HistogramPipeline<T>>();
+    }
+}
+
+fn start_histograms<T: GpuHistogram>(
+    mut commands: Commands,
+    mut buffers: ResMut<Assets<ShaderStorageBuffer>>,
+    cameras: Query<Entity, (With<T>, Without<HistogramBuffer<T>>)>,
+) {
+    for camera in &cameras {
+        let mut buffer = ShaderStorageBuffer::with_size(T::BINS * size_of::<u32>(), RenderAssetUsages::RENDER_WORLD);
+        buffer.buffer_description.usage |= BufferUsages::COPY_SRC | BufferUsages::COPY_DST;
+        let buffer = buffers.add(buffer);
+        let readback = commands
+            .spawn((Readback::buffer(buffer.clone()), HistogramOf(camera)))
+            .observe(apply_histogram::<T>)
+            .id();
+        commands.entity(camera).insert(HistogramBuffer::<T> {
+            buffer,
+            readback,
+            marker: PhantomData,
+        });
+    }
+}
+
+fn stop_histograms<T: GpuHistogram>(
+    mut commands: Commands,
+    mut removed: RemovedComponents<T>,
+    cameras: Query<&HistogramBuffer<T>, Without<T>>,
+) {
+    for camera in removed.read() {
+        if let Ok(histogram) = cameras.get(camera) {
+            commands.entity(histogram.readback).despawn();
+            commands.entity(camera).remove::<HistogramBuffer<T>>();
+        }
+    }
+}
+
+fn apply_histogram<T: GpuHistogram>(
+    trigger: Trigger<ReadbackComplete>,
+    mut commands: Commands,
+    readbacks: Query<&HistogramOf>,
+    cameras: Query<(), With<T>>,
+    mut param: StaticSystemParam<T::Param>,
+) {
+    let Ok(HistogramOf(camera)) = readbacks.get(trigger.target()) else {
+        return;
+    };
+    if !cameras.contains(*camera) {
+        // The camera was despawned, stop_histograms only sees the component removed from live cameras
+        if commands.get_entity(*camera).is_err() {
+            commands.entity(trigger.target()).despawn();
+        }
+        return;
+    }
+    let counts: Vec<u32> = trigger.0.chunks_exact(4).map(|bytes| u32::from_ne_bytes(bytes.try_into().unwrap())).collect();
+    T::apply(*camera, &counts, &mut param);
+}
+
+#[derive(Resource)]
+pub(crate) struct HistogramPipeline<T> {
+    pub(crate) layout: BindGroupLayout,
+    /// None with the compatibility pipelines, where compute shaders may not be available.
+    pipeline: Option<CachedComputePipelineId>,
+    pass_label: String,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T: GpuHistogram> FromWorld for HistogramPipeline<T> {
+    fn from_world(world: &mut World) -> Self {
+        let layout = world
+            .resource::<RenderDevice>()
+            .create_bind_group_layout(format!("simpletoon_{}_bind_group_layout", T::NAME).as_str(), &T::layout_entries());
+        let compatible = world.resource::<PostProcessPipeline>().compatible;
+        let shader = world.load_asset(T::SHADER);
+        let pipeline = (!compatible).then(|| {
+            world.resource::<PipelineCache>().queue_compute_pipeline(ComputePipelineDescriptor {
+                label: Some(format!("simpletoon_{}_pipeline", T::NAME).into()),
+                layout: vec![layout.clone()],
+                push_constant_ranges: vec![],
+                shader,
+                shader_defs: vec![],
+                entry_point: T::ENTRY_POINT.into(),
+                zero_initialize_workgroup_memory: false,
+            })
+        });
+        Self {
+            layout,
+            pipeline,
+            pass_label: format!("simpletoon_{}_pass", T::NAME),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T: GpuHistogram> HistogramPipeline<T> {
+    /// The compute pipeline, once it has compiled.
+    pub(crate) fn pipeline<'w>(&self, world: &'w World) -> Option<&'w ComputePipeline> {
+        self.pipeline.and_then(|id| world.resource::<PipelineCache>().get_compute_pipeline(id))
+    }
+
+    /// Clears `buffer` and counts every 4th pixel each way of a `size` texture into it.
+    pub(crate) fn dispatch(
+        &self,
+        render_context: &mut RenderContext,
+        pipeline: &ComputePipeline,
+        bind_group: &BindGroup,
+        offsets: &[u32],
+        buffer: &Buffer,
+        size: Extent3d,
+    ) {
+        let workgroups = |pixels: u32| pixels.div_ceil(STRIDE * WORKGROUP_SIZE);
+        let encoder = render_context.command_encoder();
+        encoder.clear_buffer(buffer, 0, None);
+        let mut pass = encoder.begin_compute_pass(&ComputePassDescriptor {
+            label: Some(&self.pass_label),
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, bind_group, offsets);
+        pass.dispatch_workgroups(workgroups(size.width), workgroups(size.height), 1);
+    }
+}
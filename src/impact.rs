@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+use bevy::{ecs::query::QueryItem, prelude::*, render::extract_component::ExtractComponent};
+
+/// Insert on a toon camera for an anime style impact frame: the picture is flattened to inverted black and white with
+/// radial action lines bursting from `centre`, and the component removes itself after `duration`.
+///
+/// With `freeze` the game is paused through [`Time<Virtual>`] for the duration, so the hit holds on screen. The
+/// action lines are redrawn every `redraw` for the usual flicker, `Duration::ZERO` keeps the same ones throughout.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ImpactFrame {
+    pub duration: Duration,
+    /// Where the action lines burst from, in UVs of the camera's viewport.
+    pub centre: Vec2,
+    /// How many action lines go round the centre.
+    pub lines: u32,
+    /// Where the lines start, as a fraction of half the viewport's diagonal, so the middle is left clear.
+    pub inner_radius: f32,
+    pub redraw: Duration,
+    pub freeze: bool,
+    elapsed: Duration,
+    /// Whether this impact paused virtual time, so only it unpauses it again.
+    paused: bool,
+}
+
+impl ImpactFrame {
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            duration,
+            centre: Vec2::splat(0.5),
+            lines: 48,
+            inner_radius: 0.3,
+            redraw: Duration::from_millis(50),
+            freeze: true,
+            elapsed: Duration::ZERO,
+            paused: false,
+        }
+    }
+}
+
+/// The render world side of [`ImpactFrame`], what the toon pass draws this frame.
+#[derive(Component, Clone, Copy)]
+pub struct ExtractedImpactFrame {
+    pub centre: Vec2,
+    pub lines: f32,
+    pub inner_radius: f32,
+    /// Changes whenever the action lines are redrawn.
+    pub seed: f32,
+}
+
+impl ExtractComponent for ImpactFrame {
+    type QueryData = &'static ImpactFrame;
+    type QueryFilter = ();
+    type Out = ExtractedImpactFrame;
+
+    fn extract_component(impact: QueryItem<'_, Self::QueryData>) -> Option<Self::Out> {
+        let redraws = match impact.redraw.is_zero() {
+            true => 0,
+            false => impact.elapsed.as_nanos() / impact.redraw.as_nanos(),
+        };
+        Some(ExtractedImpactFrame {
+            centre: impact.centre,
+            lines: impact.lines as f32,
+            inner_radius: impact.inner_radius,
+            seed: (redraws % 1024) as f32,
+        })
+    }
+}
+
+/// Counts impact frames down in real time, as virtual time may be paused by them.
+pub(crate) fn run_impact_frames(
+    mut commands: Commands,
+    time: Res<Time<Real>>,
+    mut virtual_time: ResMut<Time<Virtual>>,
+    mut impacts: Query<(Entity, &mut ImpactFrame)>,
+) {
+    for (entity, mut impact) in &mut impacts {
+        if impact.freeze && !impact.paused && !virtual_time.is_paused() {
+            virtual_time.pause();
+            impact.paused = true;
+        }
+        impact.elapsed += time.delta();
+        if impact.elapsed >= impact.duration {
+            commands.entity(entity).remove::<ImpactFrame>();
+        }
+    }
+}
+
+/// Unpauses virtual time when an impact that paused it ends, or is removed, despawned or replaced early. A replacing
+/// impact with `freeze` pauses it again next frame and owns the pause from then on.
+pub(crate) fn end_impact_frame(trigger: Trigger<OnReplace, ImpactFrame>, impacts: Query<&ImpactFrame>, mut virtual_time: ResMut<Time<Virtual>>) {
+    if impacts.get(trigger.target()).is_ok_and(|impact| impact.paused) {
+        virtual_time.unpause();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::time::TimeUpdateStrategy;
+
+    use super::*;
+
+    fn update(app: &mut App, frames: usize) {
+        for _ in 0..frames {
+            app.update();
+        }
+    }
+
+    #[test]
+    fn reinserting_during_a_freeze_still_unpauses() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(20)))
+            .add_systems(Last, run_impact_frames)
+            .add_observer(end_impact_frame);
+        let camera = app.world_mut().spawn(ImpactFrame::new(Duration::from_millis(100))).id();
+        update(&mut app, 2);
+        assert!(app.world().resource::<Time<Virtual>>().is_paused());
+
+        app.world_mut().entity_mut(camera).insert(ImpactFrame::new(Duration::from_millis(100)));
+        update(&mut app, 2);
+        assert!(app.world().resource::<Time<Virtual>>().is_paused());
+
+        update(&mut app, 10);
+        assert!(!app.world().entity(camera).contains::<ImpactFrame>());
+        assert!(!app.world().resource::<Time<Virtual>>().is_paused());
+    }
+}
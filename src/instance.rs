@@ -0,0 +1,11 @@
+use bevy::prelude::*;
+
+/// Runs the pass of the [`crate::plugin::SimpletoonPlugin::labeled`] plugin with this label on the parent camera,
+/// using the [`crate::plugin::SimpletoonSettings`] on this entity. Spawn both as a child of a 3d camera, e.g.
+/// `commands.entity(camera).with_child((ToonInstance("fine"), SimpletoonSettings { .. }))`, to stack a second pass
+/// with settings of its own on top of the camera's.
+///
+/// Overlays such as `ImpactFrame`, `ToonSpeedLines` and `ToonCompare` are only drawn by the camera's own pass, and an
+/// instance neither exports a `ToonEdgeMask` nor draws `emissive_strokes`.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ToonInstance(pub &'static str);
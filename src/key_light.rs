@@ -0,0 +1,60 @@
+use bevy::{prelude::*, render::Extract};
+
+/// Marks the light that drives lighting-dependent toon effects, for scenes with more than one light.
+///
+/// Without a marked light the brightest [`DirectionalLight`] is used. Only one light should be marked,
+/// if several are the first one found wins.
+#[derive(Component, Clone, Copy, Default, Debug)]
+pub struct ToonKeyLight;
+
+/// The key light as the toon shader sees it, extracted every frame.
+#[derive(Resource, Clone, Copy, PartialEq, Default, Debug)]
+pub(crate) struct ExtractedToonKeyLight {
+    /// For a directional light `xyz` points towards the light and `w` is 0, otherwise `xyz` is its position and `w` is 1.
+    /// All zeroes when the scene has no usable light.
+    pub(crate) position: Vec4,
+    /// Linear colour, without the light's intensity.
+    pub(crate) colour: Vec3,
+}
+
+pub(crate) type KeyLight = (
+    &'static GlobalTransform,
+    Option<&'static DirectionalLight>,
+    Option<&'static PointLight>,
+    Option<&'static SpotLight>,
+);
+
+pub(crate) fn extract_toon_key_light(
+    mut key_light: ResMut<ExtractedToonKeyLight>,
+    marked: Extract<Query<KeyLight, With<ToonKeyLight>>>,
+    directional: Extract<Query<(&GlobalTransform, &DirectionalLight)>>,
+) {
+    key_light.set_if_neq(find_key_light(&marked, &directional));
+}
+
+/// The marked light, or failing that the brightest directional light.
+pub(crate) fn find_key_light(
+    marked: &Query<KeyLight, With<ToonKeyLight>>,
+    directional: &Query<(&GlobalTransform, &DirectionalLight)>,
+) -> ExtractedToonKeyLight {
+    if marked.iter().len() > 1 {
+        warn_once!("More than one entity has ToonKeyLight, only the first one is used");
+    }
+    let light = match marked.iter().next() {
+        Some((transform, Some(light), ..)) => Some((transform.back().extend(0.0), light.color)),
+        Some((transform, None, Some(light), _)) => Some((transform.translation().extend(1.0), light.color)),
+        Some((transform, None, None, Some(light))) => Some((transform.translation().extend(1.0), light.color)),
+        Some(_) => {
+            warn_once!("ToonKeyLight is on an entity without a DirectionalLight, PointLight or SpotLight");
+            None
+        }
+        None => directional
+            .iter()
+            .max_by(|(_, a), (_, b)| a.illuminance.total_cmp(&b.illuminance))
+            .map(|(transform, light)| (transform.back().extend(0.0), light.color)),
+    };
+    match light {
+        Some((position, colour)) => ExtractedToonKeyLight { position, colour: colour.to_linear().to_vec3() },
+        None => ExtractedToonKeyLight::default(),
+    }
+}
@@ -1,2 +1,34 @@
+#[cfg(feature = "outlines")]
+pub mod auto_thresholds;
+#[cfg(feature = "banding")]
+pub mod band_anchor;
+#[cfg(feature = "banding")]
+pub mod band_histogram;
+pub mod baker;
+pub mod builder;
+pub mod compare;
+pub mod detail;
+pub mod edge_mask;
+pub mod edge_polylines;
+pub mod edge_query;
+pub mod entity_id;
+pub mod face_shadow;
+#[cfg(any(feature = "banding", feature = "outlines"))]
+mod histogram;
+pub mod impact;
+pub mod instance;
+#[cfg(feature = "gltf")]
+pub mod gltf_extras;
+pub mod key_light;
+pub mod light_bands;
 pub mod plugin;
-
+pub mod presets;
+pub mod quality;
+pub mod screenshot;
+pub mod shader_defs;
+pub mod smoothing;
+pub mod speed_lines;
+mod texture_pool;
+#[cfg(feature = "banding")]
+pub mod transparent;
+pub mod vfx;
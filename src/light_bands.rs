@@ -0,0 +1,46 @@
+use bevy::{
+    asset::embedded_asset,
+    pbr::{ExtendedMaterial, MaterialExtension},
+    prelude::*,
+    render::render_resource::{AsBindGroup, ShaderRef},
+};
+
+/// A [`StandardMaterial`] lit in bands per light, see [`ToonLightBands`].
+pub type ToonBandedLightMaterial = ExtendedMaterial<StandardMaterial, ToonLightBands>;
+
+/// Bands each light's falloff on its own before the lights are added up, for clean stylised pools of light.
+///
+/// The toon pass bands colour once every light has been added up, so the falloff of point and spot lights shows as
+/// rings where the lights overlap. Here a light is stepped in `bands` steps of its full brightness, so each light
+/// leaves a flat pool that ends where less than half a step is left. Lighting is diffuse only: specular, reflections
+/// and the environment map are left out, as the toon pass bands those away anyway.
+#[derive(Asset, AsBindGroup, Reflect, Debug, Clone)]
+pub struct ToonLightBands {
+    /// Steps between unlit and a surface lit to its full colour.
+    #[uniform(100)]
+    pub bands: u32,
+    /// How much of the top of each step eases into the next, 0 for hard steps and 1 for smooth ramps.
+    #[uniform(100)]
+    pub softness: f32,
+}
+
+impl Default for ToonLightBands {
+    fn default() -> Self {
+        Self { bands: 3, softness: 0.0 }
+    }
+}
+
+impl MaterialExtension for ToonLightBands {
+    fn fragment_shader() -> ShaderRef {
+        "embedded://bevy_simpletoon/assets/toon_light_bands.wgsl".into()
+    }
+}
+
+pub(crate) struct SimpletoonLightBandsPlugin;
+
+impl Plugin for SimpletoonLightBandsPlugin {
+    fn build(&self, app: &mut App) {
+        embedded_asset!(app, "assets/toon_light_bands.wgsl");
+        app.add_plugins(MaterialPlugin::<ToonBandedLightMaterial>::default());
+    }
+}
@@ -1,79 +1,1274 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
+#[cfg(feature = "outlines")]
+use crate::auto_thresholds::SimpletoonAutoThresholdsPlugin;
+#[cfg(feature = "banding")]
+use crate::band_anchor::SimpletoonBandAnchorPlugin;
+#[cfg(feature = "banding")]
+use crate::band_histogram::{SimpletoonBandHistogramLabel, SimpletoonBandHistogramPlugin};
+use crate::baker::{run_toon_bakers, start_toon_bakers, ToonBakeCamera};
+use crate::compare::ToonCompare;
+use crate::detail::ToonDetailTexture;
+use crate::edge_mask::ToonEdgeMask;
+use crate::edge_polylines::{start_edge_readbacks, trace_edge_readback, ToonEdgePolylines};
+use crate::edge_query::{start_edge_queries, store_edge_query_readback};
+use crate::entity_id::{SimpletoonEntityIdPlugin, SimpletoonEntityIdTexture, SimpletoonEntityOverrides};
+use crate::face_shadow::SimpletoonFaceShadowPlugin;
+use crate::impact::{end_impact_frame, run_impact_frames, ExtractedImpactFrame, ImpactFrame};
+use crate::instance::ToonInstance;
+use crate::key_light::{extract_toon_key_light, ExtractedToonKeyLight};
+use crate::light_bands::SimpletoonLightBandsPlugin;
+use crate::quality::{apply_toon_quality, ToonQuality};
+use crate::screenshot::{take_toon_screenshots, ToonScreenshot, ToonTransparentBackground};
+use crate::shader_defs::{NormalEncoding, ToonShaderDefs};
+use crate::smoothing::smooth_toon_settings;
+use crate::speed_lines::ToonSpeedLines;
+use crate::texture_pool::{prune_texture_pool, ToonTexturePool};
+#[cfg(feature = "banding")]
+use crate::transparent::SimpletoonTransparentPlugin;
+use crate::vfx::{SimpletoonVfxPlugin, ToonVfxMask};
+use bevy::render::texture::FallbackImage;
+#[cfg(feature = "outlines")]
+use bevy::{
+    core_pipeline::prepass::{DepthPrepass, NormalPrepass},
+    pbr::{GpuLights, LightMeta, ShadowSamplers},
+    render::render_resource::binding_types::{
+        texture_2d_array, texture_2d_multisampled, texture_depth_2d, texture_depth_2d_multisampled,
+    },
+};
+
 use bevy::{
-    asset::embedded_asset, core_pipeline::{
+    asset::{embedded_asset, load_internal_asset, weak_handle}, core_pipeline::{
         core_3d::graph::{Core3d, Node3d},
-        fullscreen_vertex_shader::fullscreen_shader_vertex_state, prepass::{DepthPrepass, NormalPrepass, ViewPrepassTextures},
-    }, ecs::query::QueryItem, prelude::*, render::{
-        extract_component::{
-            ComponentUniforms, DynamicUniformIndex, ExtractComponent, ExtractComponentPlugin,
-            UniformComponentPlugin,
-        },
+        dof::{calculate_focal_length, DepthOfField},
+        tonemapping::Tonemapping,
+        fullscreen_vertex_shader::fullscreen_shader_vertex_state, prepass::ViewPrepassTextures,
+    }, ecs::{component::Tick, query::QueryItem, system::SystemChangeTick}, pbr::{
+        graph::NodePbr, ScreenSpaceAmbientOcclusionResources, ViewLightsUniformOffset, ViewShadowBindings,
+    }, platform::collections::HashMap, prelude::*, render::{
         render_graph::{
-            NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner,
+            InternedRenderLabel, NodeRunError, RenderGraph, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner,
         },
         render_resource::{
-            binding_types::{sampler, texture_2d, texture_depth_2d, uniform_buffer},
+            binding_types::{sampler, storage_buffer_read_only_sized, texture_2d, uniform_buffer},
             *,
         },
-        renderer::{RenderContext, RenderDevice},
-        view::{ViewTarget, ViewUniform, ViewUniformOffset, ViewUniforms},
-        RenderApp,
+        camera::{extract_cameras, ExtractedCamera},
+        extract_component::ExtractComponentPlugin,
+        render_asset::RenderAssets,
+        renderer::{RenderAdapter, RenderContext, RenderDevice, RenderQueue},
+        settings::{Backends, WgpuFeatures, WgpuLimits},
+        sync_component::SyncComponentPlugin,
+        sync_world::{MainEntity, RenderEntity},
+        texture::{CachedTexture, GpuImage},
+        view::{ExtractedView, ViewTarget, ViewUniform, ViewUniformOffset, ViewUniforms},
+        Extract, ExtractSchedule, Render, RenderApp, RenderSet,
     }
 };
 
 
-pub struct SimpletoonPlugin;
+const OUTLINE_MASK_SHADER_HANDLE: Handle<Shader> = weak_handle!("5b8f2c3e-0d7a-4f61-9a2e-8c41d7e6b093");
+
+/// Adds the toon post process to the 3d render graph, between tonemapping and FXAA unless [`SimpletoonPlugin::with_edges`] says otherwise.
+/// [`SimpletoonPlugin::builder`] sets the same options, checking they fit together before the app runs.
+#[derive(Default)]
+pub struct SimpletoonPlugin {
+    edges: Option<(InternedRenderLabel, InternedRenderLabel)>,
+    /// Settings and HDR-ness of the cameras whose pipelines are compiled up front.
+    pub(crate) precompile: Vec<(SimpletoonSettings, bool)>,
+    pub(crate) block_until_compiled: bool,
+    detached: bool,
+    /// Set by [`SimpletoonPlugin::before_depth_of_field`], the pass then sees light before the camera's tonemapper.
+    before_tonemapping: bool,
+    /// Set by [`SimpletoonPlugin::labeled`], the plugin then only adds that instance's pass.
+    pub(crate) label: Option<&'static str>,
+    pub(crate) normal_encoding: NormalEncoding,
+    /// Set by [`SimpletoonPluginBuilder::quality`](crate::builder::SimpletoonPluginBuilder::quality), the [`ToonQuality`] the app starts with.
+    pub(crate) quality: Option<ToonQuality>,
+    #[cfg(feature = "dev-hot-reload")]
+    pub(crate) shader_path: Option<&'static str>,
+}
+
+impl SimpletoonPlugin {
+    /// Runs the toon pass after the `before` node and ahead of the `after` node, e.g. to keep it in front of another crate's vignette or CRT pass.
+    /// The edges are only added once every plugin is built, so the other nodes can come from plugins added later.
+    pub fn with_edges(before: impl RenderLabel, after: impl RenderLabel) -> Self {
+        Self {
+            edges: Some((before.intern(), after.intern())),
+            ..default()
+        }
+    }
+
+    /// Runs the toon pass between bloom and `DepthOfField`, so out of focus strokes get blurred with the rest of the scene.
+    /// The pass then sees the scene before tonemapping, so HDR cameras will likely want a different `colour_banding`.
+    pub fn before_depth_of_field() -> Self {
+        Self {
+            before_tonemapping: true,
+            ..Self::with_edges(Node3d::Bloom, Node3d::DepthOfField)
+        }
+    }
+
+    /// Runs the toon pass straight after the main pass, ahead of `VolumetricFog`, so fog and light shafts are drawn
+    /// over the banded scene and keep their smooth falloff instead of being cut into steps. Like
+    /// [`SimpletoonPlugin::before_depth_of_field`] the pass then sees the scene before tonemapping.
+    pub fn before_volumetric_fog() -> Self {
+        Self {
+            before_tonemapping: true,
+            ..Self::with_edges(Node3d::EndMainPass, NodePbr::VolumetricFog)
+        }
+    }
+
+    /// Prepares everything the toon passes need without adding them to the `Core3d` graph, for render graphs of your own.
+    /// Add [`SimpletoonPostProcessNode`] (and [`SimpletoonEmissiveStrokesNode`] ahead of bloom, for `emissive_strokes`)
+    /// to your graph as a `ViewNodeRunner`, anywhere after the main pass has written the view target and the prepasses.
+    /// The entity ID and transparency passes hook into `Core3d`'s main passes and stay there, `ToonBandHistogram` isn't
+    /// counted.
+    pub fn detached() -> Self {
+        Self {
+            detached: true,
+            ..default()
+        }
+    }
+
+    /// Adds another toon pass to the graph, run on cameras with a [`ToonInstance`] child of the same label and drawn with
+    /// that child's settings, e.g. broad banding before tonemapping under fine outlines after FXAA. Add it after the
+    /// plugin without a label, which prepares everything the passes share, [`SimpletoonPlugin::detached`] if the
+    /// cameras only use labeled passes. The precompile, blocking and detached options only apply to that plugin.
+    /// Without [`SimpletoonPlugin::between`] the pass runs after the unlabeled one, over what it drew.
+    pub fn labeled(label: &'static str) -> Self {
+        Self {
+            label: Some(label),
+            ..default()
+        }
+    }
+
+    /// Runs the pass between these nodes like [`SimpletoonPlugin::with_edges`], for a [`SimpletoonPlugin::labeled`] pass.
+    pub fn between(mut self, before: impl RenderLabel, after: impl RenderLabel) -> Self {
+        self.edges = Some((before.intern(), after.intern()));
+        self
+    }
+
+    /// Compiles the pipelines a camera with these settings will use as soon as the shader has loaded, instead of when
+    /// the camera first appears, so the effect doesn't pop in mid-game. [`SimpletoonWarmup`] tells when they are done.
+    pub fn precompile(mut self, settings: &SimpletoonSettings, hdr: bool) -> Self {
+        self.precompile.push((*settings, hdr));
+        self
+    }
+
+    /// Loads `toon.wgsl` from this asset path instead of the copy embedded in the crate, e.g. `"shaders/toon.wgsl"`
+    /// with a copy or symlink of the crate's `src/assets/toon.wgsl` in the app's `assets` folder. Edits then show up
+    /// when the file is saved, without rebuilding. Only meant for development, shipped builds should use the embedded shader.
+    #[cfg(feature = "dev-hot-reload")]
+    pub fn shader_path(mut self, path: &'static str) -> Self {
+        self.shader_path = Some(path);
+        self
+    }
+
+    /// Decodes the normal prepass as `encoding` instead of the layout the Bevy this crate is built against writes.
+    pub fn normal_encoding(mut self, encoding: NormalEncoding) -> Self {
+        self.normal_encoding = encoding;
+        self
+    }
+
+    /// Stalls rendering until the [`SimpletoonPlugin::precompile`] pipelines have compiled, rather than rendering
+    /// without the effect in the meantime. Frames before the shader has loaded are still rendered.
+    pub fn block_until_compiled(mut self) -> Self {
+        self.block_until_compiled = true;
+        self
+    }
+}
+
+/// Whether the pipelines declared with [`SimpletoonPlugin::precompile`] have all compiled, e.g. to leave a loading state.
+#[derive(Resource, Clone, Default)]
+pub struct SimpletoonWarmup {
+    ready: Arc<AtomicBool>,
+}
+
+impl SimpletoonWarmup {
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Relaxed)
+    }
+}
+
+/// Run condition for systems that should wait for [`SimpletoonWarmup`].
+pub fn simpletoon_warmed_up(warmup: Res<SimpletoonWarmup>) -> bool {
+    warmup.is_ready()
+}
+
+/// The render world side of [`SimpletoonPlugin::shader_path`].
+#[cfg(feature = "dev-hot-reload")]
+#[derive(Resource)]
+struct SimpletoonShaderPath(&'static str);
+
+/// The render world side of [`SimpletoonPlugin::precompile`].
+#[derive(Resource)]
+struct PrecompileRequests {
+    views: Vec<(SimpletoonUniform, bool)>,
+    block: bool,
+    warmup: SimpletoonWarmup,
+}
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
 pub struct SimpletoonPostProcessLabel;
 
+/// The plugin's systems, for ordering your own around them.
+///
+/// With pipelined rendering, settings are extracted while the next frame already runs, so a change made after
+/// [`SimpletoonSystems::Settings`] reaches the GPU unvalidated. Systems that edit [`SimpletoonSettings`] in `Last`
+/// should run `.before(SimpletoonSystems::Settings)`.
+///
+/// `Extract`, `Queue` and `Prepare` run in the render app, so other render crates and custom extraction can order
+/// against the toon pipeline, e.g. an extract system that reads the toon uniforms `.after(SimpletoonSystems::Extract)`.
+#[derive(SystemSet, Debug, Hash, PartialEq, Eq, Clone)]
+pub enum SimpletoonSystems {
+    /// Clamps invalid settings, in `Last` so changes from `Update` and `PostUpdate` are covered.
+    Settings,
+    /// Copies settings to the render world and sets up the entity ID phases, in `ExtractSchedule`. Runs after Bevy
+    /// extracts the cameras, so a view's settings and its view uniforms are always taken from the same frame.
+    Extract,
+    /// Queues and sorts the meshes of the entity ID pass, in `Render` within `RenderSet::QueueMeshes` and `RenderSet::PhaseSort`.
+    Queue,
+    /// Writes uniforms, pipelines, textures and bind groups, in `Render` between `RenderSet::Prepare` and `RenderSet::PrepareBindGroups`.
+    Prepare,
+}
+
+/// The pass of a [`SimpletoonPlugin::labeled`] plugin.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct SimpletoonInstanceLabel(pub &'static str);
+
+/// Draws HDR strokes before bloom when `emissive_strokes` is set.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct SimpletoonEmissiveStrokesLabel;
+
+/// Sent once all of a toon camera's pipelines have compiled, until then the camera renders without the toon effect.
+/// Sent again if new pipelines are needed later, e.g. after turning on `stroke_dilation`.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct SimpletoonPipelineReady {
+    pub camera: Entity,
+}
+
+/// Sent when a camera's [`SimpletoonSettings`] are added or changed, after they have been validated.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct SimpletoonSettingsChanged {
+    pub camera: Entity,
+}
+
+/// Cameras whose pipelines became ready in the render world, shared between both worlds.
+#[derive(Resource, Clone, Default)]
+struct ReadyPipelines(Arc<Mutex<Vec<Entity>>>);
+
+/// Marks views whose pipelines were all ready last frame.
+#[derive(Component)]
+struct SimpletoonPipelinesReady;
+
+/// Draws the toon effect into a view's [`ViewTarget`], added to `Core3d` as [`SimpletoonPostProcessLabel`].
+///
+/// Runs on views with [`SimpletoonSettings`] once their [`SimpletoonPipelineIds`] and [`SimpletoonViewBindGroups`]
+/// are prepared, which the plugin does in [`SimpletoonSystems::Prepare`]. To embed it in another graph, build the
+/// plugin with [`SimpletoonPlugin::detached`] and add a `ViewNodeRunner<SimpletoonPostProcessNode>` yourself.
 #[derive(Default)]
-struct SimpletoonPostProcessNode;
+pub struct SimpletoonPostProcessNode;
+
+/// Runs the pass of a [`ToonInstance`] on its camera, added to `Core3d` as [`SimpletoonInstanceLabel`].
+pub struct SimpletoonInstanceNode {
+    label: &'static str,
+}
+
+/// On the render entity of a [`ToonInstance`], the view its pass draws to. The camera's own pass is its view entity.
+#[derive(Component)]
+pub(crate) struct SimpletoonInstanceOf(Entity);
 
-#[derive(Component, Clone, Copy, ExtractComponent, ShaderType)]
-#[require(DepthPrepass, NormalPrepass)]
+/// On a view with [`ToonInstance`] children, their render entities by label.
+#[derive(Component)]
+pub struct SimpletoonViewInstances(Vec<(&'static str, Entity)>);
+
+/// Draws HDR strokes ahead of bloom for `emissive_strokes`, added to `Core3d` as [`SimpletoonEmissiveStrokesLabel`].
+#[derive(Default)]
+pub struct SimpletoonEmissiveStrokesNode;
+
+/// The toon effect on a [`Camera3d`].
+///
+/// A UI or 2d camera drawing over the same target later in [`Camera::order`] is left alone, give it
+/// `ClearColorConfig::None` so it composites over the toon result.
+///
+/// Reflected, so cutscenes authored with bevy's animation system can keyframe the numeric fields and `stroke_colour`
+/// directly, e.g. `AnimatableCurve::new(animated_field!(SimpletoonSettings::stroke_colour), curve)` with a curve of
+/// [`Srgba`] on the camera's animation target.
+#[derive(Component, Clone, Copy, PartialEq, Reflect)]
+#[reflect(Component, Default)]
+#[cfg_attr(feature = "outlines", require(DepthPrepass, NormalPrepass))]
 pub struct SimpletoonSettings {
-    pub depth_threshold: f32,
-    pub depth_threshold_depth_mul: f32,  // If something is further away, it should require more depth
+    pub depth_threshold: f32, // Depth difference in world units (metres) needed for a depth edge
+    pub depth_threshold_depth_mul: f32, // Added to depth_threshold per world unit of distance, so further away needs more depth
     pub depth_normal_threshold: f32, // If at a glazing angle, depth threshold should be harsher
-    pub depth_normal_threshold_mul: f32, // If at a glazing angle, depth threshold should be harsher
+    pub depth_normal_threshold_mul_along: f32, // How much harsher, for depth changing along the way the surface slopes away on screen, where glazing floors draw false edges
+    pub depth_normal_threshold_mul_across: f32, // How much harsher, for depth changing across that slope, where a flat surface doesn't change depth so most changes are real edges
     pub normal_threshold: f32,
     pub colour_threshold: f32,
     pub stroke_size: f32,
     pub colour_banding: f32,
-    pub stroke_colour: Vec4
+    pub stroke_colour: Srgba, // Converted to linear when uploaded, so colours like Srgba::rgb(0.2, 0.1, 0.1) look as authored, Srgba rather than Color so it can be animated
+    pub effect_strength: f32, // 0 leaves the source untouched, 1 is the full toon result
+    pub stroke_space: StrokeSpace,
+    pub stroke_dilation: bool, // Detect 1px edges and grow them to stroke_size, cheaper and gap-free for thick strokes
+    pub ssao_banding: bool, // Quantize ScreenSpaceAmbientOcclusion into the colour bands instead of leaving its soft gradients
+    pub emissive_strokes: f32, // Above 0 strokes are drawn before bloom at stroke_colour * emissive_strokes so they glow, needs Camera::hdr
+    pub colour_edge_space: ColourEdgeSpace,
+    pub chroma_threshold: f32, // Hue/saturation difference needed for a colour edge, only used with ColourEdgeSpace::Oklab
+    pub stroke_depth_of_field: StrokeDepthOfField,
+    pub banding_range: Vec2, // Only colours with a luminance between x and y are banded, so highlights and deep shadows can stay smooth
+    pub band_spacing: BandSpacing,
+    pub band_exposure: f32, // Colours are scaled by this before banding and back after, moving the band boundaries, see ToonBandAnchor
+    pub line_breakup: f32, // 0 draws solid strokes, towards 1 they are broken up by world-space noise for a hand-inked look
+    pub line_breakup_scale: f32, // Size of the breakup noise in world units
+    pub line_breakup_contrast: f32, // Above 1 the noise gets harsher, with clearer gaps in the strokes
+    pub kernel_radius: u32, // 1-4, how many rings of taps the edge detector checks, 1 for low-end hardware, 2-3 for smoother thick lines
+    pub stroke_cleanup: bool, // Fill 1px gaps and square off corners where strokes meet, for cleaner line art
+    pub stroke_smoothing: bool, // Antialias the edges of strokes in the toon pass, for smooth lines without FXAA softening the whole picture
+    pub high_contrast_strokes: bool, // Draw strokes black or white, whichever stands out more against the scene around them
+    pub id_edges: bool, // Stroke where meshes with a ToonOutlineId meet others, e.g. posters flush with a wall
+    pub id_edge_depth: f32, // ID edges are only drawn where both sides are within this many world units in depth
+    pub shadow_edges: bool, // Stroke the outlines of shadows cast by the first shadow casting DirectionalLight, not drawn with the compatibility pipelines
+    pub shadow_edge_threshold: f32, // Difference in how much of the light reaches either side needed for a shadow edge, 0-1
+    pub stroke_style: StrokeStyle,
+    pub dash_length: f32, // Length of each dash in pixels with StrokeStyle::Dashed
+    pub dash_gap: f32, // Space between dashes or dots in pixels
+    pub normal_sampling: NormalSampling,
+    pub normal_detail_threshold: Option<f32>, // Ink normal mapped detail like panel gaps and bricks, with its own threshold on how far the mapped normals lean off the surface, needs the outlines feature
+    pub shading: ToonShading,
+    pub gooch_warm: Vec3, // Colour surfaces take on facing the key light with ToonShading::Gooch
+    pub gooch_cool: Vec3, // Colour surfaces take on facing away from the key light
+    pub gooch_alpha: f32, // How much of the scene colour is blended into gooch_cool
+    pub gooch_beta: f32, // How much of the scene colour is blended into gooch_warm
+    pub line_art_background: Vec4, // What ToonShading::LineArt draws between the strokes, alpha 0 leaves only the strokes opaque
+    pub preserve_alpha: bool, // Keep the target's alpha instead of writing it opaque, for transparent windows and render-to-texture compositing
+    pub edge_alpha: bool, // Write how much of each pixel is stroke into alpha, as an edge hint for a custom FXAA/SMAA pass after this one, replaces preserve_alpha
+    pub screen_edge_margin: f32, // Strokes within this many pixels of the viewport's border are dropped, e.g. to keep a vignette or frame clean
+    pub emissive_range: Vec2, // Banding fades out as a colour's brightest channel goes from x to y, so emissive surfaces keep smooth gradients without a ring of bands around them
+    pub stroke_animation: Option<StrokeAnimation>, // Pulse and hue cycle the strokes on the GPU, rather than changing stroke_colour every frame
+    pub channel_banding: Option<ChannelBanding>, // Band red, green and blue separately instead of colour_banding, for a printed CMYK look
+    pub terrain: Option<TerrainBands>, // Tint ToonTerrain meshes in steps by height and slope, needs the outlines feature
+    pub blueprint: Option<BlueprintGrid>, // Replace everything that isn't geometry with a grid, needs the outlines feature
+    pub sky: Option<ToonSky>, // Replace the sky and clear colour with a banded gradient, needs the outlines feature
+    pub detail: Option<DetailMask>, // Fewer bands and interior lines where the camera's ToonDetailTexture is dark, to draw the eye to focal areas
+}
+
+/// What `stroke_size` is measured in.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Reflect)]
+pub enum StrokeSpace {
+    /// A constant width in pixels.
+    #[default]
+    Screen,
+    /// An approximate width in world units, so closer objects get thicker lines.
+    World,
+}
+
+/// How colours are compared when looking for colour edges.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Reflect)]
+pub enum ColourEdgeSpace {
+    /// Raw RGB distance against `colour_threshold`, bright saturated colours fire far more easily than dark ones.
+    #[default]
+    Rgb,
+    /// Only the difference in luminance counts, against `colour_threshold`.
+    Luma,
+    /// OKLab lightness against `colour_threshold` and chroma against `chroma_threshold`, roughly even across bright and dark materials.
+    Oklab,
+}
+
+/// How strokes react to a `DepthOfField` on the same camera.
+///
+/// To have strokes blurred along with the scene instead, run the toon pass ahead of depth of field with
+/// [`SimpletoonPlugin::before_depth_of_field`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Reflect)]
+pub enum StrokeDepthOfField {
+    /// Strokes stay sharp wherever they are.
+    #[default]
+    Sharp,
+    /// Strokes fade out as the circle of confusion grows past their width, as if they had been blurred with the scene.
+    Attenuate,
+}
+
+/// How strokes are drawn along their length, e.g. dashed for blueprint and technical drawing styles.
+///
+/// Dashes are laid out along the direction each stroke runs in on screen. Meshes with a
+/// [`crate::entity_id::ToonStrokeDashes`] keep their own dashes whatever the camera's style is.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Reflect)]
+pub enum StrokeStyle {
+    #[default]
+    Solid,
+    /// Dashes `dash_length` long, `dash_gap` apart.
+    Dashed,
+    /// Dots as long as the stroke is wide, `dash_gap` apart.
+    Dotted,
+}
+
+/// How surfaces are shaded between the strokes.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Reflect)]
+pub enum ToonShading {
+    /// Colours quantized into `colour_banding` bands, needs the `banding` feature.
+    #[default]
+    Banded,
+    /// Cool to warm Gooch shading from the normal prepass and the [`crate::key_light::ToonKeyLight`], e.g. for
+    /// technical illustration. Lights from the camera when the scene has no light, needs the `outlines` feature.
+    Gooch,
+    /// Only the strokes, over a flat `line_art_background` in place of the scene, e.g. for colouring book exports or
+    /// line art composited over footage shaded elsewhere. The background is also drawn where `sky` or `blueprint`
+    /// would be, needs the `outlines` feature.
+    LineArt,
+}
+
+/// Where the normal edge detector gets its normals from.
+///
+/// At silhouettes the normal prepass compares the foreground's normals with the background's, drawing a normal edge
+/// beside the depth edge that shows up as a doubled line on thin geometry.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Reflect)]
+pub enum NormalSampling {
+    /// Straight from the normal prepass.
+    #[default]
+    Prepass,
+    /// From the normal prepass, skipping pairs of samples on either side of a depth discontinuity.
+    RejectSilhouettes,
+    /// Reconstructed from the depth prepass, each from the neighbours on its own surface. Ignores normal maps
+    /// and smooth shading, so curved meshes show their facets.
+    Depth,
+}
+
+/// Where the boundaries between `colour_banding` bands go.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Reflect)]
+pub enum BandSpacing {
+    /// Equal steps in the colour the pass sees.
+    #[default]
+    Linear,
+    /// Equal steps in perceived lightness of the light before the camera's [`Tonemapping`], so AgX, TonyMcMapface
+    /// and the like don't crowd the bands into the highlights. Each tonemapper's shoulder is approximated, and with
+    /// [`SimpletoonPlugin::before_depth_of_field`] the pass already sees untonemapped light so none is undone.
+    /// Doesn't apply to `channel_banding`.
+    Perceptual,
+}
+
+/// The background [`SimpletoonSettings::blueprint`] draws in place of the sky and clear colour.
+#[derive(Clone, Copy, PartialEq, Debug, Reflect)]
+pub struct BlueprintGrid {
+    pub background: Vec4,
+    pub grid_colour: Vec4,
+    /// Distance between grid lines in pixels.
+    pub spacing: f32,
+    /// Width of the grid lines in pixels.
+    pub line_width: f32,
+}
+
+impl Default for BlueprintGrid {
+    fn default() -> Self {
+        Self {
+            background: Vec4::new(0.02, 0.12, 0.38, 1.0),
+            grid_colour: Vec4::new(0.35, 0.55, 0.9, 1.0),
+            spacing: 32.0,
+            line_width: 1.0,
+        }
+    }
+}
+
+/// The cel sky [`SimpletoonSettings::sky`] draws wherever nothing was rendered, a gradient from the horizon up to the
+/// zenith through the optional stops in between, so no skybox shader is needed for it.
+#[derive(Clone, Copy, PartialEq, Debug, Reflect)]
+pub struct ToonSky {
+    pub horizon: Color,
+    /// A stop a third of the way up, or halfway without an `upper` one.
+    pub lower: Option<Color>,
+    /// A stop two thirds of the way up, or halfway without a `lower` one.
+    pub upper: Option<Color>,
+    pub zenith: Color,
+    /// Flat steps the gradient is cut into, 0 keeps it smooth.
+    pub bands: f32,
+}
+
+impl Default for ToonSky {
+    fn default() -> Self {
+        Self {
+            horizon: Color::srgb(0.95, 0.85, 0.7),
+            lower: Some(Color::srgb(0.55, 0.75, 0.95)),
+            upper: None,
+            zenith: Color::srgb(0.2, 0.45, 0.85),
+            bands: 4.0,
+        }
+    }
+}
+
+/// Stepped tints [`SimpletoonSettings::terrain`] multiplies into meshes with a [`crate::entity_id::ToonTerrain`],
+/// for a stylised map look. Height is read from the depth prepass and slope from the normals.
+#[derive(Clone, Copy, PartialEq, Debug, Reflect)]
+pub struct TerrainBands {
+    /// Tint at `base_height` and below.
+    pub low_colour: Vec3,
+    /// Tint at `base_height + height_range` and above.
+    pub high_colour: Vec3,
+    /// Tint that slopes step towards as they get steeper.
+    pub cliff_colour: Vec3,
+    /// World height the bands start at.
+    pub base_height: f32,
+    /// World height the bands span.
+    pub height_range: f32,
+    /// Steps from `low_colour` to `high_colour` over `height_range`, at least 1.
+    pub height_bands: f32,
+    /// Steps from flat ground to a sheer cliff.
+    pub slope_bands: f32,
+}
+
+impl Default for TerrainBands {
+    fn default() -> Self {
+        Self {
+            low_colour: Vec3::new(0.55, 0.8, 0.45),
+            high_colour: Vec3::new(1.0, 0.95, 0.85),
+            cliff_colour: Vec3::new(0.75, 0.6, 0.5),
+            base_height: 0.0,
+            height_range: 20.0,
+            height_bands: 5.0,
+            slope_bands: 3.0,
+        }
+    }
+}
+
+/// Lowers detail for [`SimpletoonSettings::detail`] where the red channel of the camera's
+/// [`ToonDetailTexture`](crate::detail::ToonDetailTexture) is dark, a common illustrative trick to keep the focal
+/// areas of a shot detailed. White keeps the camera's own settings, cameras without the texture are left alone.
+#[derive(Clone, Copy, PartialEq, Debug, Reflect)]
+pub struct DetailMask {
+    pub projection: DetailProjection,
+    /// World units the texture spans before repeating, with [`DetailProjection::World`].
+    pub world_size: f32,
+    /// Band count where the texture is black, blended up to `colour_banding` where it's white.
+    pub low_detail_bands: f32,
+    /// How many times higher the normal and colour edge thresholds are where the texture is black, so only
+    /// silhouettes and the strongest creases are stroked there.
+    pub low_detail_threshold_mul: f32,
+}
+
+impl Default for DetailMask {
+    fn default() -> Self {
+        Self {
+            projection: DetailProjection::Screen,
+            world_size: 10.0,
+            low_detail_bands: 2.0,
+            low_detail_threshold_mul: 4.0,
+        }
+    }
+}
+
+/// How [`DetailMask`] lays its texture over the scene.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Reflect)]
+pub enum DetailProjection {
+    /// Stretched over the viewport, e.g. a vignette-like mask painted for a shot.
+    #[default]
+    Screen,
+    /// Tiled from above over the world's XZ plane, so detail stays put as the camera moves. Needs the `outlines`
+    /// feature to know where each pixel is, without it the texture is laid over the screen.
+    World,
 }
 
+/// Animates strokes for [`SimpletoonSettings::stroke_animation`] from a time uniform, e.g. for a selection highlight.
+/// Only cameras with an animation have their settings re-uploaded every frame.
+#[derive(Clone, Copy, PartialEq, Debug, Reflect)]
+pub struct StrokeAnimation {
+    /// How much of the strokes' opacity pulses away, from 0 to 1.
+    pub pulse_amplitude: f32,
+    /// Pulses per second.
+    pub pulse_frequency: f32,
+    /// Trips round the colour wheel per second, the stroke colour needs some saturation to show it.
+    pub hue_cycle: f32,
+}
+
+impl Default for StrokeAnimation {
+    fn default() -> Self {
+        Self { pulse_amplitude: 0.5, pulse_frequency: 1.0, hue_cycle: 0.0 }
+    }
+}
+
+/// Bands each primary on its own for [`SimpletoonSettings::channel_banding`], e.g. fewer blue bands than red for a
+/// cheap print look. Each channel can be shifted across the screen to fake plates printed out of register.
+#[derive(Clone, Copy, PartialEq, Debug, Reflect)]
+pub struct ChannelBanding {
+    /// Band counts for red, green and blue.
+    pub bands: Vec3,
+    /// How far each channel is shifted, in pixels.
+    pub red_offset: Vec2,
+    pub green_offset: Vec2,
+    pub blue_offset: Vec2,
+}
+
+impl Default for ChannelBanding {
+    fn default() -> Self {
+        Self {
+            bands: Vec3::new(5.0, 4.0, 3.0),
+            red_offset: Vec2::new(1.0, 0.0),
+            green_offset: Vec2::ZERO,
+            blue_offset: Vec2::new(-1.0, 1.0),
+        }
+    }
+}
+
+/// The GPU side of [`SimpletoonSettings`], this is what the render world holds for each toon camera.
+#[derive(Component, Clone, Copy, ShaderType)]
+pub struct SimpletoonUniform {
+    depth_threshold: f32,
+    depth_threshold_depth_mul: f32,
+    depth_normal_threshold: f32,
+    depth_normal_threshold_mul_along: f32,
+    depth_normal_threshold_mul_across: f32,
+    normal_threshold: f32,
+    colour_threshold: f32,
+    stroke_size: f32,
+    colour_banding: f32,
+    stroke_colour: Vec4,
+    effect_strength: f32,
+    stroke_space: u32,
+    stroke_dilation: u32,
+    ssao_banding: u32,
+    emissive_strokes: f32,
+    colour_edge_space: u32,
+    chroma_threshold: f32,
+    // Copied from the camera's DepthOfField with StrokeDepthOfField::Attenuate, dof_max_coc is 0 otherwise
+    dof_focal_distance: f32,
+    dof_focal_length: f32,
+    dof_coc_scale: f32,
+    dof_max_coc: f32,
+    dof_max_depth: f32,
+    banding_range: Vec2,
+    band_spacing: u32,
+    band_shoulder: f32, // Set from the camera's Tonemapping, see BandSpacing::Perceptual
+    band_exposure: f32,
+    line_breakup: f32,
+    line_breakup_scale: f32,
+    line_breakup_contrast: f32,
+    kernel_radius: u32, // Only read by the shader without push constants
+    stroke_cleanup: u32,
+    stroke_smoothing: u32,
+    high_contrast_strokes: u32,
+    id_edges: u32,
+    id_edge_depth: f32,
+    shadow_edges: u32,
+    shadow_edge_threshold: f32,
+    stroke_style: u32,
+    dash_length: f32,
+    dash_gap: f32,
+    normal_sampling: u32,
+    normal_detail: u32,
+    normal_detail_threshold: f32,
+    blueprint: u32,
+    blueprint_background: Vec4,
+    blueprint_grid_colour: Vec4,
+    blueprint_grid_spacing: f32,
+    blueprint_line_width: f32,
+    shading: u32,
+    gooch_warm: Vec3,
+    gooch_alpha: f32,
+    gooch_cool: Vec3,
+    gooch_beta: f32,
+    line_art_background: Vec4,
+    emissive_range: Vec2,
+    screen_edge_margin: f32,
+    preserve_alpha: u32,
+    edge_alpha: u32,
+    stroke_animation: u32,
+    stroke_pulse_amplitude: f32,
+    stroke_pulse_frequency: f32,
+    stroke_hue_cycle: f32,
+    channel_banding: u32,
+    channel_bands: Vec3,
+    channel_red_offset: Vec2,
+    channel_green_offset: Vec2,
+    channel_blue_offset: Vec2,
+    terrain: u32,
+    terrain_low_colour: Vec3,
+    terrain_base_height: f32,
+    terrain_high_colour: Vec3,
+    terrain_height_range: f32,
+    terrain_cliff_colour: Vec3,
+    terrain_height_bands: f32,
+    terrain_slope_bands: f32,
+    sky: u32, // Number of gradient stops, 0 without a sky
+    sky_stops: [Vec4; 4],
+    sky_bands: f32,
+    detail: u32,
+    detail_world_size: f32,
+    detail_low_bands: f32,
+    detail_low_threshold_mul: f32,
+    // Written every frame from ExtractedToonKeyLight, see ToonKeyLight
+    key_light: Vec4,
+    key_light_colour: Vec3,
+    transparent_background: u32, // Set while the camera has a ToonScreenshot pending
+    // Written every frame from ExtractedImpactFrame
+    impact: u32,
+    impact_lines: f32,
+    impact_inner_radius: f32,
+    impact_seed: f32,
+    impact_centre: Vec2,
+    // Written from ToonSpeedLines while a camera has them
+    speed_lines: u32,
+    speed_line_count: f32,
+    speed_line_length: f32,
+    speed_line_inner_radius: f32,
+    speed_line_jitter: f32,
+    speed_line_width: f32,
+    speed_line_seed: f32,
+    speed_line_centre: Vec2,
+    speed_line_colour: Vec4,
+    time: f32, // Only kept current while stroke_animation is on
+    // Written from ToonCompare while a camera has one
+    compare: u32,
+    compare_split: f32,
+}
+
+impl From<&SimpletoonSettings> for SimpletoonUniform {
+    fn from(settings: &SimpletoonSettings) -> Self {
+        let blueprint = settings.blueprint.unwrap_or_default();
+        let channel_banding = settings.channel_banding.unwrap_or_default();
+        let stroke_animation = settings.stroke_animation.unwrap_or_default();
+        let terrain = settings.terrain.unwrap_or_default();
+        let detail = settings.detail.unwrap_or_default();
+        let sky_stops: Vec<Vec4> = settings
+            .sky
+            .iter()
+            .flat_map(|sky| [Some(sky.horizon), sky.lower, sky.upper, Some(sky.zenith)])
+            .flatten()
+            .map(|colour| colour.to_linear().to_vec4())
+            .collect();
+        Self {
+            depth_threshold: settings.depth_threshold,
+            depth_threshold_depth_mul: settings.depth_threshold_depth_mul,
+            depth_normal_threshold: settings.depth_normal_threshold,
+            depth_normal_threshold_mul_along: settings.depth_normal_threshold_mul_along,
+            depth_normal_threshold_mul_across: settings.depth_normal_threshold_mul_across,
+            normal_threshold: settings.normal_threshold,
+            colour_threshold: settings.colour_threshold,
+            stroke_size: settings.stroke_size,
+            colour_banding: settings.colour_banding,
+            stroke_colour: LinearRgba::from(settings.stroke_colour).to_vec4(),
+            effect_strength: settings.effect_strength,
+            stroke_space: match settings.stroke_space {
+                StrokeSpace::Screen => 0,
+                StrokeSpace::World => 1,
+            },
+            stroke_dilation: settings.stroke_dilation as u32,
+            ssao_banding: settings.ssao_banding as u32,
+            emissive_strokes: settings.emissive_strokes,
+            colour_edge_space: match settings.colour_edge_space {
+                ColourEdgeSpace::Rgb => 0,
+                ColourEdgeSpace::Luma => 1,
+                ColourEdgeSpace::Oklab => 2,
+            },
+            chroma_threshold: settings.chroma_threshold,
+            dof_focal_distance: 0.0,
+            dof_focal_length: 0.0,
+            dof_coc_scale: 0.0,
+            dof_max_coc: 0.0,
+            dof_max_depth: 0.0,
+            banding_range: settings.banding_range,
+            band_spacing: match settings.band_spacing {
+                BandSpacing::Linear => 0,
+                BandSpacing::Perceptual => 1,
+            },
+            band_shoulder: 0.0,
+            band_exposure: settings.band_exposure,
+            line_breakup: settings.line_breakup,
+            line_breakup_scale: settings.line_breakup_scale,
+            line_breakup_contrast: settings.line_breakup_contrast,
+            kernel_radius: settings.kernel_radius,
+            stroke_cleanup: settings.stroke_cleanup as u32,
+            stroke_smoothing: settings.stroke_smoothing as u32,
+            high_contrast_strokes: settings.high_contrast_strokes as u32,
+            id_edges: settings.id_edges as u32,
+            id_edge_depth: settings.id_edge_depth,
+            shadow_edges: settings.shadow_edges as u32,
+            shadow_edge_threshold: settings.shadow_edge_threshold,
+            stroke_style: match settings.stroke_style {
+                StrokeStyle::Solid => 0,
+                StrokeStyle::Dashed => 1,
+                StrokeStyle::Dotted => 2,
+            },
+            dash_length: settings.dash_length,
+            dash_gap: settings.dash_gap,
+            normal_sampling: match settings.normal_sampling {
+                NormalSampling::Prepass => 0,
+                NormalSampling::RejectSilhouettes => 1,
+                NormalSampling::Depth => 2,
+            },
+            normal_detail: settings.normal_detail_threshold.is_some() as u32,
+            normal_detail_threshold: settings.normal_detail_threshold.unwrap_or_default(),
+            blueprint: settings.blueprint.is_some() as u32,
+            blueprint_background: blueprint.background,
+            blueprint_grid_colour: blueprint.grid_colour,
+            blueprint_grid_spacing: blueprint.spacing,
+            blueprint_line_width: blueprint.line_width,
+            shading: match settings.shading {
+                ToonShading::Banded => 0,
+                ToonShading::Gooch => 1,
+                ToonShading::LineArt => 2,
+            },
+            gooch_warm: settings.gooch_warm,
+            gooch_alpha: settings.gooch_alpha,
+            gooch_cool: settings.gooch_cool,
+            gooch_beta: settings.gooch_beta,
+            line_art_background: settings.line_art_background,
+            emissive_range: settings.emissive_range,
+            screen_edge_margin: settings.screen_edge_margin,
+            preserve_alpha: settings.preserve_alpha as u32,
+            edge_alpha: settings.edge_alpha as u32,
+            stroke_animation: settings.stroke_animation.is_some() as u32,
+            stroke_pulse_amplitude: stroke_animation.pulse_amplitude,
+            stroke_pulse_frequency: stroke_animation.pulse_frequency,
+            stroke_hue_cycle: stroke_animation.hue_cycle,
+            channel_banding: settings.channel_banding.is_some() as u32,
+            channel_bands: channel_banding.bands,
+            channel_red_offset: channel_banding.red_offset,
+            channel_green_offset: channel_banding.green_offset,
+            channel_blue_offset: channel_banding.blue_offset,
+            terrain: settings.terrain.is_some() as u32,
+            terrain_low_colour: terrain.low_colour,
+            terrain_base_height: terrain.base_height,
+            terrain_high_colour: terrain.high_colour,
+            terrain_height_range: terrain.height_range,
+            terrain_cliff_colour: terrain.cliff_colour,
+            terrain_height_bands: terrain.height_bands,
+            terrain_slope_bands: terrain.slope_bands,
+            sky: sky_stops.len() as u32,
+            sky_stops: std::array::from_fn(|i| sky_stops.get(i).copied().unwrap_or_default()),
+            sky_bands: settings.sky.map_or(0.0, |sky| sky.bands),
+            detail: match settings.detail.map(|detail| detail.projection) {
+                None => 0,
+                Some(DetailProjection::Screen) => 1,
+                Some(DetailProjection::World) => 2,
+            },
+            detail_world_size: detail.world_size,
+            detail_low_bands: detail.low_detail_bands,
+            detail_low_threshold_mul: detail.low_detail_threshold_mul,
+            key_light: Vec4::ZERO,
+            key_light_colour: Vec3::ZERO,
+            transparent_background: 0,
+            impact: 0,
+            impact_lines: 0.0,
+            impact_inner_radius: 0.0,
+            impact_seed: 0.0,
+            impact_centre: Vec2::ZERO,
+            speed_lines: 0,
+            speed_line_count: 0.0,
+            speed_line_length: 0.0,
+            speed_line_inner_radius: 0.0,
+            speed_line_jitter: 0.0,
+            speed_line_width: 0.0,
+            speed_line_seed: 0.0,
+            speed_line_centre: Vec2::ZERO,
+            speed_line_colour: Vec4::ZERO,
+            time: 0.0,
+            compare: 0,
+            compare_split: 0.0,
+        }
+    }
+}
+
+impl SimpletoonUniform {
+    /// Takes the same circle of confusion parameters bevy's depth of field pass uses.
+    fn set_depth_of_field(&mut self, depth_of_field: &DepthOfField, projection: &PerspectiveProjection) {
+        let focal_length = calculate_focal_length(depth_of_field.sensor_height, projection.fov);
+        self.dof_focal_distance = depth_of_field.focal_distance;
+        self.dof_focal_length = focal_length;
+        self.dof_coc_scale = focal_length * focal_length / (depth_of_field.sensor_height * depth_of_field.aperture_f_stops);
+        self.dof_max_coc = depth_of_field.max_circle_of_confusion_diameter;
+        self.dof_max_depth = depth_of_field.max_depth;
+    }
+
+    /// Roughly how hard each tonemapper rolls off the highlights, as `s` in `x / (1 + s * x)` for x scaled so 1 maps to 1.
+    fn set_band_shoulder(&mut self, tonemapping: Tonemapping) {
+        self.band_shoulder = match tonemapping {
+            Tonemapping::None => 0.0,
+            Tonemapping::Reinhard | Tonemapping::ReinhardLuminance => 0.9,
+            Tonemapping::AgX | Tonemapping::TonyMcMapface => 0.85,
+            Tonemapping::AcesFitted | Tonemapping::BlenderFilmic => 0.8,
+            Tonemapping::SomewhatBoringDisplayTransform => 0.7,
+        };
+    }
+}
+
+/// Whether the pass runs ahead of tonemapping, in which case there is no tonemapper for band spacing to undo.
 #[derive(Resource)]
-struct PostProcessPipeline {
+struct PassBeforeTonemapping(bool);
+
+/// The render world side of [`SimpletoonPlugin::normal_encoding`].
+#[derive(Resource)]
+struct SimpletoonNormalEncoding(NormalEncoding);
+
+/// Settings uniforms for every toon camera, only rewritten when a camera's settings change.
+#[derive(Resource, Default)]
+struct SimpletoonUniforms {
+    buffer: DynamicUniformBuffer<SimpletoonUniform>,
+    entities: Vec<Entity>,
+}
+
+/// Everything a bind group is built from, so it only needs recreating when one of them changes (e.g. on resize).
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum BindGroupKey {
+    View {
+        source: TextureViewId,
+        #[cfg(feature = "outlines")]
+        depth: TextureViewId,
+        #[cfg(feature = "outlines")]
+        normal: TextureViewId,
+        settings: BufferId,
+        view: BufferId,
+        // Uniform offsets baked into the bind group in compatibility mode, zero otherwise
+        offsets: [u32; 2],
+        #[cfg(feature = "banding")]
+        ssao: TextureViewId,
+        entity_ids: TextureViewId,
+        entity_overrides: BufferId,
+        vfx: TextureViewId,
+        detail: TextureViewId,
+        // The light uniform, its offset and the directional shadow maps for shadow edges, None in compatibility mode
+        #[cfg(feature = "outlines")]
+        shadows: Option<(BufferId, u32, TextureViewId)>,
+    },
+    Mask(TextureViewId),
+    #[cfg(feature = "outlines")]
+    DepthHistogram {
+        depth: TextureViewId,
+        view: BufferId,
+        histogram: BufferId,
+    },
+    #[cfg(feature = "banding")]
+    LuminanceHistogram {
+        source: TextureViewId,
+        histogram: BufferId,
+    },
+    #[cfg(feature = "banding")]
+    BandHistogram {
+        source: TextureViewId,
+        histogram: BufferId,
+    },
+    #[cfg(feature = "banding")]
+    Transparent {
+        source: TextureViewId,
+        opaque: TextureViewId,
+        settings: BufferId,
+    },
+    VfxMask {
+        source: TextureViewId,
+        snapshot: TextureViewId,
+        settings: BufferId,
+    },
+}
+
+/// Bind groups reused across frames, entries that go a frame without being used are dropped.
+#[derive(Resource, Default)]
+pub(crate) struct SimpletoonBindGroupCache {
+    entries: Mutex<HashMap<BindGroupKey, (BindGroup, bool)>>,
+}
+
+impl SimpletoonBindGroupCache {
+    pub(crate) fn get_or_create(&self, key: BindGroupKey, create: impl FnOnce() -> BindGroup) -> BindGroup {
+        let mut entries = self.entries.lock().unwrap();
+        let (bind_group, used) = entries.entry(key).or_insert_with(|| (create(), true));
+        *used = true;
+        bind_group.clone()
+    }
+}
+
+/// Offset of a view's settings within [`SimpletoonUniforms`].
+#[derive(Component)]
+struct SimpletoonUniformIndex(u32);
+
+/// The toon shader's bind group layouts and shared resources, specialized per [`SimpletoonPipelineKey`].
+///
+/// Group 0 is the view bind group, built from [`PostProcessPipeline::layout`]: the source texture, the settings and
+/// view uniforms, the prepass textures and entity overrides. Group 1 is an edge mask from [`PostProcessPipeline::mask_layout`].
+/// Views with MSAA have multisampled prepass textures and use [`PostProcessPipeline::multisampled_layout`] instead.
+#[derive(Resource)]
+pub struct PostProcessPipeline {
     layout: BindGroupLayout,
+    multisampled_layout: BindGroupLayout,
+    mask_layout: BindGroupLayout,
     sampler: Sampler,
-    pipeline_id: CachedRenderPipelineId,
+    /// Tiling noise for `line_breakup`, with a repeating sampler.
+    #[cfg(feature = "outlines")]
+    noise: TextureView,
+    #[cfg(feature = "outlines")]
+    noise_sampler: Sampler,
+    shader: Handle<Shader>,
+    /// Mobile/GL path: no dynamic offsets, depth bound as a plain float texture and no SSAO binding.
+    pub(crate) compatible: bool,
+    /// Native adapters get per-view constants like `kernel_radius` as push constants, elsewhere the shader reads them from the settings uniform.
+    push_constants: bool,
+    normal_encoding: NormalEncoding,
+}
+
+impl PostProcessPipeline {
+    pub fn layout(&self) -> &BindGroupLayout {
+        &self.layout
+    }
+
+    pub fn multisampled_layout(&self) -> &BindGroupLayout {
+        &self.multisampled_layout
+    }
+
+    /// The view layout matching prepass textures with this many samples.
+    fn view_layout(&self, multisampled_prepass: bool) -> &BindGroupLayout {
+        if multisampled_prepass { &self.multisampled_layout } else { &self.layout }
+    }
+
+    pub fn mask_layout(&self) -> &BindGroupLayout {
+        &self.mask_layout
+    }
+
+    pub fn sampler(&self) -> &Sampler {
+        &self.sampler
+    }
+
+    /// Whether the GL/mobile layout is in use, where the view bind group takes no dynamic offsets.
+    pub fn is_compatible(&self) -> bool {
+        self.compatible
+    }
+
+    /// The shader defs every pass on this adapter is compiled with, before the per-pass ones are set.
+    pub fn shader_defs(&self) -> ToonShaderDefs {
+        ToonShaderDefs::default()
+            .compatible(self.compatible)
+            .push_constants(self.push_constants)
+            .normal_encoding(self.normal_encoding)
+    }
+
+    /// Mali/Adreno drivers (and GL in general) reject reading `texture_depth_2d` with `textureLoad`
+    /// and struggle with dynamic uniform offsets, so fall back to the compatibility layout there.
+    fn needs_compatibility(backend: Backends, limits: &WgpuLimits) -> bool {
+        backend == Backends::GL
+            || limits.max_dynamic_uniform_buffers_per_pipeline_layout < 2
+            || limits.max_sampled_textures_per_shader_stage < 5
+    }
+
+    /// Dynamic offsets for the view bind group, in compatibility mode they are baked into the bind group instead.
+    fn view_offsets<'a>(&self, offsets: &'a [u32; 2]) -> &'a [u32] {
+        if self.compatible { &[] } else { offsets }
+    }
+
+    /// The bytes pushed before every draw, empty when the adapter has no push constants.
+    fn push_constants(&self, settings: &SimpletoonUniform) -> Vec<u8> {
+        if !self.push_constants {
+            return Vec::new();
+        }
+        settings.kernel_radius.clamp(1, 4).to_ne_bytes().to_vec()
+    }
+}
+
+/// Rarely changing per-view parameters are pushed instead of specialized, so they don't multiply the pipelines.
+/// Just `kernel_radius` for now, must match `ToonPushConstants` in toon.wgsl.
+const PUSH_CONSTANTS_SIZE: u32 = size_of::<u32>() as u32;
+
+/// The passes the toon shader is split into.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+enum SimpletoonPass {
+    /// Edge detection and banding in one pass.
+    Single,
+    /// Strokes written into the intermediate edge mask.
+    Edge,
+    /// Gaps and corners in the edge mask filled in, before any dilation.
+    Cleanup,
+    DilateX,
+    DilateY,
+    /// Banding with strokes read from the edge mask.
+    Composite,
+    /// HDR strokes drawn before bloom.
+    EmissiveStrokes,
+}
+
+/// Which pass of the toon shader a pipeline runs, and whether it writes to an HDR target.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SimpletoonPipelineKey {
+    pass: SimpletoonPass,
+    hdr: bool,
+    /// The view has MSAA, so its prepass textures are multisampled.
+    multisampled_prepass: bool,
+}
+
+/// The pipelines a view's passes use, specialized for its target format.
+#[derive(Component)]
+pub struct SimpletoonPipelineIds {
+    main: CachedRenderPipelineId,
+    edge: Option<CachedRenderPipelineId>,
+    cleanup: Option<CachedRenderPipelineId>,
+    dilate: Option<[CachedRenderPipelineId; 2]>,
+    emissive_strokes: Option<CachedRenderPipelineId>,
+}
+
+impl SimpletoonPipelineIds {
+    /// The pass that writes the toon result into the view target.
+    pub fn main(&self) -> CachedRenderPipelineId {
+        self.main
+    }
+
+    fn iter(&self) -> impl Iterator<Item = CachedRenderPipelineId> {
+        let dilate = self.dilate.into_iter().flatten();
+        [Some(self.main), self.edge, self.cleanup, self.emissive_strokes].into_iter().flatten().chain(dilate)
+    }
+}
+
+/// Bind groups for a view's passes, built in [`RenderSet::PrepareBindGroups`].
+#[derive(Component)]
+pub struct SimpletoonViewBindGroups {
+    /// The view bind group for either main texture as the source.
+    views: [(TextureViewId, BindGroup); 2],
+    /// Reading the edge mask and the dilation scratch texture, with [`SimpletoonEdgeTextures`].
+    masks: Option<[BindGroup; 2]>,
+    /// Dynamic offsets for the view bind group.
+    offsets: Vec<u32>,
+    /// Pushed before every draw when the adapter supports push constants.
+    push_constants: Vec<u8>,
+}
+
+impl SimpletoonViewBindGroups {
+    /// The view bind group reading `source`, pass [`ViewTarget::main_texture_view`] before calling `post_process_write`.
+    pub fn view(&self, source: &TextureView) -> Option<&BindGroup> {
+        self.views.iter().find(|(id, _)| *id == source.id()).map(|(_, bind_group)| bind_group)
+    }
+
+    /// Dynamic offsets to bind [`SimpletoonViewBindGroups::view`] with, empty on the compatibility layout.
+    pub fn offsets(&self) -> &[u32] {
+        &self.offsets
+    }
+
+    /// Pushed before every draw, empty when the adapter has no push constants.
+    pub fn push_constants(&self) -> &[u8] {
+        &self.push_constants
+    }
+}
+
+const EDGE_MASK_FORMAT: TextureFormat = TextureFormat::R8Unorm;
+
+/// Intermediate edge masks for views that clean up, dilate or export their strokes, `scratch` holds the raw edges before
+/// cleanup and the horizontal dilation pass.
+#[derive(Component)]
+pub struct SimpletoonEdgeTextures {
+    mask: CachedTexture,
+    scratch: CachedTexture,
 }
 
 impl Plugin for SimpletoonPlugin {
     fn build(&self, app: &mut App) {
+        if let Some(label) = self.label {
+            assert!(
+                app.world().contains_resource::<SimpletoonWarmup>(),
+                "SimpletoonPlugin::labeled(\"{label}\") needs a SimpletoonPlugin without a label added before it"
+            );
+            let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+                return;
+            };
+            let node = ViewNodeRunner::new(SimpletoonInstanceNode { label }, render_app.world_mut());
+            let mut render_graph = render_app.world_mut().resource_mut::<RenderGraph>();
+            if let Some(graph) = render_graph.get_sub_graph_mut(Core3d) {
+                graph.add_node(SimpletoonInstanceLabel(label), node);
+            }
+            return;
+        }
+
         embedded_asset!(app, "assets/toon.wgsl");
+        // Always loaded, so custom materials can #import bevy_simpletoon::outline_mask by name
+        load_internal_asset!(app, OUTLINE_MASK_SHADER_HANDLE, "assets/toon_outline_mask.wgsl", Shader::from_wgsl);
         app.add_plugins((
-            ExtractComponentPlugin::<SimpletoonSettings>::default(),
-            UniformComponentPlugin::<SimpletoonSettings>::default(),
+            SyncComponentPlugin::<SimpletoonSettings>::default(),
+            SyncComponentPlugin::<ToonInstance>::default(),
+            ExtractComponentPlugin::<ToonEdgeMask>::default(),
+            ExtractComponentPlugin::<ToonScreenshot>::default(),
+            ExtractComponentPlugin::<ToonBakeCamera>::default(),
+            ExtractComponentPlugin::<ImpactFrame>::default(),
+            ExtractComponentPlugin::<ToonSpeedLines>::default(),
+            ExtractComponentPlugin::<ToonCompare>::default(),
+            ExtractComponentPlugin::<ToonDetailTexture>::default(),
         ));
+        #[cfg(feature = "banding")]
+        app.add_plugins((SimpletoonTransparentPlugin, SimpletoonBandAnchorPlugin, SimpletoonBandHistogramPlugin { detached: self.detached }));
+        app.add_plugins((SimpletoonEntityIdPlugin, SimpletoonVfxPlugin));
+        app.add_plugins((SimpletoonFaceShadowPlugin, SimpletoonLightBandsPlugin));
+        #[cfg(feature = "outlines")]
+        app.add_plugins(SimpletoonAutoThresholdsPlugin);
+        match self.quality {
+            Some(quality) => app.insert_resource(quality),
+            None => app.init_resource::<ToonQuality>(),
+        };
+        app.init_resource::<SettingsValidated>();
+        app.register_type::<SimpletoonSettings>();
+        let ready_pipelines = ReadyPipelines::default();
+        let warmup = SimpletoonWarmup::default();
+        app.insert_resource(warmup.clone());
+        app.add_event::<SimpletoonPipelineReady>()
+            .add_event::<SimpletoonSettingsChanged>()
+            .insert_resource(ready_pipelines.clone())
+            .add_systems(First, send_pipeline_ready_events)
+            .add_systems(Last, send_settings_changed_events.after(SimpletoonSystems::Settings));
+        app.init_resource::<ToonEdgePolylines>()
+            .add_systems(Last, (start_edge_readbacks, start_edge_queries, take_toon_screenshots))
+            .add_observer(trace_edge_readback)
+            .add_observer(store_edge_query_readback);
+        app.add_systems(Update, (start_toon_bakers, run_toon_bakers).chain());
+        app.add_systems(Last, run_impact_frames).add_observer(end_impact_frame);
+        app.add_systems(Last, (apply_toon_quality, smooth_toon_settings, validate_settings.in_set(SimpletoonSystems::Settings)).chain());
+        #[cfg(feature = "gltf")]
+        app.add_systems(PreUpdate, crate::gltf_extras::apply_gltf_extras);
 
         let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
             return;
         };
 
+        render_app
+            .insert_resource(ready_pipelines)
+            .insert_resource(PrecompileRequests {
+                views: self.precompile.iter().map(|(settings, hdr)| (SimpletoonUniform::from(settings), *hdr)).collect(),
+                block: self.block_until_compiled,
+                warmup,
+            })
+            .init_resource::<SimpletoonUniforms>()
+            .insert_resource(PassBeforeTonemapping(self.before_tonemapping))
+            .insert_resource(SimpletoonNormalEncoding(self.normal_encoding))
+            .init_resource::<ExtractedToonKeyLight>()
+            .add_systems(ExtractSchedule, (extract_settings, extract_instances, extract_toon_key_light).in_set(SimpletoonSystems::Extract))
+            .configure_sets(ExtractSchedule, SimpletoonSystems::Extract.after(extract_cameras))
+            .init_resource::<SimpletoonBindGroupCache>()
+            .init_resource::<ToonTexturePool>()
+            .init_resource::<SpecializedRenderPipelines<PostProcessPipeline>>()
+            .add_systems(
+                Render,
+                (
+                    (
+                        (precompile_pipelines, prepare_pipelines).in_set(RenderSet::Prepare),
+                        (prepare_frame_uniforms, prepare_settings_uniforms).chain().in_set(RenderSet::PrepareResources),
+                        prepare_edge_textures.in_set(RenderSet::PrepareResources),
+                        prepare_view_bind_groups.in_set(RenderSet::PrepareBindGroups),
+                    )
+                        .in_set(SimpletoonSystems::Prepare),
+                    (prune_bind_group_cache, prune_texture_pool).in_set(RenderSet::Cleanup),
+                ),
+            );
+        #[cfg(feature = "dev-hot-reload")]
+        if let Some(path) = self.shader_path {
+            render_app.insert_resource(SimpletoonShaderPath(path));
+        }
+        if self.detached {
+            return;
+        }
         render_app
             .add_render_graph_node::<ViewNodeRunner<SimpletoonPostProcessNode>>(
                 Core3d,
                 SimpletoonPostProcessLabel,
             )
+            .add_render_graph_node::<ViewNodeRunner<SimpletoonEmissiveStrokesNode>>(
+                Core3d,
+                SimpletoonEmissiveStrokesLabel,
+            )
             .add_render_graph_edges(
                 Core3d,
                 (
+                    Node3d::EndMainPass,
+                    SimpletoonEmissiveStrokesLabel,
+                    Node3d::Bloom,
                     Node3d::Tonemapping,
-                    SimpletoonPostProcessLabel,
-                    Node3d::Fxaa,
-                    Node3d::EndMainPassPostProcessing,
                 ),
             );
     }
@@ -83,8 +1278,549 @@ impl Plugin for SimpletoonPlugin {
             return;
         };
 
+        if let Some(label) = self.label {
+            match self.edges {
+                Some((before, after)) => render_app.add_render_graph_edges(Core3d, (before, SimpletoonInstanceLabel(label), after)),
+                None => render_app.add_render_graph_edges(
+                    Core3d,
+                    (
+                        Node3d::Tonemapping,
+                        SimpletoonInstanceLabel(label),
+                        Node3d::Fxaa,
+                        Node3d::EndMainPassPostProcessing,
+                    ),
+                ),
+            };
+            // Drawn over the camera's own pass when both sit between tonemapping and FXAA, and after its bands are
+            // counted. Neither node is in the graph when the unlabeled plugin is detached
+            if self.edges.is_none() {
+                let graph = render_app.world().resource::<RenderGraph>().get_sub_graph(Core3d);
+                let previous: Vec<InternedRenderLabel> = [
+                    SimpletoonPostProcessLabel.intern(),
+                    #[cfg(feature = "banding")]
+                    SimpletoonBandHistogramLabel.intern(),
+                ]
+                .into_iter()
+                .filter(|node| graph.is_some_and(|graph| graph.get_node_state(*node).is_ok()))
+                .collect();
+                for node in previous {
+                    render_app.add_render_graph_edge(Core3d, node, SimpletoonInstanceLabel(label));
+                }
+            }
+            return;
+        }
+
         render_app
             .init_resource::<PostProcessPipeline>();
+
+        if self.detached {
+            return;
+        }
+        match self.edges {
+            Some((before, after)) => render_app.add_render_graph_edges(Core3d, (before, SimpletoonPostProcessLabel, after)),
+            None => render_app.add_render_graph_edges(
+                Core3d,
+                (
+                    Node3d::Tonemapping,
+                    SimpletoonPostProcessLabel,
+                    Node3d::Fxaa,
+                    Node3d::EndMainPassPostProcessing,
+                ),
+            ),
+        };
+        // Counted before whatever follows the toon pass, so FXAA and the like don't blur the bands
+        #[cfg(feature = "banding")]
+        match self.edges {
+            Some((_, after)) => render_app.add_render_graph_edges(Core3d, (SimpletoonPostProcessLabel, SimpletoonBandHistogramLabel, after)),
+            None => render_app.add_render_graph_edges(Core3d, (SimpletoonPostProcessLabel, SimpletoonBandHistogramLabel, Node3d::Fxaa)),
+        };
+    }
+
+    // Labeled plugins add one pass each on top of the unlabeled one
+    fn is_unique(&self) -> bool {
+        self.label.is_none()
+    }
+}
+
+type ExtractedSettings = (
+    RenderEntity,
+    Ref<'static, SimpletoonSettings>,
+    Option<Ref<'static, DepthOfField>>,
+    Option<Ref<'static, Projection>>,
+    Option<Ref<'static, Tonemapping>>,
+);
+
+/// Only copies settings into the render world when they have changed, the render world keeps the rest from previous frames.
+/// Removing any synced component clears the whole render entity, so anything missing its uniform is re-extracted too.
+fn extract_settings(
+    mut commands: Commands,
+    // The pass is only in the 3d graph, a 2d or UI camera sharing the target must not get its bind groups and textures
+    query: Extract<Query<ExtractedSettings, With<Camera3d>>>,
+    main_ticks: Extract<SystemChangeTick>,
+    validated: Extract<Res<SettingsValidated>>,
+    before_tonemapping: Res<PassBeforeTonemapping>,
+    extracted: Query<(), With<SimpletoonUniform>>,
+) {
+    for (entity, settings, depth_of_field, projection, tonemapping) in &query {
+        // Validated next frame, so until then the GPU would see unclamped settings and then jump to the clamped ones
+        if settings.last_changed().is_newer_than(validated.0, main_ticks.this_run()) {
+            warn_once!("SimpletoonSettings were changed after SimpletoonSystems::Settings, order the system before it to avoid a frame of unvalidated settings");
+        }
+        let camera_changed = depth_of_field.as_ref().is_some_and(|dof| dof.is_changed())
+            || projection.as_ref().is_some_and(|projection| projection.is_changed())
+            || tonemapping.as_ref().is_some_and(|tonemapping| tonemapping.is_changed());
+        if settings.is_changed() || camera_changed || !extracted.contains(entity) {
+            let uniform = settings_uniform(&settings, depth_of_field.as_deref(), projection.as_deref(), tonemapping.as_deref(), before_tonemapping.0);
+            commands.entity(entity).insert(uniform);
+        }
+    }
+}
+
+fn settings_uniform(
+    settings: &SimpletoonSettings,
+    depth_of_field: Option<&DepthOfField>,
+    projection: Option<&Projection>,
+    tonemapping: Option<&Tonemapping>,
+    before_tonemapping: bool,
+) -> SimpletoonUniform {
+    let mut uniform = SimpletoonUniform::from(settings);
+    // Depth of field only works with a perspective projection
+    if let (StrokeDepthOfField::Attenuate, Some(depth_of_field), Some(Projection::Perspective(projection))) =
+        (settings.stroke_depth_of_field, depth_of_field, projection)
+    {
+        uniform.set_depth_of_field(depth_of_field, projection);
+    }
+    if let (BandSpacing::Perceptual, Some(tonemapping), false) = (settings.band_spacing, tonemapping, before_tonemapping) {
+        uniform.set_band_shoulder(*tonemapping);
+    }
+    uniform
+}
+
+type ExtractedInstance = (RenderEntity, Ref<'static, SimpletoonSettings>, &'static ToonInstance, &'static ChildOf);
+
+type InstanceCamera = (
+    RenderEntity,
+    Option<Ref<'static, DepthOfField>>,
+    Option<Ref<'static, Projection>>,
+    Option<Ref<'static, Tonemapping>>,
+);
+
+/// Gives each [`ToonInstance`] its own settings uniform, drawn by its label's pass on the parent camera's view. Render
+/// entities of instances that are no longer under a 3d camera lose both again.
+fn extract_instances(
+    mut commands: Commands,
+    instances: Extract<Query<ExtractedInstance>>,
+    cameras: Extract<Query<InstanceCamera, With<Camera3d>>>,
+    before_tonemapping: Res<PassBeforeTonemapping>,
+    extracted: Query<(), With<SimpletoonUniform>>,
+    views: Query<Entity, With<SimpletoonViewInstances>>,
+    previous: Query<Entity, With<SimpletoonInstanceOf>>,
+) {
+    let mut view_instances = HashMap::<Entity, Vec<(&'static str, Entity)>>::default();
+    for (entity, settings, instance, child_of) in &instances {
+        let Ok((view, depth_of_field, projection, tonemapping)) = cameras.get(child_of.parent()) else {
+            warn_once!("ToonInstance(\"{}\") is ignored, it must be the child of a 3d camera", instance.0);
+            continue;
+        };
+        let camera_changed = depth_of_field.as_ref().is_some_and(|dof| dof.is_changed())
+            || projection.as_ref().is_some_and(|projection| projection.is_changed())
+            || tonemapping.as_ref().is_some_and(|tonemapping| tonemapping.is_changed());
+        if settings.is_changed() || camera_changed || !extracted.contains(entity) {
+            let uniform = settings_uniform(&settings, depth_of_field.as_deref(), projection.as_deref(), tonemapping.as_deref(), before_tonemapping.0);
+            commands.entity(entity).insert(uniform);
+        }
+        commands.entity(entity).insert(SimpletoonInstanceOf(view));
+        view_instances.entry(view).or_default().push((instance.0, entity));
+    }
+    for view in &views {
+        if !view_instances.contains_key(&view) {
+            commands.entity(view).remove::<SimpletoonViewInstances>();
+        }
+    }
+    for entity in &previous {
+        if !view_instances.values().flatten().any(|(_, instance)| *instance == entity) {
+            commands.entity(entity).remove::<(SimpletoonInstanceOf, SimpletoonUniform)>();
+        }
+    }
+    for (view, instances) in view_instances {
+        commands.entity(view).insert(SimpletoonViewInstances(instances));
+    }
+}
+
+fn send_pipeline_ready_events(ready_pipelines: Res<ReadyPipelines>, mut events: EventWriter<SimpletoonPipelineReady>) {
+    let ready: Vec<Entity> = ready_pipelines.0.lock().unwrap().drain(..).collect();
+    events.write_batch(ready.into_iter().map(|camera| SimpletoonPipelineReady { camera }));
+}
+
+fn send_settings_changed_events(query: Query<Entity, Changed<SimpletoonSettings>>, mut events: EventWriter<SimpletoonSettingsChanged>) {
+    events.write_batch(query.iter().map(|camera| SimpletoonSettingsChanged { camera }));
+}
+
+type FrameUniformView = (
+    &'static mut SimpletoonUniform,
+    Has<ToonTransparentBackground>,
+    Option<&'static ExtractedImpactFrame>,
+    Option<&'static ToonSpeedLines>,
+    Option<&'static ToonCompare>,
+    Option<&'static SimpletoonInstanceOf>,
+);
+
+/// Copies what can change every frame into each view's settings, extract_settings only rebuilds them when the settings change.
+fn prepare_frame_uniforms(
+    key_light: Res<ExtractedToonKeyLight>,
+    time: Res<Time>,
+    mut query: Query<FrameUniformView>,
+    transparent_backgrounds: Query<(), With<ToonTransparentBackground>>,
+) {
+    for (mut uniform, transparent_background, impact, speed_lines, compare, instance) in &mut query {
+        // Instances keep the alpha of a camera taking a transparent screenshot too
+        let transparent_background = transparent_background || instance.is_some_and(|instance| transparent_backgrounds.contains(instance.0));
+        if uniform.key_light != key_light.position || uniform.key_light_colour != key_light.colour {
+            uniform.key_light = key_light.position;
+            uniform.key_light_colour = key_light.colour;
+        }
+        if uniform.transparent_background != transparent_background as u32 {
+            uniform.transparent_background = transparent_background as u32;
+        }
+        let impact = impact.map_or((0, 0.0, 0.0, 0.0, Vec2::ZERO), |impact| {
+            (1, impact.lines, impact.inner_radius, impact.seed, impact.centre)
+        });
+        if (uniform.impact, uniform.impact_lines, uniform.impact_inner_radius, uniform.impact_seed, uniform.impact_centre) != impact {
+            (uniform.impact, uniform.impact_lines, uniform.impact_inner_radius, uniform.impact_seed, uniform.impact_centre) = impact;
+        }
+        if uniform.stroke_animation != 0 {
+            uniform.time = time.elapsed_secs_wrapped();
+        }
+        let speed_lines = match speed_lines {
+            // Only as often as the lines are redrawn, so the upload is still skipped on the frames between
+            Some(lines) => (
+                1,
+                [lines.count as f32, lines.length, lines.inner_radius, lines.jitter, lines.width, (time.elapsed_secs_wrapped() * lines.jitter_rate).floor()],
+                lines.centre,
+                lines.colour.to_linear().to_vec4(),
+            ),
+            None => (0, [0.0; 6], Vec2::ZERO, Vec4::ZERO),
+        };
+        let current = (
+            uniform.speed_lines,
+            [
+                uniform.speed_line_count,
+                uniform.speed_line_length,
+                uniform.speed_line_inner_radius,
+                uniform.speed_line_jitter,
+                uniform.speed_line_width,
+                uniform.speed_line_seed,
+            ],
+            uniform.speed_line_centre,
+            uniform.speed_line_colour,
+        );
+        if current != speed_lines {
+            let (enabled, [count, length, inner_radius, jitter, width, seed], centre, colour) = speed_lines;
+            uniform.speed_lines = enabled;
+            uniform.speed_line_count = count;
+            uniform.speed_line_length = length;
+            uniform.speed_line_inner_radius = inner_radius;
+            uniform.speed_line_jitter = jitter;
+            uniform.speed_line_width = width;
+            uniform.speed_line_seed = seed;
+            uniform.speed_line_centre = centre;
+            uniform.speed_line_colour = colour;
+        }
+        let compare = compare.map_or((0, 0.0), |compare| (1, compare.split));
+        if (uniform.compare, uniform.compare_split) != compare {
+            (uniform.compare, uniform.compare_split) = compare;
+        }
+    }
+}
+
+/// Rewrites the settings uniform buffer, skipping the upload when no camera's settings have changed.
+fn prepare_settings_uniforms(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+    mut uniforms: ResMut<SimpletoonUniforms>,
+    query: Query<(Entity, Ref<SimpletoonUniform>)>,
+) {
+    let unchanged = query.iter().len() == uniforms.entities.len()
+        && query
+            .iter()
+            .zip(&uniforms.entities)
+            .all(|((entity, settings), previous)| entity == *previous && !settings.is_changed());
+    if unchanged {
+        return;
+    }
+
+    let uniforms = uniforms.as_mut();
+    uniforms.buffer.clear();
+    uniforms.entities.clear();
+    for (entity, settings) in &query {
+        let index = uniforms.buffer.push(&*settings);
+        uniforms.entities.push(entity);
+        commands.entity(entity).insert(SimpletoonUniformIndex(index));
+    }
+    uniforms.buffer.write_buffer(&render_device, &render_queue);
+}
+
+type PipelinePass = (
+    Entity,
+    &'static MainEntity,
+    &'static SimpletoonUniform,
+    Has<SimpletoonPipelinesReady>,
+    Option<&'static SimpletoonInstanceOf>,
+);
+
+type PipelineView = (&'static ExtractedView, Has<ToonEdgeMask>, Option<&'static Msaa>);
+
+fn prepare_pipelines(
+    mut commands: Commands,
+    pipeline_cache: Res<PipelineCache>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<PostProcessPipeline>>,
+    post_process_pipeline: Res<PostProcessPipeline>,
+    ready_pipelines: Res<ReadyPipelines>,
+    passes: Query<PipelinePass>,
+    views: Query<PipelineView>,
+) {
+    for (entity, main_entity, settings, was_ready, instance) in &passes {
+        let Ok((view, exported, msaa)) = views.get(instance.map_or(entity, |instance| instance.0)) else {
+            continue;
+        };
+        // Only the camera's own pass exports its edge mask
+        let exported = exported && instance.is_none();
+        // Only the outlines read the prepass textures
+        let multisampled_prepass = cfg!(feature = "outlines") && msaa.is_some_and(|msaa| msaa.samples() > 1);
+        let ids = specialize_view(&mut pipelines, &pipeline_cache, &post_process_pipeline, settings, view.hdr, exported, multisampled_prepass);
+        let ready = ids.iter().all(|id| pipeline_cache.get_render_pipeline(id).is_some());
+        match (ready, was_ready) {
+            (true, false) => {
+                ready_pipelines.0.lock().unwrap().push(main_entity.id());
+                commands.entity(entity).insert(SimpletoonPipelinesReady);
+            }
+            (false, true) => {
+                commands.entity(entity).remove::<SimpletoonPipelinesReady>();
+            }
+            _ => {}
+        }
+        commands.entity(entity).insert(ids);
+    }
+}
+
+/// The pipelines every pass of a view with these settings needs.
+fn specialize_view(
+    pipelines: &mut SpecializedRenderPipelines<PostProcessPipeline>,
+    pipeline_cache: &PipelineCache,
+    post_process_pipeline: &PostProcessPipeline,
+    settings: &SimpletoonUniform,
+    hdr: bool,
+    exported: bool,
+    multisampled_prepass: bool,
+) -> SimpletoonPipelineIds {
+    let key = |pass| SimpletoonPipelineKey { pass, hdr, multisampled_prepass };
+    let mut specialize = |pass| pipelines.specialize(pipeline_cache, post_process_pipeline, key(pass));
+    let uses_edge_mask = uses_edge_mask(settings, exported);
+    let dilates = cfg!(feature = "outlines") && settings.stroke_dilation != 0;
+    let cleans_up = cfg!(feature = "outlines") && settings.stroke_cleanup != 0;
+    let emissive = cfg!(feature = "outlines") && settings.emissive_strokes > 0.0;
+    SimpletoonPipelineIds {
+        main: specialize(if uses_edge_mask { SimpletoonPass::Composite } else { SimpletoonPass::Single }),
+        edge: uses_edge_mask.then(|| specialize(SimpletoonPass::Edge)),
+        cleanup: cleans_up.then(|| specialize(SimpletoonPass::Cleanup)),
+        dilate: dilates.then(|| [specialize(SimpletoonPass::DilateX), specialize(SimpletoonPass::DilateY)]),
+        emissive_strokes: emissive.then(|| specialize(SimpletoonPass::EmissiveStrokes)),
+    }
+}
+
+/// Specializes the declared pipelines every frame until they have all compiled, the cache makes repeats free.
+fn precompile_pipelines(
+    mut pipeline_cache: ResMut<PipelineCache>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<PostProcessPipeline>>,
+    post_process_pipeline: Res<PostProcessPipeline>,
+    requests: Res<PrecompileRequests>,
+) {
+    if requests.warmup.is_ready() {
+        return;
+    }
+    let ids: Vec<CachedRenderPipelineId> = requests
+        .views
+        .iter()
+        .flat_map(|(settings, hdr)| specialize_view(&mut pipelines, &pipeline_cache, &post_process_pipeline, settings, *hdr, false, false).iter().collect::<Vec<_>>())
+        .collect();
+    if requests.block {
+        pipeline_cache.process_queue();
+        // Pipelines still waiting for their shader can't be blocked on yet
+        for id in &ids {
+            if matches!(pipeline_cache.get_render_pipeline_state(*id), CachedPipelineState::Creating(_)) {
+                pipeline_cache.block_on_render_pipeline(*id);
+            }
+        }
+    }
+    let ready = ids.iter().all(|id| pipeline_cache.get_render_pipeline(*id).is_some());
+    requests.warmup.ready.store(ready, Ordering::Relaxed);
+}
+
+/// Strokes go through the edge mask when they are dilated or exported, there are none to mask without `outlines`.
+fn uses_edge_mask(settings: &SimpletoonUniform, exported: bool) -> bool {
+    cfg!(feature = "outlines") && (settings.stroke_dilation != 0 || settings.stroke_cleanup != 0 || exported)
+}
+
+fn prepare_edge_textures(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    mut texture_pool: ResMut<ToonTexturePool>,
+    passes: Query<(Entity, &SimpletoonUniform, Option<&SimpletoonInstanceOf>)>,
+    views: Query<(&ExtractedCamera, Has<ToonEdgeMask>)>,
+) {
+    for (entity, settings, instance) in &passes {
+        let view = views.get(instance.map_or(entity, |instance| instance.0)).ok();
+        let size = view.and_then(|(camera, exported)| camera.physical_target_size.filter(|_| uses_edge_mask(settings, exported && instance.is_none())));
+        let Some(size) = size else {
+            commands.entity(entity).remove::<SimpletoonEdgeTextures>();
+            continue;
+        };
+        let descriptor = |label| TextureDescriptor {
+            label: Some(label),
+            size: Extent3d {
+                width: size.x,
+                height: size.y,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: TextureDimension::D2,
+            format: EDGE_MASK_FORMAT,
+            usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_SRC,
+            view_formats: &[],
+        };
+        commands.entity(entity).insert(SimpletoonEdgeTextures {
+            mask: texture_pool.get(&render_device, descriptor("simpletoon_edge_mask")),
+            scratch: texture_pool.get(&render_device, descriptor("simpletoon_edge_mask_scratch")),
+        });
+    }
+}
+
+fn prune_bind_group_cache(mut cache: ResMut<SimpletoonBindGroupCache>) {
+    cache
+        .entries
+        .get_mut()
+        .unwrap()
+        .retain(|_, (_, used)| core::mem::take(used));
+}
+
+type ValidatedSettings = (Entity, &'static mut SimpletoonSettings, Option<&'static Camera>, Has<Camera3d>);
+
+/// When [`SimpletoonSystems::Settings`] last ran, so extraction can tell settings edited after it apart.
+#[derive(Resource, Default)]
+struct SettingsValidated(Tick);
+
+/// Clamps invalid settings before they reach the GPU, where they would otherwise just produce a black screen.
+fn validate_settings(
+    ticks: SystemChangeTick,
+    mut validated: ResMut<SettingsValidated>,
+    mut query: Query<ValidatedSettings, Changed<SimpletoonSettings>>,
+) {
+    validated.0 = ticks.this_run();
+    let defaults = SimpletoonSettings::default();
+    for (entity, mut settings, camera, is_3d) in &mut query {
+        if camera.is_some() && !is_3d {
+            warn!("SimpletoonSettings on {entity} are ignored, the toon pass only runs on 3d cameras");
+        }
+        let mut fixed = *settings;
+        let clamp = |name: &str, value: &mut f32, min: f32, max: f32, fallback: f32| {
+            let clamped = if value.is_nan() { fallback } else { value.clamp(min, max) };
+            if clamped != *value {
+                warn!("SimpletoonSettings::{name} on {entity} was {value}, using {clamped}");
+                *value = clamped;
+            }
+        };
+        clamp("depth_threshold", &mut fixed.depth_threshold, 0.0, f32::MAX, defaults.depth_threshold);
+        clamp("depth_threshold_depth_mul", &mut fixed.depth_threshold_depth_mul, 0.0, f32::MAX, defaults.depth_threshold_depth_mul);
+        clamp("depth_normal_threshold", &mut fixed.depth_normal_threshold, 0.0, 0.999, defaults.depth_normal_threshold);
+        clamp("depth_normal_threshold_mul_along", &mut fixed.depth_normal_threshold_mul_along, 0.0, f32::MAX, defaults.depth_normal_threshold_mul_along);
+        clamp("depth_normal_threshold_mul_across", &mut fixed.depth_normal_threshold_mul_across, 0.0, f32::MAX, defaults.depth_normal_threshold_mul_across);
+        clamp("normal_threshold", &mut fixed.normal_threshold, 0.0, f32::MAX, defaults.normal_threshold);
+        clamp("colour_threshold", &mut fixed.colour_threshold, 0.0, f32::MAX, defaults.colour_threshold);
+        clamp("stroke_size", &mut fixed.stroke_size, 0.0, f32::MAX, defaults.stroke_size);
+        clamp("colour_banding", &mut fixed.colour_banding, 1.0, f32::MAX, defaults.colour_banding);
+        clamp("effect_strength", &mut fixed.effect_strength, 0.0, 1.0, defaults.effect_strength);
+        clamp("emissive_strokes", &mut fixed.emissive_strokes, 0.0, f32::MAX, defaults.emissive_strokes);
+        clamp("chroma_threshold", &mut fixed.chroma_threshold, 0.0, f32::MAX, defaults.chroma_threshold);
+        clamp("line_breakup", &mut fixed.line_breakup, 0.0, 1.0, defaults.line_breakup);
+        clamp("line_breakup_scale", &mut fixed.line_breakup_scale, 0.001, f32::MAX, defaults.line_breakup_scale);
+        clamp("line_breakup_contrast", &mut fixed.line_breakup_contrast, 0.0, f32::MAX, defaults.line_breakup_contrast);
+        clamp("id_edge_depth", &mut fixed.id_edge_depth, 0.0, f32::MAX, defaults.id_edge_depth);
+        clamp("shadow_edge_threshold", &mut fixed.shadow_edge_threshold, 0.0, 1.0, defaults.shadow_edge_threshold);
+        clamp("dash_length", &mut fixed.dash_length, 0.0, f32::MAX, defaults.dash_length);
+        clamp("dash_gap", &mut fixed.dash_gap, 0.0, f32::MAX, defaults.dash_gap);
+        clamp("gooch_alpha", &mut fixed.gooch_alpha, 0.0, 1.0, defaults.gooch_alpha);
+        clamp("gooch_beta", &mut fixed.gooch_beta, 0.0, 1.0, defaults.gooch_beta);
+        clamp("band_exposure", &mut fixed.band_exposure, 0.001, f32::MAX, defaults.band_exposure);
+        clamp("screen_edge_margin", &mut fixed.screen_edge_margin, 0.0, f32::MAX, defaults.screen_edge_margin);
+        if let Some(threshold) = &mut fixed.normal_detail_threshold {
+            clamp("normal_detail_threshold", threshold, 0.0, f32::MAX, defaults.normal_threshold);
+        }
+        if let Some(blueprint) = &mut fixed.blueprint {
+            let grid_defaults = BlueprintGrid::default();
+            clamp("blueprint.spacing", &mut blueprint.spacing, 1.0, f32::MAX, grid_defaults.spacing);
+            clamp("blueprint.line_width", &mut blueprint.line_width, 0.0, f32::MAX, grid_defaults.line_width);
+        }
+        if let Some(stroke_animation) = &mut fixed.stroke_animation {
+            let animation_defaults = StrokeAnimation::default();
+            clamp("stroke_animation.pulse_amplitude", &mut stroke_animation.pulse_amplitude, 0.0, 1.0, animation_defaults.pulse_amplitude);
+            clamp("stroke_animation.pulse_frequency", &mut stroke_animation.pulse_frequency, 0.0, f32::MAX, animation_defaults.pulse_frequency);
+        }
+        if let Some(sky) = &mut fixed.sky {
+            clamp("sky.bands", &mut sky.bands, 0.0, f32::MAX, ToonSky::default().bands);
+        }
+        if let Some(detail) = &mut fixed.detail {
+            let detail_defaults = DetailMask::default();
+            clamp("detail.world_size", &mut detail.world_size, 0.001, f32::MAX, detail_defaults.world_size);
+            clamp("detail.low_detail_bands", &mut detail.low_detail_bands, 1.0, f32::MAX, detail_defaults.low_detail_bands);
+            clamp("detail.low_detail_threshold_mul", &mut detail.low_detail_threshold_mul, 0.0, f32::MAX, detail_defaults.low_detail_threshold_mul);
+        }
+        if let Some(terrain) = &mut fixed.terrain {
+            let terrain_defaults = TerrainBands::default();
+            clamp("terrain.height_range", &mut terrain.height_range, 0.001, f32::MAX, terrain_defaults.height_range);
+            clamp("terrain.height_bands", &mut terrain.height_bands, 1.0, f32::MAX, terrain_defaults.height_bands);
+            clamp("terrain.slope_bands", &mut terrain.slope_bands, 1.0, f32::MAX, terrain_defaults.slope_bands);
+        }
+        if let Some(channel_banding) = &mut fixed.channel_banding {
+            let channel_defaults = ChannelBanding::default();
+            clamp("channel_banding.bands.x", &mut channel_banding.bands.x, 1.0, f32::MAX, channel_defaults.bands.x);
+            clamp("channel_banding.bands.y", &mut channel_banding.bands.y, 1.0, f32::MAX, channel_defaults.bands.y);
+            clamp("channel_banding.bands.z", &mut channel_banding.bands.z, 1.0, f32::MAX, channel_defaults.bands.z);
+        }
+        clamp("banding_range.x", &mut fixed.banding_range.x, 0.0, f32::MAX, defaults.banding_range.x);
+        let min_luminance = fixed.banding_range.x;
+        clamp("banding_range.y", &mut fixed.banding_range.y, min_luminance, f32::MAX, defaults.banding_range.y);
+        clamp("emissive_range.x", &mut fixed.emissive_range.x, 0.0, f32::MAX, defaults.emissive_range.x);
+        let fade_start = fixed.emissive_range.x;
+        clamp("emissive_range.y", &mut fixed.emissive_range.y, fade_start, f32::MAX, defaults.emissive_range.y);
+        if !(1..=4).contains(&fixed.kernel_radius) {
+            let clamped = fixed.kernel_radius.clamp(1, 4);
+            warn!("SimpletoonSettings::kernel_radius on {entity} was {}, using {clamped}", fixed.kernel_radius);
+            fixed.kernel_radius = clamped;
+        }
+        if fixed.emissive_strokes > 0.0 && !camera.is_some_and(|camera| camera.hdr) {
+            warn!("SimpletoonSettings::emissive_strokes on {entity} needs Camera::hdr to glow");
+        }
+        if !fixed.stroke_colour.to_vec4().is_finite() {
+            warn!("SimpletoonSettings::stroke_colour on {entity} was {:?}, using {:?}", fixed.stroke_colour, defaults.stroke_colour);
+            fixed.stroke_colour = defaults.stroke_colour;
+        }
+        if fixed.edge_alpha && fixed.preserve_alpha {
+            warn!("SimpletoonSettings::edge_alpha on {entity} writes over the alpha preserve_alpha keeps, using preserve_alpha: false");
+            fixed.preserve_alpha = false;
+        }
+        if fixed.shading == ToonShading::LineArt && !cfg!(feature = "outlines") {
+            warn!("SimpletoonSettings::shading on {entity} is ToonShading::LineArt, which has no strokes to draw without the outlines feature");
+        }
+        if !fixed.line_art_background.is_finite() {
+            warn!("SimpletoonSettings::line_art_background on {entity} was {}, using {}", fixed.line_art_background, defaults.line_art_background);
+            fixed.line_art_background = defaults.line_art_background;
+        }
+
+        if fixed != *settings {
+            *settings = fixed;
+        }
     }
 }
 
@@ -95,133 +1831,649 @@ impl ViewNode for SimpletoonPostProcessNode {
     // This query will only run on the view entity
     type ViewQuery = (
         &'static ViewTarget,
-        &'static ViewPrepassTextures,
-        // This makes sure the node only runs on cameras with the PostProcessSettings component
-        &'static SimpletoonSettings,
-        // As there could be multiple post processing components sent to the GPU (one per camera),
-        // we need to get the index of the one that is associated with the current view.
-        &'static DynamicUniformIndex<SimpletoonSettings>,
-        &'static ViewUniformOffset,
+        // This makes sure the node only runs on cameras with the SimpletoonSettings component
+        &'static SimpletoonUniform,
+        &'static SimpletoonPipelineIds,
+        // Built for this view by prepare_view_bind_groups, along with its uniform offsets
+        &'static SimpletoonViewBindGroups,
+        // Only present when strokes are dilated or exported from an intermediate edge mask
+        Option<&'static SimpletoonEdgeTextures>,
+        Option<&'static ToonEdgeMask>,
     );
 
     fn run(
         &self,
         _graph: &mut RenderGraphContext,
         render_context: &mut RenderContext,
-        (view_target, prepass_textures, _post_process_settings, settings_index, view_uniform): QueryItem<Self::ViewQuery>,
+        (view_target, settings, pipeline_ids, bind_groups, edge_textures, edge_mask_export): QueryItem<Self::ViewQuery>,
         world: &World,
     ) -> Result<(), NodeRunError> {
+        draw_toon_pass(render_context, world, view_target, settings, pipeline_ids, bind_groups, edge_textures, edge_mask_export);
+        Ok(())
+    }
+}
 
-        let post_process_pipeline = world.resource::<PostProcessPipeline>();
-
-        let pipeline_cache = world.resource::<PipelineCache>();
+impl ViewNode for SimpletoonInstanceNode {
+    type ViewQuery = (&'static ViewTarget, &'static SimpletoonViewInstances);
 
-        let Some(pipeline) = pipeline_cache.get_render_pipeline(post_process_pipeline.pipeline_id)
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (view_target, instances): QueryItem<Self::ViewQuery>,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let Some(instance) = instances.0.iter().find(|(label, _)| *label == self.label).and_then(|(_, entity)| world.get_entity(*entity).ok()) else {
+            return Ok(());
+        };
+        let (Some(settings), Some(pipeline_ids), Some(bind_groups)) =
+            (instance.get::<SimpletoonUniform>(), instance.get::<SimpletoonPipelineIds>(), instance.get::<SimpletoonViewBindGroups>())
         else {
             return Ok(());
         };
+        draw_toon_pass(render_context, world, view_target, settings, pipeline_ids, bind_groups, instance.get(), None);
+        Ok(())
+    }
+}
+
+/// Records every pass of one toon pass over the view, for the camera's own settings or a [`ToonInstance`]'s.
+#[allow(clippy::too_many_arguments)]
+fn draw_toon_pass(
+    render_context: &mut RenderContext,
+    world: &World,
+    view_target: &ViewTarget,
+    settings: &SimpletoonUniform,
+    pipeline_ids: &SimpletoonPipelineIds,
+    bind_groups: &SimpletoonViewBindGroups,
+    edge_textures: Option<&SimpletoonEdgeTextures>,
+    edge_mask_export: Option<&ToonEdgeMask>,
+) {
+    let pipeline_cache = world.resource::<PipelineCache>();
+
+    let Some(pipeline) = pipeline_cache.get_render_pipeline(pipeline_ids.main) else {
+        return;
+    };
+
+    let Some(view_bind_group) = bind_groups.view(view_target.main_texture_view()) else {
+        return;
+    };
+    let view_offsets = bind_groups.offsets.as_slice();
+    let push_constants = bind_groups.push_constants.as_slice();
+
+    // Strokes are drawn into an edge mask first when they need dilating or exporting
+    let composite_mask = match (edge_textures, &bind_groups.masks) {
+        (Some(edge_textures), Some([mask, scratch])) => {
+            let Some(edge_pipeline) = pipeline_ids.edge.and_then(|id| pipeline_cache.get_render_pipeline(id)) else {
+                return;
+            };
 
-        let settings_uniforms = world.resource::<ComponentUniforms<SimpletoonSettings>>();
-        let view_uniforms = world.resource::<ViewUniforms>();
-        let Some(view_uniforms) = view_uniforms.uniforms.binding() else {
+            // Cleaning up reads the raw edges from the scratch texture and writes them back to the mask
+            if settings.stroke_cleanup != 0 {
+                let Some(cleanup_pipeline) = pipeline_ids.cleanup.and_then(|id| pipeline_cache.get_render_pipeline(id)) else {
+                    return;
+                };
+                draw_fullscreen(render_context, "simpletoon_edge_pass", edge_pipeline, &edge_textures.scratch.default_view, &[(view_bind_group, view_offsets)], push_constants);
+                draw_fullscreen(render_context, "simpletoon_cleanup_pass", cleanup_pipeline, &edge_textures.mask.default_view, &[(view_bind_group, view_offsets), (scratch, &[])], push_constants);
+            } else {
+                draw_fullscreen(render_context, "simpletoon_edge_pass", edge_pipeline, &edge_textures.mask.default_view, &[(view_bind_group, view_offsets)], push_constants);
+            }
+
+            // Thick strokes are detected as thin edges, then grown with a separable dilation
+            if settings.stroke_dilation != 0 {
+                let Some([Some(dilate_x_pipeline), Some(dilate_y_pipeline)]) =
+                    pipeline_ids.dilate.map(|ids| ids.map(|id| pipeline_cache.get_render_pipeline(id)))
+                else {
+                    return;
+                };
+                draw_fullscreen(render_context, "simpletoon_dilate_x_pass", dilate_x_pipeline, &edge_textures.scratch.default_view, &[(view_bind_group, view_offsets), (mask, &[])], push_constants);
+                draw_fullscreen(render_context, "simpletoon_dilate_y_pass", dilate_y_pipeline, &edge_textures.mask.default_view, &[(view_bind_group, view_offsets), (scratch, &[])], push_constants);
+            }
+
+            if let Some(export) = edge_mask_export {
+                copy_edge_mask(render_context, world, &edge_textures.mask, export);
+            }
+            Some(mask)
+        }
+        _ => None,
+    };
+
+    let post_process = view_target.post_process_write();
+
+    match composite_mask {
+        Some(mask) => draw_fullscreen(render_context, "post_process_pass", pipeline, post_process.destination, &[(view_bind_group, view_offsets), (mask, &[])], push_constants),
+        None => draw_fullscreen(render_context, "post_process_pass", pipeline, post_process.destination, &[(view_bind_group, view_offsets)], push_constants),
+    }
+}
+
+impl ViewNode for SimpletoonEmissiveStrokesNode {
+    type ViewQuery = (
+        &'static ViewTarget,
+        &'static SimpletoonPipelineIds,
+        &'static SimpletoonViewBindGroups,
+    );
+
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (view_target, pipeline_ids, bind_groups): QueryItem<Self::ViewQuery>,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let Some(pipeline) = pipeline_ids
+            .emissive_strokes
+            .and_then(|id| world.resource::<PipelineCache>().get_render_pipeline(id))
+        else {
             return Ok(());
         };
-        let Some(settings_binding) = settings_uniforms.uniforms().binding() else {
+        let Some(view_bind_group) = bind_groups.view(view_target.main_texture_view()) else {
             return Ok(());
         };
+
+        let post_process = view_target.post_process_write();
+        draw_fullscreen(render_context, "simpletoon_emissive_strokes_pass", pipeline, post_process.destination, &[(view_bind_group, &bind_groups.offsets)], &bind_groups.push_constants);
+
+        Ok(())
+    }
+}
+
+type BindGroupPass = (
+    Entity,
+    &'static SimpletoonUniform,
+    &'static SimpletoonUniformIndex,
+    Option<&'static SimpletoonEdgeTextures>,
+    Option<&'static SimpletoonInstanceOf>,
+);
+
+type BindGroupView = (
+    &'static ViewTarget,
+    Option<&'static ViewPrepassTextures>,
+    &'static ViewUniformOffset,
+    Option<&'static ScreenSpaceAmbientOcclusionResources>,
+    Option<&'static SimpletoonEntityIdTexture>,
+    Option<&'static ViewShadowBindings>,
+    Option<&'static ViewLightsUniformOffset>,
+    Option<&'static ToonVfxMask>,
+    Option<&'static ToonDetailTexture>,
+);
+
+/// Builds the bind groups every pass of a view uses, so the nodes only record draws.
+#[allow(clippy::too_many_arguments)]
+#[cfg_attr(not(all(feature = "outlines", feature = "banding")), allow(unused_variables))]
+fn prepare_view_bind_groups(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    post_process_pipeline: Res<PostProcessPipeline>,
+    settings_uniforms: Res<SimpletoonUniforms>,
+    view_uniforms: Res<ViewUniforms>,
+    entity_overrides: Res<SimpletoonEntityOverrides>,
+    bind_group_cache: Res<SimpletoonBindGroupCache>,
+    fallback_image: Res<FallbackImage>,
+    images: Res<RenderAssets<GpuImage>>,
+    #[cfg(feature = "outlines")] light_meta: Res<LightMeta>,
+    #[cfg(feature = "outlines")] shadow_samplers: Res<ShadowSamplers>,
+    passes: Query<BindGroupPass, With<SimpletoonPipelineIds>>,
+    views: Query<BindGroupView>,
+) {
+    let (Some(view_buffer), Some(settings_buffer), Some(overrides_buffer)) =
+        (view_uniforms.uniforms.buffer(), settings_uniforms.buffer.buffer(), entity_overrides.overrides.buffer())
+    else {
+        return;
+    };
+    for (entity, settings, settings_index, edge_textures, instance) in &passes {
+        let Ok((view_target, prepass_textures, view_uniform, ssao, entity_ids, shadows, lights, vfx_mask, detail)) =
+            views.get(instance.map_or(entity, |instance| instance.0))
+        else {
+            commands.entity(entity).remove::<SimpletoonViewBindGroups>();
+            continue;
+        };
+        #[cfg(feature = "outlines")]
         let (Some(depth_texture), Some(normal_texture)) =
-            (&prepass_textures.depth, &prepass_textures.normal)
+            (prepass_textures.and_then(|textures| textures.depth.as_ref()), prepass_textures.and_then(|textures| textures.normal.as_ref()))
         else {
-            println!("could not find depth or normal");
-            return Ok(());
+            warn_once!("SimpletoonSettings on {entity} are skipped, the camera needs a DepthPrepass and NormalPrepass");
+            commands.entity(entity).remove::<SimpletoonViewBindGroups>();
+            continue;
         };
+        // Bevy's prepare_lights gives every 3d view its lights and shadow maps, missing they mean the view isn't lit
+        #[cfg(feature = "outlines")]
+        let shadows = match (light_meta.view_gpu_lights.buffer(), shadows, lights) {
+            _ if post_process_pipeline.compatible => None,
+            (Some(buffer), Some(shadows), Some(lights)) => Some((buffer, lights.offset, &shadows.directional_light_depth_texture_view)),
+            _ => {
+                commands.entity(entity).remove::<SimpletoonViewBindGroups>();
+                continue;
+            }
+        };
+        // Without SSAO a white fallback leaves the scene's occlusion untouched
+        #[cfg(feature = "banding")]
+        let ssao = match ssao {
+            Some(ssao) => &ssao.screen_space_ambient_occlusion_texture.default_view,
+            None => &fallback_image.d2.texture_view,
+        };
+        // Views only get an ID texture while some entity has overrides
+        let entity_ids = match entity_ids {
+            Some(entity_ids) => &entity_ids.0.default_view,
+            None => &entity_overrides.fallback_texture,
+        };
+        // White keeps the whole toon result on views without a ToonVfxExclusion
+        let vfx_mask = match vfx_mask {
+            Some(vfx_mask) => &vfx_mask.0.default_view,
+            None => &fallback_image.d2.texture_view,
+        };
+        // Also white until the ToonDetailTexture's image has loaded, leaving the camera's detail as it is
+        let detail = match detail.and_then(|detail| images.get(&detail.0)) {
+            Some(image) => &image.texture_view,
+            None => &fallback_image.d2.texture_view,
+        };
+        let offsets = [settings_index.0, view_uniform.offset];
 
-        let post_process = view_target.post_process_write();
-
-        let bind_group = render_context.render_device().create_bind_group(
-            "post_process_bind_group",
-            &post_process_pipeline.layout,
-            &BindGroupEntries::sequential((
-                post_process.source,
-                &post_process_pipeline.sampler,
-                settings_binding.clone(),
-                &depth_texture.texture.default_view,
-                &normal_texture.texture.default_view,
-                view_uniforms
-            )),
-        );
+        let view_bind_group = |source: &TextureView| {
+            let key = BindGroupKey::View {
+                source: source.id(),
+                #[cfg(feature = "outlines")]
+                depth: depth_texture.texture.default_view.id(),
+                #[cfg(feature = "outlines")]
+                normal: normal_texture.texture.default_view.id(),
+                settings: settings_buffer.id(),
+                view: view_buffer.id(),
+                offsets: if post_process_pipeline.compatible { offsets } else { [0; 2] },
+                #[cfg(feature = "banding")]
+                ssao: ssao.id(),
+                entity_ids: entity_ids.id(),
+                entity_overrides: overrides_buffer.id(),
+                vfx: vfx_mask.id(),
+                detail: detail.id(),
+                #[cfg(feature = "outlines")]
+                shadows: shadows.map(|(buffer, offset, view)| (buffer.id(), offset, view.id())),
+            };
+            bind_group_cache.get_or_create(key, || {
+                let (settings_binding, view_binding) = if post_process_pipeline.compatible {
+                    (
+                        BindingResource::Buffer(BufferBinding {
+                            buffer: settings_buffer,
+                            offset: offsets[0] as u64,
+                            size: Some(SimpletoonUniform::min_size()),
+                        }),
+                        BindingResource::Buffer(BufferBinding {
+                            buffer: view_buffer,
+                            offset: offsets[1] as u64,
+                            size: Some(ViewUniform::min_size()),
+                        }),
+                    )
+                } else {
+                    (settings_uniforms.buffer.binding().unwrap(), view_uniforms.uniforms.binding().unwrap())
+                };
+                let mut entries = BindGroupEntries::with_indices((
+                    (0, source),
+                    (1, &post_process_pipeline.sampler),
+                    (2, settings_binding),
+                    (5, view_binding),
+                    (15, vfx_mask),
+                    (16, detail),
+                ))
+                .to_vec();
+                #[cfg(feature = "outlines")]
+                entries.extend_from_slice(&BindGroupEntries::with_indices((
+                    (3, &depth_texture.texture.default_view),
+                    (4, &normal_texture.texture.default_view),
+                    (9, &post_process_pipeline.noise),
+                    (10, &post_process_pipeline.noise_sampler),
+                )));
+                #[cfg(feature = "banding")]
+                if !post_process_pipeline.compatible {
+                    entries.extend_from_slice(&BindGroupEntries::with_indices(((6, ssao),)));
+                }
+                if !post_process_pipeline.compatible {
+                    entries.extend_from_slice(&BindGroupEntries::with_indices((
+                        (7, entity_ids),
+                        (8, overrides_buffer.as_entire_binding()),
+                    )));
+                }
+                #[cfg(feature = "outlines")]
+                if let Some((buffer, offset, directional_shadows)) = shadows {
+                    let lights = BufferBinding {
+                        buffer,
+                        offset: offset as u64,
+                        size: Some(GpuLights::min_size()),
+                    };
+                    entries.extend_from_slice(&BindGroupEntries::with_indices((
+                        (12, BindingResource::Buffer(lights)),
+                        (13, directional_shadows),
+                        (14, &shadow_samplers.directional_light_comparison_sampler),
+                    )));
+                }
+                // Matches the pipeline specialized from the view's Msaa
+                #[cfg(feature = "outlines")]
+                let layout = post_process_pipeline.view_layout(depth_texture.texture.texture.sample_count() > 1);
+                #[cfg(not(feature = "outlines"))]
+                let layout = &post_process_pipeline.layout;
+                render_device.create_bind_group("post_process_bind_group", layout, &entries)
+            })
+        };
+        let mask_bind_group = |texture: &CachedTexture| {
+            bind_group_cache.get_or_create(BindGroupKey::Mask(texture.default_view.id()), || {
+                render_device.create_bind_group(
+                    "simpletoon_edge_mask_bind_group",
+                    &post_process_pipeline.mask_layout,
+                    &BindGroupEntries::single(&texture.default_view),
+                )
+            })
+        };
 
-        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
-            label: Some("post_process_pass"),
-            color_attachments: &[Some(RenderPassColorAttachment {
-                view: post_process.destination,
-                resolve_target: None,
-                ops: Operations::default(),
-            })],
-            depth_stencil_attachment: None,
-            timestamp_writes: None,
-            occlusion_query_set: None,
+        // Which main texture is the source is only known once earlier nodes have run, so both get one
+        let sources = [view_target.main_texture_view(), view_target.main_texture_other_view()];
+        commands.entity(entity).insert(SimpletoonViewBindGroups {
+            views: sources.map(|source| (source.id(), view_bind_group(source))),
+            masks: edge_textures.map(|textures| [mask_bind_group(&textures.mask), mask_bind_group(&textures.scratch)]),
+            offsets: post_process_pipeline.view_offsets(&offsets).to_vec(),
+            push_constants: post_process_pipeline.push_constants(settings),
         });
+    }
+}
 
-        render_pass.set_render_pipeline(pipeline);
+fn copy_edge_mask(render_context: &mut RenderContext, world: &World, mask: &CachedTexture, export: &ToonEdgeMask) {
+    let Some(image) = world.resource::<RenderAssets<GpuImage>>().get(&export.0) else {
+        return;
+    };
+    if image.texture_format != EDGE_MASK_FORMAT {
+        warn_once!("ToonEdgeMask image is {:?} but must be {:?}, the edge mask will not be copied", image.texture_format, EDGE_MASK_FORMAT);
+        return;
+    }
+    let size = Extent3d {
+        width: image.size.width.min(mask.texture.width()),
+        height: image.size.height.min(mask.texture.height()),
+        depth_or_array_layers: 1,
+    };
+    render_context.command_encoder().copy_texture_to_texture(
+        mask.texture.as_image_copy(),
+        image.texture.as_image_copy(),
+        size,
+    );
+}
 
-        render_pass.set_bind_group(0, &bind_group, &[settings_index.index(), view_uniform.offset]);
-        render_pass.draw(0..3, 0..1);
+/// Records a fullscreen triangle into `target`, with `bind_groups` bound in order from group 0, e.g. a
+/// [`SimpletoonViewBindGroups::view`] with its [`SimpletoonViewBindGroups::offsets`].
+pub fn draw_fullscreen(
+    render_context: &mut RenderContext,
+    label: &'static str,
+    pipeline: &RenderPipeline,
+    target: &TextureView,
+    bind_groups: &[(&BindGroup, &[u32])],
+    push_constants: &[u8],
+) {
+    let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+        label: Some(label),
+        color_attachments: &[Some(RenderPassColorAttachment {
+            view: target,
+            resolve_target: None,
+            ops: Operations::default(),
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    });
 
-        Ok(())
+    render_pass.set_render_pipeline(pipeline);
+    for (index, (bind_group, offsets)) in bind_groups.iter().enumerate() {
+        render_pass.set_bind_group(index, bind_group, offsets);
+    }
+    if !push_constants.is_empty() {
+        render_pass.set_push_constants(ShaderStages::FRAGMENT, 0, push_constants);
+    }
+    render_pass.draw(0..3, 0..1);
+}
+
+/// The depth and normal prepass bindings, empty without `outlines`. Depth formats can also be bound as unfilterable
+/// floats, which GL can read with texelFetch. Normals are only ever loaded, so unfilterable custom normal formats
+/// like `Rgba32Float` bind too.
+fn prepass_layout_entries(compatible: bool, multisampled: bool) -> Vec<BindGroupLayoutEntry> {
+    #[cfg(feature = "outlines")]
+    {
+        let float = TextureSampleType::Float { filterable: false };
+        let (depth, normal) = match (multisampled, compatible) {
+            (false, false) => (texture_depth_2d(), texture_2d(float)),
+            (false, true) => (texture_2d(float), texture_2d(float)),
+            (true, false) => (texture_depth_2d_multisampled(), texture_2d_multisampled(float)),
+            (true, true) => (texture_2d_multisampled(float), texture_2d_multisampled(float)),
+        };
+        BindGroupLayoutEntries::with_indices(ShaderStages::FRAGMENT, ((3, depth), (4, normal))).to_vec()
+    }
+    #[cfg(not(feature = "outlines"))]
+    {
+        let _ = (compatible, multisampled);
+        Vec::new()
     }
 }
 
 impl FromWorld for PostProcessPipeline {
     fn from_world(world: &mut World) -> Self {
         let render_device = world.resource::<RenderDevice>();
+        let backend = world.resource::<RenderAdapter>().get_info().backend;
+        let compatible = Self::needs_compatibility(backend.into(), &render_device.limits());
+        // WebGPU and WebGL don't expose push constants
+        let push_constants = render_device.features().contains(WgpuFeatures::PUSH_CONSTANTS)
+            && render_device.limits().max_push_constant_size >= PUSH_CONSTANTS_SIZE;
+        if compatible {
+            info!("Simpletoon is using its compatibility pipelines on this {backend:?} adapter, SSAO banding and per-entity overrides are disabled");
+        }
 
-        let layout = render_device.create_bind_group_layout(
-            "post_process_bind_group_layout",
-            &BindGroupLayoutEntries::sequential(
+        // The prepass textures are only bound with `outlines` and the SSAO texture with `banding`
+        let mut entries = BindGroupLayoutEntries::with_indices(
+            ShaderStages::FRAGMENT,
+            (
+                (0, texture_2d(TextureSampleType::Float { filterable: true })),
+                (1, sampler(SamplerBindingType::Filtering)),
+                (2, uniform_buffer::<SimpletoonUniform>(!compatible)),
+                (5, uniform_buffer::<ViewUniform>(!compatible)),
+                (15, texture_2d(TextureSampleType::Float { filterable: true })),
+                (16, texture_2d(TextureSampleType::Float { filterable: true })),
+            ),
+        )
+        .to_vec();
+        #[cfg(feature = "outlines")]
+        entries.extend_from_slice(&BindGroupLayoutEntries::with_indices(
+            ShaderStages::FRAGMENT,
+            (
+                (9, texture_2d(TextureSampleType::Float { filterable: true })),
+                (10, sampler(SamplerBindingType::Filtering)),
+            ),
+        ));
+        #[cfg(feature = "banding")]
+        if !compatible {
+            entries.extend_from_slice(&BindGroupLayoutEntries::with_indices(
+                ShaderStages::FRAGMENT,
+                ((6, texture_2d(TextureSampleType::Float { filterable: true })),),
+            ));
+        }
+        // Per-entity overrides are read from a storage buffer, which the compatibility path can't rely on
+        if !compatible {
+            entries.extend_from_slice(&BindGroupLayoutEntries::with_indices(
                 ShaderStages::FRAGMENT,
                 (
-                    texture_2d(TextureSampleType::Float { filterable: true }),
-                    sampler(SamplerBindingType::Filtering),
-                    uniform_buffer::<SimpletoonSettings>(true),
-                    texture_depth_2d(),
-                    texture_2d(TextureSampleType::Float { filterable: true }),
-                    uniform_buffer::<ViewUniform>(true),
+                    (7, texture_2d(TextureSampleType::Uint)),
+                    (8, storage_buffer_read_only_sized(false, None)),
                 ),
+            ));
+        }
+        // Shadow edges read bevy's light uniform and directional shadow maps, which aren't array textures on GL
+        #[cfg(feature = "outlines")]
+        if !compatible {
+            entries.extend_from_slice(&BindGroupLayoutEntries::with_indices(
+                ShaderStages::FRAGMENT,
+                (
+                    (12, uniform_buffer::<GpuLights>(false)),
+                    (13, texture_2d_array(TextureSampleType::Depth)),
+                    (14, sampler(SamplerBindingType::Comparison)),
+                ),
+            ));
+        }
+        let multisampled_entries = [entries.clone(), prepass_layout_entries(compatible, true)].concat();
+        entries.extend(prepass_layout_entries(compatible, false));
+        let layout = render_device.create_bind_group_layout("post_process_bind_group_layout", &entries);
+        let multisampled_layout =
+            render_device.create_bind_group_layout("simpletoon_multisampled_bind_group_layout", &multisampled_entries);
+
+        let mask_layout = render_device.create_bind_group_layout(
+            "simpletoon_edge_mask_bind_group_layout",
+            &BindGroupLayoutEntries::single(
+                ShaderStages::FRAGMENT,
+                texture_2d(TextureSampleType::Float { filterable: true }),
             ),
         );
 
+        // Nearest filtering, the default, so band boundaries in the scene colour stay as sharp as the bands themselves
         let sampler = render_device.create_sampler(&SamplerDescriptor::default());
 
-        let shader = world.load_asset("embedded://bevy_simpletoon/assets/toon.wgsl");
-
-        let pipeline_id = world
-            .resource_mut::<PipelineCache>()
-            .queue_render_pipeline(RenderPipelineDescriptor {
-                label: Some("post_process_pipeline".into()),
-                layout: vec![layout.clone()],
-                vertex: fullscreen_shader_vertex_state(),
-                fragment: Some(FragmentState {
-                    shader,
-                    shader_defs: vec![],
-                    entry_point: "fragment".into(),
-                    targets: vec![Some(ColorTargetState {
-                        format: TextureFormat::bevy_default(),
-                        blend: None,
-                        write_mask: ColorWrites::ALL,
-                    })],
-                }),
-                primitive: PrimitiveState::default(),
-                depth_stencil: None,
-                multisample: MultisampleState::default(),
-                push_constant_ranges: vec![],
-                zero_initialize_workgroup_memory: false,
-            });
+        #[cfg(feature = "outlines")]
+        let noise = render_device
+            .create_texture_with_data(
+                world.resource::<RenderQueue>(),
+                &TextureDescriptor {
+                    label: Some("simpletoon_line_breakup_noise"),
+                    size: Extent3d {
+                        width: NOISE_SIZE,
+                        height: NOISE_SIZE,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: TextureDimension::D2,
+                    format: TextureFormat::R8Unorm,
+                    usage: TextureUsages::TEXTURE_BINDING,
+                    view_formats: &[],
+                },
+                TextureDataOrder::default(),
+                &line_breakup_noise(),
+            )
+            .create_view(&TextureViewDescriptor::default());
+        #[cfg(feature = "outlines")]
+        let noise_sampler = render_device.create_sampler(&SamplerDescriptor {
+            label: Some("simpletoon_line_breakup_sampler"),
+            address_mode_u: AddressMode::Repeat,
+            address_mode_v: AddressMode::Repeat,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..default()
+        });
+
+        let path = "embedded://bevy_simpletoon/assets/toon.wgsl";
+        #[cfg(feature = "dev-hot-reload")]
+        let path = world.get_resource::<SimpletoonShaderPath>().map_or(path, |shader_path| shader_path.0);
+        let shader = world.load_asset(path);
 
         Self {
             layout,
+            multisampled_layout,
+            mask_layout,
             sampler,
-            pipeline_id,
+            #[cfg(feature = "outlines")]
+            noise,
+            #[cfg(feature = "outlines")]
+            noise_sampler,
+            shader,
+            compatible,
+            push_constants,
+            normal_encoding: world.resource::<SimpletoonNormalEncoding>().0,
+        }
+    }
+}
+
+#[cfg(feature = "outlines")]
+const NOISE_SIZE: u32 = 64;
+
+/// Tiling value noise for `line_breakup`, a few octaves of smoothly interpolated random values on a wrapping grid.
+/// Generated rather than shipped as an image so it is the same on every platform without an asset to load.
+#[cfg(feature = "outlines")]
+fn line_breakup_noise() -> Vec<u8> {
+    // Small integer hash, so the texture doesn't depend on a random number generator
+    let hash = |x: u32, y: u32, octave: u32| {
+        let mut h = x.wrapping_mul(0x27d4_eb2d) ^ y.wrapping_mul(0x1656_67b1) ^ octave.wrapping_mul(0x9e37_79b9);
+        h ^= h >> 15;
+        h = h.wrapping_mul(0x85eb_ca6b);
+        h ^= h >> 13;
+        (h & 0xffff) as f32 / 65535.0
+    };
+    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+
+    let mut data = Vec::with_capacity((NOISE_SIZE * NOISE_SIZE) as usize);
+    for y in 0..NOISE_SIZE {
+        for x in 0..NOISE_SIZE {
+            let (mut value, mut weight, mut total) = (0.0, 1.0, 0.0);
+            for (octave, cells) in [4u32, 8, 16].into_iter().enumerate() {
+                let cell_size = (NOISE_SIZE / cells) as f32;
+                let (fx, fy) = (x as f32 / cell_size, y as f32 / cell_size);
+                let (x0, y0) = (fx.floor() as u32, fy.floor() as u32);
+                let (tx, ty) = (smooth(fx.fract()), smooth(fy.fract()));
+                let corner = |dx: u32, dy: u32| hash((x0 + dx) % cells, (y0 + dy) % cells, octave as u32);
+                let top = corner(0, 0) + (corner(1, 0) - corner(0, 0)) * tx;
+                let bottom = corner(0, 1) + (corner(1, 1) - corner(0, 1)) * tx;
+                value += (top + (bottom - top) * ty) * weight;
+                total += weight;
+                weight *= 0.5;
+            }
+            data.push((value / total * 255.0).round() as u8);
+        }
+    }
+    data
+}
+
+impl SpecializedRenderPipeline for PostProcessPipeline {
+    type Key = SimpletoonPipelineKey;
+
+    fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
+        let (label, entry_point) = match key.pass {
+            SimpletoonPass::Single => ("post_process_pipeline", "fragment"),
+            SimpletoonPass::Edge => ("simpletoon_edge_pipeline", "edge_mask"),
+            SimpletoonPass::Cleanup => ("simpletoon_cleanup_pipeline", "cleanup_edges"),
+            SimpletoonPass::DilateX => ("simpletoon_dilate_x_pipeline", "dilate"),
+            SimpletoonPass::DilateY => ("simpletoon_dilate_y_pipeline", "dilate"),
+            SimpletoonPass::Composite => ("simpletoon_composite_pipeline", "fragment"),
+            SimpletoonPass::EmissiveStrokes => ("simpletoon_emissive_strokes_pipeline", "emissive_strokes"),
+        };
+        let edge_mask_input = matches!(
+            key.pass,
+            SimpletoonPass::Cleanup | SimpletoonPass::DilateX | SimpletoonPass::DilateY | SimpletoonPass::Composite
+        );
+        let shader_defs = self
+            .shader_defs()
+            .multisampled_prepass(key.multisampled_prepass)
+            .edge_mask_input(edge_mask_input)
+            .dilate_vertical(key.pass == SimpletoonPass::DilateY)
+            .build();
+        let view_layout = self.view_layout(key.multisampled_prepass).clone();
+        let layout = match edge_mask_input {
+            true => vec![view_layout, self.mask_layout.clone()],
+            false => vec![view_layout],
+        };
+        let push_constant_ranges = match self.push_constants {
+            true => vec![PushConstantRange { stages: ShaderStages::FRAGMENT, range: 0..PUSH_CONSTANTS_SIZE }],
+            false => vec![],
+        };
+        let format = match key.pass {
+            SimpletoonPass::Edge | SimpletoonPass::Cleanup | SimpletoonPass::DilateX | SimpletoonPass::DilateY => EDGE_MASK_FORMAT,
+            _ if key.hdr => ViewTarget::TEXTURE_FORMAT_HDR,
+            _ => TextureFormat::bevy_default(),
+        };
+
+        RenderPipelineDescriptor {
+            label: Some(label.into()),
+            layout,
+            vertex: fullscreen_shader_vertex_state(),
+            fragment: Some(FragmentState {
+                shader: self.shader.clone(),
+                shader_defs,
+                entry_point: entry_point.into(),
+                targets: vec![Some(ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            push_constant_ranges,
+            zero_initialize_workgroup_memory: false,
         }
     }
 }
@@ -229,15 +2481,199 @@ impl FromWorld for PostProcessPipeline {
 impl Default for SimpletoonSettings {
     fn default() -> Self {
         Self { 
-            depth_threshold: 1.0, 
-            depth_threshold_depth_mul: 1.0, 
+            depth_threshold: 0.02,
+            depth_threshold_depth_mul: 0.01,
             depth_normal_threshold: 0.4, 
-            depth_normal_threshold_mul: 30.0, 
+            depth_normal_threshold_mul_along: 30.0,
+            depth_normal_threshold_mul_across: 2.0,
             normal_threshold: 0.4, 
             colour_threshold: 0.2, 
             stroke_size: 1.0,
             colour_banding: 5.0, 
-            stroke_colour: Vec4::new(0.1, 0.1, 0.1, 1.0) 
+            stroke_colour: LinearRgba::rgb(0.1, 0.1, 0.1).into(),
+            effect_strength: 1.0,
+            stroke_space: StrokeSpace::Screen,
+            stroke_dilation: false,
+            ssao_banding: false,
+            emissive_strokes: 0.0,
+            colour_edge_space: ColourEdgeSpace::Rgb,
+            chroma_threshold: 0.1,
+            stroke_depth_of_field: StrokeDepthOfField::Sharp,
+            banding_range: Vec2::new(0.0, f32::MAX),
+            band_spacing: BandSpacing::Linear,
+            band_exposure: 1.0,
+            line_breakup: 0.0,
+            line_breakup_scale: 1.0,
+            line_breakup_contrast: 1.0,
+            kernel_radius: 1,
+            stroke_cleanup: false,
+            stroke_smoothing: false,
+            high_contrast_strokes: false,
+            id_edges: false,
+            id_edge_depth: 0.05,
+            shadow_edges: false,
+            shadow_edge_threshold: 0.5,
+            stroke_style: StrokeStyle::Solid,
+            dash_length: 6.0,
+            dash_gap: 4.0,
+            normal_sampling: NormalSampling::Prepass,
+            normal_detail_threshold: None,
+            shading: ToonShading::Banded,
+            gooch_warm: Vec3::new(0.4, 0.4, 0.0),
+            gooch_cool: Vec3::new(0.0, 0.0, 0.4),
+            gooch_alpha: 0.2,
+            gooch_beta: 0.6,
+            line_art_background: Vec4::ONE,
+            preserve_alpha: false,
+            edge_alpha: false,
+            screen_edge_margin: 0.0,
+            emissive_range: Vec2::splat(f32::MAX),
+            stroke_animation: None,
+            channel_banding: None,
+            terrain: None,
+            sky: None,
+            detail: None,
+            blueprint: None,
+        }
+    }
+}
+
+impl SimpletoonSettings {
+    /// A technical drawing look, white strokes over a [`BlueprintGrid`] with the default colours.
+    pub fn blueprint() -> Self {
+        Self {
+            stroke_colour: Srgba::WHITE,
+            blueprint: Some(BlueprintGrid::default()),
+            ..default()
+        }
+    }
+
+    /// Blends towards `other`, numbers and colours are interpolated while modes and toggles switch over halfway. `t` is
+    /// clamped to 0..1, and either end gives back those settings exactly, colours included.
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        if t <= 0.0 {
+            return *self;
+        }
+        if t >= 1.0 {
+            return *other;
         }
+        let mut mixed = if t < 0.5 { *self } else { *other };
+        mixed.lerp_numbers(self, other, t);
+        mixed
     }
-}
\ No newline at end of file
+
+    /// Sets the numbers and colours [`SimpletoonSettings::lerp`] interpolates, leaving the rest as they are.
+    pub(crate) fn lerp_numbers(&mut self, from: &Self, to: &Self, t: f32) {
+        let lerp = |a: f32, b: f32| a.lerp(b, t);
+        self.depth_threshold = lerp(from.depth_threshold, to.depth_threshold);
+        self.depth_threshold_depth_mul = lerp(from.depth_threshold_depth_mul, to.depth_threshold_depth_mul);
+        self.depth_normal_threshold = lerp(from.depth_normal_threshold, to.depth_normal_threshold);
+        self.depth_normal_threshold_mul_along = lerp(from.depth_normal_threshold_mul_along, to.depth_normal_threshold_mul_along);
+        self.depth_normal_threshold_mul_across = lerp(from.depth_normal_threshold_mul_across, to.depth_normal_threshold_mul_across);
+        self.normal_threshold = lerp(from.normal_threshold, to.normal_threshold);
+        self.colour_threshold = lerp(from.colour_threshold, to.colour_threshold);
+        self.stroke_size = lerp(from.stroke_size, to.stroke_size);
+        self.colour_banding = lerp(from.colour_banding, to.colour_banding);
+        self.stroke_colour = LinearRgba::from(from.stroke_colour).mix(&to.stroke_colour.into(), t).into();
+        self.effect_strength = lerp(from.effect_strength, to.effect_strength);
+        self.emissive_strokes = lerp(from.emissive_strokes, to.emissive_strokes);
+        self.chroma_threshold = lerp(from.chroma_threshold, to.chroma_threshold);
+        self.banding_range = from.banding_range.lerp(to.banding_range, t);
+        self.band_exposure = lerp(from.band_exposure, to.band_exposure);
+        self.line_breakup = lerp(from.line_breakup, to.line_breakup);
+        self.line_breakup_scale = lerp(from.line_breakup_scale, to.line_breakup_scale);
+        self.line_breakup_contrast = lerp(from.line_breakup_contrast, to.line_breakup_contrast);
+        self.id_edge_depth = lerp(from.id_edge_depth, to.id_edge_depth);
+        self.shadow_edge_threshold = lerp(from.shadow_edge_threshold, to.shadow_edge_threshold);
+        self.dash_length = lerp(from.dash_length, to.dash_length);
+        self.dash_gap = lerp(from.dash_gap, to.dash_gap);
+        self.gooch_warm = from.gooch_warm.lerp(to.gooch_warm, t);
+        self.gooch_cool = from.gooch_cool.lerp(to.gooch_cool, t);
+        self.gooch_alpha = lerp(from.gooch_alpha, to.gooch_alpha);
+        self.gooch_beta = lerp(from.gooch_beta, to.gooch_beta);
+        self.line_art_background = from.line_art_background.lerp(to.line_art_background, t);
+        self.screen_edge_margin = lerp(from.screen_edge_margin, to.screen_edge_margin);
+        self.emissive_range = from.emissive_range.lerp(to.emissive_range, t);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        ecs::system::RunSystemOnce,
+        render::{sync_world::RenderEntity, MainWorld},
+    };
+
+    use super::*;
+
+    #[test]
+    fn moved_instances_stop_drawing() {
+        let mut render_world = World::new();
+        render_world.insert_resource(PassBeforeTonemapping(false));
+        render_world.init_resource::<MainWorld>();
+        let view = render_world.spawn_empty().id();
+        let instance = render_world.spawn_empty().id();
+
+        let mut main_world = render_world.resource_mut::<MainWorld>();
+        let camera = main_world.spawn((Camera3d::default(), RenderEntity::from(view))).id();
+        let other = main_world.spawn(Transform::default()).id();
+        let child = main_world
+            .spawn((ToonInstance("fine"), SimpletoonSettings::default(), RenderEntity::from(instance), ChildOf(camera)))
+            .id();
+        render_world.run_system_once(extract_instances).unwrap();
+        assert!(render_world.entity(instance).contains::<SimpletoonInstanceOf>());
+        assert!(render_world.entity(instance).contains::<SimpletoonUniform>());
+        assert!(render_world.entity(view).contains::<SimpletoonViewInstances>());
+
+        // Under something that isn't a camera the instance is ignored, and mustn't keep drawing on its old one
+        render_world.resource_mut::<MainWorld>().entity_mut(child).insert(ChildOf(other));
+        render_world.run_system_once(extract_instances).unwrap();
+        assert!(!render_world.entity(instance).contains::<SimpletoonInstanceOf>());
+        assert!(!render_world.entity(instance).contains::<SimpletoonUniform>());
+        assert!(!render_world.entity(view).contains::<SimpletoonViewInstances>());
+    }
+
+    fn lerp_ends() -> (SimpletoonSettings, SimpletoonSettings) {
+        let from = SimpletoonSettings {
+            stroke_size: 1.0,
+            stroke_colour: Srgba::BLACK,
+            shading: ToonShading::Gooch,
+            kernel_radius: 1,
+            ..default()
+        };
+        let to = SimpletoonSettings {
+            stroke_size: 3.0,
+            stroke_colour: Srgba::WHITE,
+            shading: ToonShading::LineArt,
+            kernel_radius: 3,
+            ..default()
+        };
+        (from, to)
+    }
+
+    #[test]
+    fn lerp_endpoints() {
+        let (from, to) = lerp_ends();
+        assert!(from.lerp(&to, 0.0) == from);
+        assert!(from.lerp(&to, 1.0) == to);
+    }
+
+    #[test]
+    fn lerp_switches_modes_halfway() {
+        let (from, to) = lerp_ends();
+        let before = from.lerp(&to, 0.49);
+        assert_eq!((before.shading, before.kernel_radius), (ToonShading::Gooch, 1));
+        let after = from.lerp(&to, 0.5);
+        assert_eq!((after.shading, after.kernel_radius), (ToonShading::LineArt, 3));
+        assert_eq!(after.stroke_size, 2.0);
+    }
+
+    #[test]
+    fn lerp_blends_colours_in_linear_space() {
+        let (from, to) = lerp_ends();
+        let halfway = LinearRgba::from(from.lerp(&to, 0.5).stroke_colour);
+        assert!((halfway.red - 0.5).abs() < 1e-4, "{halfway:?}");
+        // Halfway in sRGB would be much darker
+        assert!(from.lerp(&to, 0.5).stroke_colour.red > 0.7);
+    }
+}
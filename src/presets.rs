@@ -0,0 +1,120 @@
+use std::time::Duration;
+
+use bevy::{prelude::*, state::state::StateTransitionEvent};
+
+use crate::plugin::SimpletoonSettings;
+
+/// Maps game states to toon presets, so e.g. a flashback state can get its own look without wiring it up by hand.
+pub trait SimpletoonAppExt {
+    /// Crossfades every toon camera to `preset` on entering `state`, and back to the settings they had before once a
+    /// state without a preset is entered. Cameras spawned while in `state` start out with `preset`.
+    ///
+    /// The crossfade takes [`ToonPresetCrossfade`], see [`SimpletoonSettings::lerp`] for how settings are blended.
+    fn add_toon_preset_for_state<S: States>(&mut self, state: S, preset: SimpletoonSettings) -> &mut Self;
+}
+
+/// How long state preset crossfades take, zero to switch straight away.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct ToonPresetCrossfade(pub Duration);
+
+impl Default for ToonPresetCrossfade {
+    fn default() -> Self {
+        Self(Duration::from_millis(500))
+    }
+}
+
+#[derive(Resource)]
+struct ToonStatePresets<S: States> {
+    presets: Vec<(S, SimpletoonSettings)>,
+}
+
+impl<S: States> ToonStatePresets<S> {
+    fn get(&self, state: &S) -> Option<SimpletoonSettings> {
+        self.presets.iter().find(|(preset_state, _)| preset_state == state).map(|(_, preset)| *preset)
+    }
+}
+
+/// A camera's settings from before it took on a preset, restored outside preset states.
+#[derive(Component)]
+struct ToonPresetBase(SimpletoonSettings);
+
+#[derive(Component)]
+struct ToonPresetFade {
+    from: SimpletoonSettings,
+    to: SimpletoonSettings,
+    elapsed: Duration,
+    /// Fading back to the [`ToonPresetBase`], which is dropped once it is reached.
+    restoring: bool,
+}
+
+impl SimpletoonAppExt for App {
+    fn add_toon_preset_for_state<S: States>(&mut self, state: S, preset: SimpletoonSettings) -> &mut Self {
+        if !self.world().contains_resource::<ToonPresetCrossfade>() {
+            self.init_resource::<ToonPresetCrossfade>().add_systems(Update, run_toon_preset_fades);
+        }
+        if !self.world().contains_resource::<ToonStatePresets<S>>() {
+            self.insert_resource(ToonStatePresets::<S> { presets: Vec::new() })
+                .add_systems(Update, start_toon_preset_fades::<S>.before(run_toon_preset_fades));
+        }
+        self.world_mut().resource_mut::<ToonStatePresets<S>>().presets.push((state, preset));
+        self
+    }
+}
+
+fn start_toon_preset_fades<S: States>(
+    mut commands: Commands,
+    mut transitions: EventReader<StateTransitionEvent<S>>,
+    state: Option<Res<State<S>>>,
+    presets: Res<ToonStatePresets<S>>,
+    mut cameras: Query<(Entity, &mut SimpletoonSettings, Option<&ToonPresetBase>)>,
+) {
+    let entered = transitions
+        .read()
+        .filter(|transition| transition.entered != transition.exited)
+        .last()
+        .and_then(|transition| transition.entered.clone());
+    for (entity, mut settings, base) in &mut cameras {
+        if settings.is_added() {
+            if let Some(preset) = state.as_ref().and_then(|state| presets.get(state.get())) {
+                commands.entity(entity).insert(ToonPresetBase(*settings));
+                *settings = preset;
+            }
+            continue;
+        }
+        let Some(entered) = &entered else {
+            continue;
+        };
+        let (to, restoring) = match (presets.get(entered), base) {
+            (Some(preset), _) => (preset, false),
+            (None, Some(base)) => (base.0, true),
+            (None, None) => continue,
+        };
+        if base.is_none() {
+            commands.entity(entity).insert(ToonPresetBase(*settings));
+        }
+        commands.entity(entity).insert(ToonPresetFade { from: *settings, to, elapsed: Duration::ZERO, restoring });
+    }
+}
+
+fn run_toon_preset_fades(
+    mut commands: Commands,
+    time: Res<Time>,
+    crossfade: Res<ToonPresetCrossfade>,
+    mut cameras: Query<(Entity, &mut SimpletoonSettings, &mut ToonPresetFade)>,
+) {
+    for (entity, mut settings, mut fade) in &mut cameras {
+        fade.elapsed += time.delta();
+        let t = match crossfade.0.is_zero() {
+            true => 1.0,
+            false => (fade.elapsed.as_secs_f32() / crossfade.0.as_secs_f32()).min(1.0),
+        };
+        *settings = fade.from.lerp(&fade.to, t);
+        if t >= 1.0 {
+            let mut camera = commands.entity(entity);
+            camera.remove::<ToonPresetFade>();
+            if fade.restoring {
+                camera.remove::<ToonPresetBase>();
+            }
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use bevy::prelude::*;
+
+use crate::plugin::SimpletoonSettings;
+
+/// One knob for settings menus, applied to every toon camera whenever it changes and to cameras spawned afterwards.
+///
+/// Each level only touches the cost-related settings, `kernel_radius` and `stroke_dilation`, leaving thresholds and
+/// colours as authored. [`ToonQuality::Custom`] leaves all settings alone, so cameras can be tuned by hand.
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum ToonQuality {
+    /// A single ring of taps, with thick strokes grown by dilation.
+    Low,
+    /// Two rings of taps, with thick strokes grown by dilation.
+    Medium,
+    /// Three rings of taps at every pixel, the smoothest thick strokes.
+    High,
+    /// Leaves every camera's settings as they are.
+    #[default]
+    Custom,
+}
+
+impl ToonQuality {
+    /// Sets the settings this level controls, does nothing for [`ToonQuality::Custom`].
+    pub fn apply(self, settings: &mut SimpletoonSettings) {
+        let (kernel_radius, stroke_dilation) = match self {
+            ToonQuality::Low => (1, true),
+            ToonQuality::Medium => (2, true),
+            ToonQuality::High => (3, false),
+            ToonQuality::Custom => return,
+        };
+        settings.kernel_radius = kernel_radius;
+        settings.stroke_dilation = stroke_dilation;
+    }
+}
+
+pub(crate) fn apply_toon_quality(quality: Res<ToonQuality>, mut query: Query<&mut SimpletoonSettings>) {
+    for mut settings in &mut query {
+        if !quality.is_changed() && !settings.is_added() {
+            continue;
+        }
+        let mut applied = *settings;
+        quality.apply(&mut applied);
+        settings.set_if_neq(applied);
+    }
+}
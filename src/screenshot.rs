@@ -0,0 +1,71 @@
+use std::path::{Path, PathBuf};
+
+use bevy::{
+    ecs::query::QueryItem,
+    prelude::*,
+    render::{
+        extract_component::ExtractComponent,
+        view::screenshot::{Screenshot, ScreenshotCaptured},
+    },
+};
+
+/// Add to a toon camera to save its next frame as a PNG with a transparent background, e.g. for marketing art or
+/// baking sprites from 3D models. The component removes itself once the screenshot has been taken.
+///
+/// Alpha is taken from the depth prepass, so everything that isn't geometry or a stroke is see-through. This needs
+/// the `outlines` feature, without it the screenshot is opaque. Effects that run after the toon pass, like FXAA,
+/// are captured too.
+#[derive(Component, Clone, Debug)]
+pub struct ToonScreenshot {
+    pub path: PathBuf,
+}
+
+impl ToonScreenshot {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+/// The render world side of [`ToonScreenshot`], the toon pass of views with it writes alpha from depth.
+#[derive(Component, Clone, Copy)]
+pub struct ToonTransparentBackground;
+
+impl ExtractComponent for ToonScreenshot {
+    type QueryData = ();
+    type QueryFilter = ();
+    type Out = ToonTransparentBackground;
+
+    fn extract_component(_: QueryItem<'_, Self::QueryData>) -> Option<Self::Out> {
+        Some(ToonTransparentBackground)
+    }
+}
+
+/// Takes a screenshot of the camera's target in the same frame its transparent background is first rendered.
+pub(crate) fn take_toon_screenshots(mut commands: Commands, cameras: Query<(Entity, &Camera, &ToonScreenshot), Added<ToonScreenshot>>) {
+    for (camera, camera_settings, screenshot) in &cameras {
+        let path = screenshot.path.clone();
+        commands
+            .spawn(Screenshot(camera_settings.target.clone()))
+            .observe(move |trigger: Trigger<ScreenshotCaptured>, mut commands: Commands| {
+                save_with_alpha(&trigger.0, &path);
+                if let Ok(mut camera) = commands.get_entity(camera) {
+                    camera.remove::<ToonScreenshot>();
+                }
+            });
+    }
+}
+
+/// Saves a captured frame with its alpha, bevy's `save_to_disk` drops it.
+fn save_with_alpha(image: &Image, path: &Path) {
+    let image = match image.clone().try_into_dynamic() {
+        Ok(image) => image.to_rgba8(),
+        Err(err) => {
+            error!("Cannot save toon screenshot to {}: {err}", path.display());
+            return;
+        }
+    };
+    match image.save(path) {
+        Ok(()) => info!("Toon screenshot saved to {}", path.display()),
+        Err(err) => error!("Cannot save toon screenshot to {}: {err}", path.display()),
+    }
+}
@@ -0,0 +1,107 @@
+use bevy::{
+    pbr::{MAX_CASCADES_PER_LIGHT, MAX_DIRECTIONAL_LIGHTS},
+    render::render_resource::ShaderDefVal,
+};
+
+/// The shader defs `toon.wgsl` is compiled with, for custom nodes and shader overrides that compile it themselves and
+/// need to bind what the crate's pipelines bind. Start from [`crate::plugin::PostProcessPipeline::shader_defs`], which
+/// matches the adapter, then set what differs per pass.
+///
+/// `OUTLINES` and `BANDING` follow the crate's cargo features, and `MAX_DIRECTIONAL_LIGHTS` and
+/// `MAX_CASCADES_PER_LIGHT` are always set to size bevy's `Lights`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ToonShaderDefs {
+    compatible: bool,
+    normal_encoding: NormalEncoding,
+    push_constants: bool,
+    multisampled_prepass: bool,
+    edge_mask_input: bool,
+    dilate_vertical: bool,
+}
+
+impl ToonShaderDefs {
+    /// `COMPATIBILITY` for the GL/mobile layout, otherwise `ENTITY_OVERRIDES` as the overrides are bound.
+    pub fn compatible(mut self, compatible: bool) -> Self {
+        self.compatible = compatible;
+        self
+    }
+
+    /// `NORMAL_OCTAHEDRAL` for [`NormalEncoding::Octahedral`], how the normal prepass is decoded.
+    pub fn normal_encoding(mut self, normal_encoding: NormalEncoding) -> Self {
+        self.normal_encoding = normal_encoding;
+        self
+    }
+
+    /// `PUSH_CONSTANTS`, per-view constants like `kernel_radius` are pushed instead of read from the settings uniform.
+    pub fn push_constants(mut self, push_constants: bool) -> Self {
+        self.push_constants = push_constants;
+        self
+    }
+
+    /// `MULTISAMPLED_PREPASS`, the view has MSAA so its prepass textures are multisampled.
+    pub fn multisampled_prepass(mut self, multisampled_prepass: bool) -> Self {
+        self.multisampled_prepass = multisampled_prepass;
+        self
+    }
+
+    /// `EDGE_MASK_INPUT`, the pass reads strokes from an edge mask bound as group 1. Without the `outlines` feature
+    /// there are no strokes to read, the mask is still bound but left unused.
+    pub fn edge_mask_input(mut self, edge_mask_input: bool) -> Self {
+        self.edge_mask_input = edge_mask_input;
+        self
+    }
+
+    /// `DILATE_VERTICAL`, the `dilate` entry point grows strokes vertically rather than horizontally.
+    pub fn dilate_vertical(mut self, dilate_vertical: bool) -> Self {
+        self.dilate_vertical = dilate_vertical;
+        self
+    }
+
+    /// The defs in the order the crate's pipelines pass them.
+    pub fn build(&self) -> Vec<ShaderDefVal> {
+        let mut shader_defs: Vec<ShaderDefVal> = vec![];
+        if self.edge_mask_input {
+            shader_defs.push("EDGE_MASK_INPUT".into());
+        }
+        if self.dilate_vertical {
+            shader_defs.push("DILATE_VERTICAL".into());
+        }
+        if self.multisampled_prepass {
+            shader_defs.push("MULTISAMPLED_PREPASS".into());
+        }
+        if cfg!(feature = "outlines") {
+            shader_defs.push("OUTLINES".into());
+        }
+        if cfg!(feature = "banding") {
+            shader_defs.push("BANDING".into());
+        }
+        if self.push_constants {
+            shader_defs.push("PUSH_CONSTANTS".into());
+        }
+        if self.normal_encoding == NormalEncoding::Octahedral {
+            shader_defs.push("NORMAL_OCTAHEDRAL".into());
+        }
+        // Sizes the arrays in bevy's Lights, which the shader imports
+        shader_defs.push(ShaderDefVal::UInt("MAX_DIRECTIONAL_LIGHTS".into(), MAX_DIRECTIONAL_LIGHTS as u32));
+        shader_defs.push(ShaderDefVal::UInt("MAX_CASCADES_PER_LIGHT".into(), MAX_CASCADES_PER_LIGHT as u32));
+        if self.compatible {
+            shader_defs.push("COMPATIBILITY".into());
+        } else {
+            shader_defs.push("ENTITY_OVERRIDES".into());
+        }
+        shader_defs
+    }
+}
+
+/// How the normal prepass stores its normals, which has changed between Bevy versions. The default is what the Bevy
+/// this crate is built against writes, set it with [`crate::plugin::SimpletoonPlugin::normal_encoding`] for a patched
+/// or newer Bevy, or custom prepass shaders that write another layout. It is configuration only, nothing looks at the
+/// prepass to detect which layout it actually holds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum NormalEncoding {
+    /// World normals remapped from -1..1 to 0..1 in the red, green and blue channels, as Bevy 0.16 writes them.
+    #[default]
+    Rgb,
+    /// World normals folded onto an octahedron and unwrapped into the red and green channels, 0..1.
+    Octahedral,
+}
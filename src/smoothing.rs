@@ -0,0 +1,109 @@
+use bevy::{prelude::*, reflect::Struct};
+
+use crate::plugin::SimpletoonSettings;
+
+/// Eases runtime changes to the camera's [`SimpletoonSettings`] in over time instead of applying them at once, so e.g.
+/// dragging a quality slider doesn't make the strokes and bands pop.
+///
+/// Every frame the fields edited since the last one become new targets, and the numbers and colours move towards
+/// their targets exponentially, `time_constant` seconds being how long it takes to cover about two thirds of the way.
+/// `stroke_colour` is eased in OKLab so it doesn't dip through grey, and modes and toggles switch right away.
+/// `colour_banding` is a number too, so bands split or merge gradually while it eases towards a new count.
+#[derive(Component, Clone, Copy, Debug)]
+#[require(ToonSmoothingState)]
+pub struct ToonSmoothing {
+    pub time_constant: f32,
+}
+
+impl Default for ToonSmoothing {
+    fn default() -> Self {
+        Self { time_constant: 0.15 }
+    }
+}
+
+/// The settings [`ToonSmoothing`] is easing towards, and the ones it wrote last.
+#[derive(Component, Default)]
+pub(crate) struct ToonSmoothingState {
+    settings: Option<(SimpletoonSettings, SimpletoonSettings)>,
+    /// Seconds since the latest edit, the targets are snapped to once this is far enough past the time constant.
+    elapsed: f32,
+}
+
+/// How many time constants until the settings snap to their targets, by then less than 0.1% of the way is left.
+const SETTLE_TIME_CONSTANTS: f32 = 7.0;
+
+pub(crate) fn smooth_toon_settings(
+    time: Res<Time>,
+    mut cameras: Query<(&ToonSmoothing, &mut SimpletoonSettings, &mut ToonSmoothingState)>,
+) {
+    for (smoothing, mut settings, mut state) in &mut cameras {
+        let state = state.as_mut();
+        let Some((target, written)) = state.settings.as_mut() else {
+            state.settings = Some((*settings, *settings));
+            continue;
+        };
+
+        // Only the fields that were edited are retargeted, the rest keep easing towards what they were heading for
+        for i in 0..settings.field_len() {
+            let (Some(edited), Some(previous)) = (settings.field_at(i), written.field_at(i)) else {
+                continue;
+            };
+            if edited.reflect_partial_eq(previous) != Some(true) {
+                if let Some(field) = target.field_at_mut(i) {
+                    field.apply(edited);
+                }
+                state.elapsed = 0.0;
+            }
+        }
+
+        state.elapsed += time.delta_secs();
+        let mut next = *target;
+        if state.elapsed < smoothing.time_constant * SETTLE_TIME_CONSTANTS {
+            let t = 1.0 - (-time.delta_secs() / smoothing.time_constant).exp();
+            next.lerp_numbers(written, target, t);
+            let from = Oklaba::from(written.stroke_colour);
+            let to = Oklaba::from(target.stroke_colour);
+            next.stroke_colour = from.mix(&to, t).into();
+        }
+        *written = next;
+        settings.set_if_neq(next);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::time::TimeUpdateStrategy;
+
+    use super::*;
+
+    #[test]
+    fn edits_ease_in_then_snap() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(20)))
+            .add_systems(Last, smooth_toon_settings);
+        let camera = app.world_mut().spawn((ToonSmoothing::default(), SimpletoonSettings::default())).id();
+        app.update();
+
+        let start = SimpletoonSettings::default();
+        let mut settings = app.world_mut().get_mut::<SimpletoonSettings>(camera).unwrap();
+        settings.stroke_size = start.stroke_size + 4.0;
+        settings.colour_banding = start.colour_banding + 3.0;
+        for _ in 0..5 {
+            app.update();
+        }
+        let eased = *app.world().get::<SimpletoonSettings>(camera).unwrap();
+        assert!(eased.stroke_size > start.stroke_size && eased.stroke_size < start.stroke_size + 4.0, "{}", eased.stroke_size);
+        assert!(eased.colour_banding > start.colour_banding && eased.colour_banding < start.colour_banding + 3.0);
+
+        // 7 time constants is a little over a second at 50 frames a second
+        for _ in 0..60 {
+            app.update();
+        }
+        let settled = *app.world().get::<SimpletoonSettings>(camera).unwrap();
+        assert_eq!(settled.stroke_size, start.stroke_size + 4.0);
+        assert_eq!(settled.colour_banding, start.colour_banding + 3.0);
+    }
+}
@@ -0,0 +1,47 @@
+use bevy::{ecs::query::QueryItem, prelude::*, render::extract_component::ExtractComponent};
+
+/// Insert on a toon camera to draw radial speed lines over the toon pass, e.g. while sprinting or dashing, and remove
+/// it to stop them. The lines run outwards from around `inner_radius`, radii being fractions of half the viewport's
+/// diagonal measured from `centre`, and each one is redrawn with a fresh start and width `jitter_rate` times a second.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ToonSpeedLines {
+    /// How many lines go round the centre.
+    pub count: u32,
+    /// How far each line runs outwards from where it starts, 1 or more reaches the corners.
+    pub length: f32,
+    pub inner_radius: f32,
+    /// How far a line's start can move in or out of `inner_radius`.
+    pub jitter: f32,
+    /// Redraws per second, 0 keeps the same lines.
+    pub jitter_rate: f32,
+    /// Thickness at the outer end, as a fraction of the gap between lines. Lines taper to a point at their start.
+    pub width: f32,
+    pub colour: Color,
+    /// Where the lines point at, in UVs of the camera's viewport.
+    pub centre: Vec2,
+}
+
+impl Default for ToonSpeedLines {
+    fn default() -> Self {
+        Self {
+            count: 64,
+            length: 1.0,
+            inner_radius: 0.6,
+            jitter: 0.15,
+            jitter_rate: 20.0,
+            width: 0.3,
+            colour: Color::WHITE,
+            centre: Vec2::splat(0.5),
+        }
+    }
+}
+
+impl ExtractComponent for ToonSpeedLines {
+    type QueryData = &'static ToonSpeedLines;
+    type QueryFilter = ();
+    type Out = ToonSpeedLines;
+
+    fn extract_component(speed_lines: QueryItem<'_, Self::QueryData>) -> Option<Self::Out> {
+        Some(*speed_lines)
+    }
+}
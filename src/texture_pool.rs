@@ -0,0 +1,38 @@
+use std::mem;
+
+use bevy::{
+    platform::collections::HashMap,
+    prelude::*,
+    render::{
+        render_resource::TextureDescriptor,
+        renderer::RenderDevice,
+        texture::CachedTexture,
+    },
+};
+
+/// Intermediate textures shared by every toon camera.
+///
+/// Cameras render one after the other and none of these textures outlive their own camera's passes,
+/// so views asking for the same descriptor (label included, which keeps a view's own textures apart)
+/// get the same texture instead of one each. Unlike bevy's `TextureCache`, memory then stays flat as cameras are added.
+#[derive(Resource, Default)]
+pub(crate) struct ToonTexturePool {
+    textures: HashMap<TextureDescriptor<'static>, (CachedTexture, bool)>,
+}
+
+impl ToonTexturePool {
+    pub(crate) fn get(&mut self, render_device: &RenderDevice, descriptor: TextureDescriptor<'static>) -> CachedTexture {
+        let (texture, used) = self.textures.entry(descriptor.clone()).or_insert_with(|| {
+            let texture = render_device.create_texture(&descriptor);
+            let default_view = texture.create_view(&default());
+            (CachedTexture { texture, default_view }, true)
+        });
+        *used = true;
+        texture.clone()
+    }
+}
+
+/// Drops textures no camera asked for this frame, e.g. after a resize.
+pub(crate) fn prune_texture_pool(mut pool: ResMut<ToonTexturePool>) {
+    pool.textures.retain(|_, (_, used)| mem::take(used));
+}
@@ -0,0 +1,311 @@
+use bevy::{
+    asset::embedded_asset,
+    core_pipeline::{
+        core_3d::graph::{Core3d, Node3d},
+        fullscreen_vertex_shader::fullscreen_shader_vertex_state,
+    },
+    ecs::query::QueryItem,
+    prelude::*,
+    render::{
+        camera::ExtractedCamera,
+        extract_component::{
+            ComponentUniforms, DynamicUniformIndex, ExtractComponent, ExtractComponentPlugin,
+            UniformComponentPlugin,
+        },
+        render_graph::{
+            NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner,
+        },
+        render_resource::{
+            binding_types::{texture_2d, uniform_buffer},
+            *,
+        },
+        renderer::{RenderContext, RenderDevice},
+        texture::CachedTexture,
+        view::{ExtractedView, ViewTarget},
+        Render, RenderApp, RenderSet,
+    },
+};
+
+use crate::plugin::{BindGroupKey, SimpletoonBindGroupCache, SimpletoonSystems};
+use crate::texture_pool::ToonTexturePool;
+
+/// Bands water, glass and other transparent surfaces, which the main pass can't tell apart from what is behind them.
+///
+/// The scene is copied after the opaque pass, and only pixels the transparent pass changed are banded,
+/// before tonemapping and independently of [`SimpletoonSettings`](crate::plugin::SimpletoonSettings).
+#[derive(Component, Clone, Copy, ExtractComponent, ShaderType)]
+pub struct SimpletoonTransparentSettings {
+    pub colour_banding: f32,
+    pub effect_strength: f32, // 0 leaves transparent surfaces untouched, 1 is fully banded
+}
+
+impl Default for SimpletoonTransparentSettings {
+    fn default() -> Self {
+        Self {
+            colour_banding: 5.0,
+            effect_strength: 1.0,
+        }
+    }
+}
+
+/// Copies the scene once opaque geometry is drawn.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct SimpletoonOpaqueSnapshotLabel;
+
+/// Bands whatever the transparent pass drew over the opaque snapshot.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct SimpletoonTransparentLabel;
+
+pub(crate) struct SimpletoonTransparentPlugin;
+
+impl Plugin for SimpletoonTransparentPlugin {
+    fn build(&self, app: &mut App) {
+        embedded_asset!(app, "assets/toon_transparent.wgsl");
+        app.add_plugins((
+            ExtractComponentPlugin::<SimpletoonTransparentSettings>::default(),
+            UniformComponentPlugin::<SimpletoonTransparentSettings>::default(),
+        ));
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app
+            .init_resource::<SpecializedRenderPipelines<TransparentPipeline>>()
+            .add_systems(
+                Render,
+                (
+                    prepare_transparent_pipelines.in_set(RenderSet::Prepare),
+                    prepare_opaque_snapshots.in_set(RenderSet::PrepareResources),
+                )
+                    .in_set(SimpletoonSystems::Prepare),
+            )
+            .add_render_graph_node::<ViewNodeRunner<OpaqueSnapshotNode>>(
+                Core3d,
+                SimpletoonOpaqueSnapshotLabel,
+            )
+            .add_render_graph_node::<ViewNodeRunner<TransparentNode>>(
+                Core3d,
+                SimpletoonTransparentLabel,
+            )
+            .add_render_graph_edges(
+                Core3d,
+                (
+                    Node3d::MainOpaquePass,
+                    SimpletoonOpaqueSnapshotLabel,
+                    Node3d::MainTransmissivePass,
+                ),
+            )
+            .add_render_graph_edges(
+                Core3d,
+                (
+                    Node3d::MainTransparentPass,
+                    SimpletoonTransparentLabel,
+                    Node3d::EndMainPass,
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app.init_resource::<TransparentPipeline>();
+    }
+}
+
+#[derive(Resource)]
+struct TransparentPipeline {
+    layout: BindGroupLayout,
+    shader: Handle<Shader>,
+}
+
+#[derive(Component)]
+struct TransparentPipelineId(CachedRenderPipelineId);
+
+/// The scene as it was before transparent geometry was drawn.
+#[derive(Component)]
+struct OpaqueSnapshot(CachedTexture);
+
+#[derive(Default)]
+struct OpaqueSnapshotNode;
+
+#[derive(Default)]
+struct TransparentNode;
+
+impl FromWorld for TransparentPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let layout = world.resource::<RenderDevice>().create_bind_group_layout(
+            "simpletoon_transparent_bind_group_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    uniform_buffer::<SimpletoonTransparentSettings>(true),
+                ),
+            ),
+        );
+
+        Self {
+            layout,
+            shader: world.load_asset("embedded://bevy_simpletoon/assets/toon_transparent.wgsl"),
+        }
+    }
+}
+
+impl SpecializedRenderPipeline for TransparentPipeline {
+    // Whether the view is HDR
+    type Key = bool;
+
+    fn specialize(&self, hdr: Self::Key) -> RenderPipelineDescriptor {
+        RenderPipelineDescriptor {
+            label: Some("simpletoon_transparent_pipeline".into()),
+            layout: vec![self.layout.clone()],
+            vertex: fullscreen_shader_vertex_state(),
+            fragment: Some(FragmentState {
+                shader: self.shader.clone(),
+                shader_defs: vec![],
+                entry_point: "fragment".into(),
+                targets: vec![Some(ColorTargetState {
+                    format: match hdr {
+                        true => ViewTarget::TEXTURE_FORMAT_HDR,
+                        false => TextureFormat::bevy_default(),
+                    },
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            push_constant_ranges: vec![],
+            zero_initialize_workgroup_memory: false,
+        }
+    }
+}
+
+fn prepare_transparent_pipelines(
+    mut commands: Commands,
+    pipeline_cache: Res<PipelineCache>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<TransparentPipeline>>,
+    transparent_pipeline: Res<TransparentPipeline>,
+    views: Query<(Entity, &ExtractedView), With<SimpletoonTransparentSettings>>,
+) {
+    for (entity, view) in &views {
+        let id = pipelines.specialize(&pipeline_cache, &transparent_pipeline, view.hdr);
+        commands.entity(entity).insert(TransparentPipelineId(id));
+    }
+}
+
+fn prepare_opaque_snapshots(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    mut texture_pool: ResMut<ToonTexturePool>,
+    views: Query<(Entity, &ExtractedCamera, &ViewTarget), With<SimpletoonTransparentSettings>>,
+) {
+    for (entity, camera, view_target) in &views {
+        let Some(size) = camera.physical_target_size else {
+            continue;
+        };
+        let texture = texture_pool.get(
+            &render_device,
+            TextureDescriptor {
+                label: Some("simpletoon_opaque_snapshot"),
+                size: Extent3d {
+                    width: size.x,
+                    height: size.y,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: view_target.main_texture_format(),
+                usage: TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+        );
+        commands.entity(entity).insert(OpaqueSnapshot(texture));
+    }
+}
+
+impl ViewNode for OpaqueSnapshotNode {
+    type ViewQuery = (&'static ViewTarget, &'static OpaqueSnapshot);
+
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (view_target, snapshot): QueryItem<Self::ViewQuery>,
+        _world: &World,
+    ) -> Result<(), NodeRunError> {
+        render_context.command_encoder().copy_texture_to_texture(
+            view_target.main_texture().as_image_copy(),
+            snapshot.0.texture.as_image_copy(),
+            snapshot.0.texture.size(),
+        );
+        Ok(())
+    }
+}
+
+impl ViewNode for TransparentNode {
+    type ViewQuery = (
+        &'static ViewTarget,
+        &'static OpaqueSnapshot,
+        &'static TransparentPipelineId,
+        &'static DynamicUniformIndex<SimpletoonTransparentSettings>,
+    );
+
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (view_target, snapshot, pipeline_id, settings_index): QueryItem<Self::ViewQuery>,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let Some(pipeline) = world.resource::<PipelineCache>().get_render_pipeline(pipeline_id.0) else {
+            return Ok(());
+        };
+        let settings_uniforms = world.resource::<ComponentUniforms<SimpletoonTransparentSettings>>();
+        let Some(settings_buffer) = settings_uniforms.uniforms().buffer() else {
+            return Ok(());
+        };
+
+        let post_process = view_target.post_process_write();
+
+        let key = BindGroupKey::Transparent {
+            source: post_process.source.id(),
+            opaque: snapshot.0.default_view.id(),
+            settings: settings_buffer.id(),
+        };
+        let bind_group = world.resource::<SimpletoonBindGroupCache>().get_or_create(key, || {
+            world.resource::<RenderDevice>().create_bind_group(
+                "simpletoon_transparent_bind_group",
+                &world.resource::<TransparentPipeline>().layout,
+                &BindGroupEntries::sequential((
+                    post_process.source,
+                    &snapshot.0.default_view,
+                    settings_uniforms.uniforms().binding().unwrap(),
+                )),
+            )
+        });
+
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("simpletoon_transparent_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: post_process.destination,
+                resolve_target: None,
+                ops: Operations::default(),
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_render_pipeline(pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[settings_index.index()]);
+        render_pass.draw(0..3, 0..1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,297 @@
+use bevy::{
+    asset::embedded_asset,
+    core_pipeline::{
+        core_3d::graph::{Core3d, Node3d},
+        fullscreen_vertex_shader::fullscreen_shader_vertex_state,
+    },
+    ecs::query::QueryItem,
+    prelude::*,
+    render::{
+        camera::ExtractedCamera,
+        extract_component::{
+            ComponentUniforms, DynamicUniformIndex, ExtractComponent, ExtractComponentPlugin,
+            UniformComponentPlugin,
+        },
+        render_graph::{
+            NodeRunError, RenderGraphApp, RenderGraphContext, RenderLabel, ViewNode, ViewNodeRunner,
+        },
+        render_resource::{
+            binding_types::{texture_2d, uniform_buffer},
+            *,
+        },
+        renderer::{RenderContext, RenderDevice},
+        texture::CachedTexture,
+        view::ViewTarget,
+        Render, RenderApp, RenderSet,
+    },
+};
+
+use crate::plugin::{BindGroupKey, SimpletoonBindGroupCache, SimpletoonSystems};
+use crate::texture_pool::ToonTexturePool;
+
+/// Leaves GPU particles and other VFX drawn in the transparent pass, like `bevy_hanabi` effects, out of the toon
+/// pass, which would otherwise band them and outline their soft edges into mush.
+///
+/// The scene is copied before the transparent pass, and pixels it changed by more than `threshold` keep the colours
+/// they were drawn with, along with `spread` pixels around them so no strokes are drawn along the VFX's edges.
+/// Transparent meshes that should still be toon shaded are better left to
+/// [`SimpletoonTransparentSettings`](crate::transparent::SimpletoonTransparentSettings) without this.
+#[derive(Component, Clone, Copy, ExtractComponent, ShaderType)]
+pub struct ToonVfxExclusion {
+    /// How far any colour channel has to change, in the camera's linear colour, for a pixel to count as VFX.
+    pub threshold: f32,
+    /// Pixels around the VFX that are left alone too.
+    pub spread: f32,
+}
+
+impl Default for ToonVfxExclusion {
+    fn default() -> Self {
+        Self {
+            threshold: 0.01,
+            spread: 2.0,
+        }
+    }
+}
+
+/// Copies the scene before transparent geometry and VFX are drawn.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct SimpletoonVfxSnapshotLabel;
+
+/// Marks the pixels the transparent pass changed, for the toon pass to leave alone.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+pub struct SimpletoonVfxMaskLabel;
+
+/// How much of the toon result to keep per pixel, 0 over VFX. The toon pass binds a white fallback without it.
+#[derive(Component)]
+pub(crate) struct ToonVfxMask(pub(crate) CachedTexture);
+
+pub(crate) const VFX_MASK_FORMAT: TextureFormat = TextureFormat::R8Unorm;
+
+pub(crate) struct SimpletoonVfxPlugin;
+
+impl Plugin for SimpletoonVfxPlugin {
+    fn build(&self, app: &mut App) {
+        embedded_asset!(app, "assets/toon_vfx_mask.wgsl");
+        app.add_plugins((
+            ExtractComponentPlugin::<ToonVfxExclusion>::default(),
+            UniformComponentPlugin::<ToonVfxExclusion>::default(),
+        ));
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app
+            .add_systems(
+                Render,
+                prepare_vfx_textures.in_set(RenderSet::PrepareResources).in_set(SimpletoonSystems::Prepare),
+            )
+            .add_render_graph_node::<ViewNodeRunner<VfxSnapshotNode>>(Core3d, SimpletoonVfxSnapshotLabel)
+            .add_render_graph_node::<ViewNodeRunner<VfxMaskNode>>(Core3d, SimpletoonVfxMaskLabel)
+            .add_render_graph_edges(
+                Core3d,
+                (
+                    Node3d::MainTransmissivePass,
+                    SimpletoonVfxSnapshotLabel,
+                    Node3d::MainTransparentPass,
+                    SimpletoonVfxMaskLabel,
+                    Node3d::EndMainPass,
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app.init_resource::<VfxMaskPipeline>();
+    }
+}
+
+#[derive(Resource)]
+struct VfxMaskPipeline {
+    layout: BindGroupLayout,
+    pipeline: CachedRenderPipelineId,
+}
+
+impl FromWorld for VfxMaskPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let layout = world.resource::<RenderDevice>().create_bind_group_layout(
+            "simpletoon_vfx_mask_bind_group_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    uniform_buffer::<ToonVfxExclusion>(true),
+                ),
+            ),
+        );
+        let shader = world.load_asset("embedded://bevy_simpletoon/assets/toon_vfx_mask.wgsl");
+
+        // The mask has the same format on every view, so one pipeline does
+        let pipeline = world.resource::<PipelineCache>().queue_render_pipeline(RenderPipelineDescriptor {
+            label: Some("simpletoon_vfx_mask_pipeline".into()),
+            layout: vec![layout.clone()],
+            vertex: fullscreen_shader_vertex_state(),
+            fragment: Some(FragmentState {
+                shader,
+                shader_defs: vec![],
+                entry_point: "fragment".into(),
+                targets: vec![Some(ColorTargetState {
+                    format: VFX_MASK_FORMAT,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            push_constant_ranges: vec![],
+            zero_initialize_workgroup_memory: false,
+        });
+
+        Self { layout, pipeline }
+    }
+}
+
+/// The scene as it was before the transparent pass.
+#[derive(Component)]
+struct VfxSnapshot(CachedTexture);
+
+fn prepare_vfx_textures(
+    mut commands: Commands,
+    render_device: Res<RenderDevice>,
+    mut texture_pool: ResMut<ToonTexturePool>,
+    views: Query<(Entity, &ExtractedCamera, &ViewTarget), With<ToonVfxExclusion>>,
+    removed: Query<Entity, (With<ToonVfxMask>, Without<ToonVfxExclusion>)>,
+) {
+    // Otherwise the toon pass would keep leaving the last mask's pixels alone
+    for entity in &removed {
+        commands.entity(entity).remove::<(VfxSnapshot, ToonVfxMask)>();
+    }
+    for (entity, camera, view_target) in &views {
+        let Some(size) = camera.physical_target_size else {
+            continue;
+        };
+        let size = Extent3d {
+            width: size.x,
+            height: size.y,
+            depth_or_array_layers: 1,
+        };
+        let snapshot = texture_pool.get(
+            &render_device,
+            TextureDescriptor {
+                label: Some("simpletoon_vfx_snapshot"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: view_target.main_texture_format(),
+                usage: TextureUsages::COPY_DST | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+        );
+        let mask = texture_pool.get(
+            &render_device,
+            TextureDescriptor {
+                label: Some("simpletoon_vfx_mask"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: VFX_MASK_FORMAT,
+                usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            },
+        );
+        commands.entity(entity).insert((VfxSnapshot(snapshot), ToonVfxMask(mask)));
+    }
+}
+
+#[derive(Default)]
+struct VfxSnapshotNode;
+
+impl ViewNode for VfxSnapshotNode {
+    type ViewQuery = (&'static ViewTarget, &'static VfxSnapshot);
+
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (view_target, snapshot): QueryItem<Self::ViewQuery>,
+        _world: &World,
+    ) -> Result<(), NodeRunError> {
+        render_context.command_encoder().copy_texture_to_texture(
+            view_target.main_texture().as_image_copy(),
+            snapshot.0.texture.as_image_copy(),
+            snapshot.0.texture.size(),
+        );
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct VfxMaskNode;
+
+impl ViewNode for VfxMaskNode {
+    type ViewQuery = (
+        &'static ViewTarget,
+        &'static VfxSnapshot,
+        &'static ToonVfxMask,
+        &'static DynamicUniformIndex<ToonVfxExclusion>,
+    );
+
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (view_target, snapshot, mask, settings_index): QueryItem<Self::ViewQuery>,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let mask_pipeline = world.resource::<VfxMaskPipeline>();
+        let Some(pipeline) = world.resource::<PipelineCache>().get_render_pipeline(mask_pipeline.pipeline) else {
+            return Ok(());
+        };
+        let settings_uniforms = world.resource::<ComponentUniforms<ToonVfxExclusion>>();
+        let Some(settings_buffer) = settings_uniforms.uniforms().buffer() else {
+            return Ok(());
+        };
+
+        let source = view_target.main_texture_view();
+        let key = BindGroupKey::VfxMask {
+            source: source.id(),
+            snapshot: snapshot.0.default_view.id(),
+            settings: settings_buffer.id(),
+        };
+        let bind_group = world.resource::<SimpletoonBindGroupCache>().get_or_create(key, || {
+            world.resource::<RenderDevice>().create_bind_group(
+                "simpletoon_vfx_mask_bind_group",
+                &mask_pipeline.layout,
+                &BindGroupEntries::sequential((
+                    source,
+                    &snapshot.0.default_view,
+                    settings_uniforms.uniforms().binding().unwrap(),
+                )),
+            )
+        });
+
+        let mut render_pass = render_context.begin_tracked_render_pass(RenderPassDescriptor {
+            label: Some("simpletoon_vfx_mask_pass"),
+            color_attachments: &[Some(RenderPassColorAttachment {
+                view: &mask.0.default_view,
+                resolve_target: None,
+                ops: Operations::default(),
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        render_pass.set_render_pipeline(pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[settings_index.index()]);
+        render_pass.draw(0..3, 0..1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+//! Checks [`SimpletoonPluginBuilder::build`] refuses options that can't work together, or need a cargo feature the
+//! crate was built without, before the app ever runs.
+
+use bevy::core_pipeline::core_3d::graph::Node3d;
+#[cfg(not(all(feature = "outlines", feature = "banding")))]
+use bevy_simpletoon::builder::ToonFeatures;
+use bevy_simpletoon::{
+    builder::ToonPassOrder,
+    plugin::{SimpletoonPlugin, SimpletoonSettings},
+    quality::ToonQuality,
+};
+
+#[test]
+#[cfg(not(feature = "outlines"))]
+#[should_panic(expected = "built without its \"outlines\" feature")]
+fn missing_outlines_feature() {
+    SimpletoonPlugin::builder().features(ToonFeatures { outlines: true, ..Default::default() }).build();
+}
+
+#[test]
+#[cfg(not(feature = "banding"))]
+#[should_panic(expected = "built without its \"banding\" feature")]
+fn missing_banding_feature() {
+    SimpletoonPlugin::builder().features(ToonFeatures { banding: true, ..Default::default() }).build();
+}
+
+#[test]
+#[should_panic(expected = "has nothing to wait for without .precompile(..)")]
+fn block_without_precompile() {
+    SimpletoonPlugin::builder().block_until_compiled().build();
+}
+
+#[test]
+#[should_panic(expected = "labeled(\"fine\") can't use quality(..)")]
+fn labeled_with_shared_option() {
+    SimpletoonPlugin::builder().labeled("fine").quality(ToonQuality::Medium).build();
+}
+
+#[test]
+#[should_panic(expected = "can't run the pass both after and before")]
+fn between_one_node() {
+    SimpletoonPlugin::builder().order(ToonPassOrder::between(Node3d::Tonemapping, Node3d::Tonemapping)).build();
+}
+
+#[test]
+fn compatible_options() {
+    SimpletoonPlugin::builder()
+        .order(ToonPassOrder::between(Node3d::Tonemapping, Node3d::Fxaa))
+        .quality(ToonQuality::Medium)
+        .precompile(&SimpletoonSettings::default(), false)
+        .block_until_compiled()
+        .build();
+    SimpletoonPlugin::builder().labeled("fine").order(ToonPassOrder::between(Node3d::Fxaa, Node3d::EndMainPassPostProcessing)).build();
+}
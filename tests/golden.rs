@@ -0,0 +1,250 @@
+//! Renders a small reference scene headlessly and compares it against the PNGs in `tests/golden`.
+//!
+//! Skips when no Vulkan, Metal or DX12 adapter is available, GL is left out since parts of bevy's
+//! own pipeline don't compile there. Run with `SIMPLETOON_BLESS=1` to (re)write the goldens
+//! after an intentional visual change, a missing golden fails the test otherwise.
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use bevy::{
+    app::ScheduleRunnerPlugin,
+    core_pipeline::tonemapping::Tonemapping,
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        gpu_readback::{Readback, ReadbackComplete},
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+        settings::{Backends, WgpuSettings},
+        RenderPlugin,
+    },
+    window::ExitCondition,
+    winit::WinitPlugin,
+};
+use bevy_simpletoon::plugin::{SimpletoonPlugin, SimpletoonSettings, StrokeSpace};
+
+// 256 * 4 bytes per row is already aligned, so the readback has no row padding to strip
+const SIZE: u32 = 256;
+// Pipelines compile asynchronously, so give them a while before trusting the output
+const WARMUP_FRAMES: u32 = 60;
+const MAX_FRAMES: u32 = 600;
+// Per channel difference (0-255) a pixel may have before it counts as changed
+const CHANNEL_TOLERANCE: u8 = 8;
+// Fraction of pixels allowed to change, covers driver differences along edges
+const PIXEL_TOLERANCE: f32 = 0.005;
+
+#[test]
+fn default_settings() {
+    golden("default_settings", SimpletoonSettings::default());
+}
+
+#[test]
+fn ui_camera_overlay() {
+    golden_with("ui_camera_overlay", SimpletoonSettings::default(), |world, target| {
+        // The usual dual camera setup, a 2d UI camera drawing over the toon camera's output
+        world.spawn((
+            Camera2d,
+            Camera {
+                order: 1,
+                target: RenderTarget::Image(target.into()),
+                clear_color: ClearColorConfig::None,
+                ..default()
+            },
+            Tonemapping::None,
+            Msaa::Off,
+            IsDefaultUiCamera,
+        ));
+        world.spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Px(16.0),
+                top: Val::Px(16.0),
+                width: Val::Px(64.0),
+                height: Val::Px(32.0),
+                ..default()
+            },
+            BackgroundColor(Color::srgb_u8(40, 200, 80)),
+        ));
+    });
+}
+
+#[test]
+fn thick_dilated_strokes() {
+    golden(
+        "thick_dilated_strokes",
+        SimpletoonSettings {
+            stroke_size: 5.0,
+            stroke_dilation: true,
+            ..default()
+        },
+    );
+}
+
+#[test]
+fn world_space_strokes() {
+    golden(
+        "world_space_strokes",
+        SimpletoonSettings {
+            stroke_size: 0.05,
+            stroke_space: StrokeSpace::World,
+            ..default()
+        },
+    );
+}
+
+fn has_adapter() -> bool {
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+        backends: Backends::PRIMARY,
+        ..default()
+    });
+    bevy::tasks::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default())).is_some()
+}
+
+fn golden(name: &str, settings: SimpletoonSettings) {
+    golden_with(name, settings, |_, _| {});
+}
+
+/// Like [`golden`], with `extra` adding to the scene, e.g. more cameras on the same target.
+fn golden_with(name: &str, settings: SimpletoonSettings, extra: impl FnOnce(&mut World, Handle<Image>)) {
+    if !has_adapter() {
+        eprintln!("skipping golden test `{name}`: no GPU adapter available");
+        return;
+    }
+
+    let rendered = render(settings, extra);
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{name}.png"));
+
+    if std::env::var_os("SIMPLETOON_BLESS").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        image::save_buffer(&path, &rendered, SIZE, SIZE, image::ExtendedColorType::Rgba8).unwrap();
+        eprintln!("wrote golden image {}", path.display());
+        return;
+    }
+
+    assert!(
+        path.exists(),
+        "golden `{name}` is missing, run with SIMPLETOON_BLESS=1 to write {} and commit it",
+        path.display()
+    );
+    let expected = image::open(&path).unwrap().into_rgba8();
+    assert_eq!(expected.dimensions(), (SIZE, SIZE), "golden `{name}` has the wrong size");
+
+    let changed = expected
+        .as_raw()
+        .chunks_exact(4)
+        .zip(rendered.chunks_exact(4))
+        .filter(|(a, b)| a.iter().zip(b.iter()).any(|(a, b)| a.abs_diff(*b) > CHANNEL_TOLERANCE))
+        .count();
+    let changed = changed as f32 / (SIZE * SIZE) as f32;
+
+    if changed > PIXEL_TOLERANCE {
+        let actual = path.with_file_name(format!("{name}.actual.png"));
+        image::save_buffer(&actual, &rendered, SIZE, SIZE, image::ExtendedColorType::Rgba8).unwrap();
+        panic!(
+            "`{name}` differs from its golden in {:.2}% of pixels, output written to {}",
+            changed * 100.0,
+            actual.display()
+        );
+    }
+}
+
+fn render(settings: SimpletoonSettings, extra: impl FnOnce(&mut World, Handle<Image>)) -> Vec<u8> {
+    let mut app = App::new();
+    app.add_plugins((
+        DefaultPlugins
+            .set(WindowPlugin {
+                primary_window: None,
+                exit_condition: ExitCondition::DontExit,
+                close_when_requested: false,
+            })
+            .set(RenderPlugin {
+                render_creation: WgpuSettings {
+                    backends: Some(Backends::PRIMARY),
+                    ..default()
+                }
+                .into(),
+                ..default()
+            })
+            .disable::<WinitPlugin>(),
+        ScheduleRunnerPlugin::default(),
+        SimpletoonPlugin::default(),
+    ));
+
+    let target = {
+        let mut image = Image::new_fill(
+            Extent3d { width: SIZE, height: SIZE, depth_or_array_layers: 1 },
+            TextureDimension::D2,
+            &[0; 4],
+            TextureFormat::Rgba8UnormSrgb,
+            default(),
+        );
+        image.texture_descriptor.usage |=
+            TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_SRC | TextureUsages::TEXTURE_BINDING;
+        app.world_mut().resource_mut::<Assets<Image>>().add(image)
+    };
+
+    spawn_scene(app.world_mut(), target.clone(), settings);
+    extra(app.world_mut(), target.clone());
+
+    let frame = Arc::new(Mutex::new(None::<Vec<u8>>));
+    let sink = frame.clone();
+    app.world_mut()
+        .spawn(Readback::texture(target))
+        .observe(move |trigger: Trigger<ReadbackComplete>| {
+            *sink.lock().unwrap() = Some(trigger.event().0.clone());
+        });
+
+    app.finish();
+    app.cleanup();
+
+    for update in 0..MAX_FRAMES {
+        app.update();
+        if let Some(frame) = frame.lock().unwrap().take().filter(|_| update >= WARMUP_FRAMES) {
+            return frame;
+        }
+    }
+    panic!("no frame was read back after {MAX_FRAMES} updates");
+}
+
+fn spawn_scene(world: &mut World, target: Handle<Image>, settings: SimpletoonSettings) {
+    let sphere = world.resource_mut::<Assets<Mesh>>().add(Sphere::new(0.6).mesh().uv(32, 18));
+    let cube = world.resource_mut::<Assets<Mesh>>().add(Cuboid::default());
+    let plane = world.resource_mut::<Assets<Mesh>>().add(Plane3d::default().mesh().size(8.0, 8.0));
+
+    let mut materials = world.resource_mut::<Assets<StandardMaterial>>();
+    let mut material = |c: Color| {
+        materials.add(StandardMaterial {
+            perceptual_roughness: 0.8,
+            base_color: c,
+            ..default()
+        })
+    };
+    let red = material(Color::srgb_u8(244, 0, 30));
+    let blue = material(Color::srgb_u8(124, 144, 255));
+    let grey = material(Color::srgb_u8(200, 200, 200));
+
+    world.spawn((Mesh3d(sphere), MeshMaterial3d(red), Transform::from_xyz(-0.8, 0.6, 0.0)));
+    world.spawn((
+        Mesh3d(cube),
+        MeshMaterial3d(blue),
+        Transform::from_xyz(0.8, 0.5, 0.0).with_rotation(Quat::from_rotation_y(0.6)),
+    ));
+    world.spawn((Mesh3d(plane), MeshMaterial3d(grey)));
+
+    world.spawn((
+        DirectionalLight { shadows_enabled: true, ..default() },
+        Transform::from_xyz(4.0, 8.0, 4.0).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+
+    world.spawn((
+        Camera3d::default(),
+        Camera { target: RenderTarget::Image(target.into()), ..default() },
+        // Keep the output independent of the tonemapping LUTs and MSAA resolve
+        Tonemapping::None,
+        Msaa::Off,
+        Transform::from_xyz(0.0, 3.0, 5.0).looking_at(Vec3::new(0.0, 0.4, 0.0), Vec3::Y),
+        settings,
+    ));
+}
@@ -0,0 +1,184 @@
+//! Runs cameras the toon pass has to cope with headlessly, and checks the app neither panics nor keeps logging
+//! warnings once it has settled: a camera whose depth prepass was taken away, one with MSAA, and one without
+//! `SimpletoonSettings` next to a toon camera.
+//!
+//! Skips when no Vulkan, Metal or DX12 adapter is available, like the golden tests.
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex,
+};
+
+use bevy::{
+    app::ScheduleRunnerPlugin,
+    core_pipeline::prepass::DepthPrepass,
+    log::{
+        tracing::{Event, Subscriber},
+        tracing_subscriber::{layer::Context, Layer},
+        BoxedLayer, Level, LogPlugin,
+    },
+    prelude::*,
+    render::{
+        camera::RenderTarget,
+        render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+        settings::{Backends, WgpuSettings},
+        RenderPlugin,
+    },
+    window::ExitCondition,
+    winit::WinitPlugin,
+};
+use bevy_simpletoon::plugin::{SimpletoonPlugin, SimpletoonSettings};
+
+const SIZE: u32 = 64;
+// Pipelines compile asynchronously and may warn while they do, only what's logged after this counts
+const WARMUP_FRAMES: u32 = 60;
+const CHECKED_FRAMES: u32 = 60;
+
+/// Warnings and errors logged by any app in this process, tallied by [`CountWarnings`].
+static WARNINGS: AtomicUsize = AtomicUsize::new(0);
+/// The ones of those logged by `bevy_simpletoon` itself.
+static TOON_WARNINGS: AtomicUsize = AtomicUsize::new(0);
+/// The log subscriber is global, so the tests take turns to keep their warnings apart.
+static SERIAL: Mutex<()> = Mutex::new(());
+
+struct CountWarnings;
+
+impl<S: Subscriber> Layer<S> for CountWarnings {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        // More severe levels compare lower
+        if *event.metadata().level() <= Level::WARN {
+            WARNINGS.fetch_add(1, Ordering::Relaxed);
+            if event.metadata().target().starts_with("bevy_simpletoon") {
+                TOON_WARNINGS.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+#[test]
+fn camera_without_depth_prepass() {
+    let toon_warnings = settles("camera_without_depth_prepass", |world, target| {
+        let camera = world.spawn(toon_camera(target)).id();
+        // SimpletoonSettings requires the prepasses, so the only way to lose one is to remove it afterwards
+        world.entity_mut(camera).remove::<DepthPrepass>();
+    });
+    // The missing prepass is reported, once, so the frames checked above did go down that path
+    if let Some(toon_warnings) = toon_warnings {
+        assert!(toon_warnings > 0, "the camera without a depth prepass wasn't warned about while warming up");
+    }
+}
+
+#[test]
+fn camera_with_msaa() {
+    settles("camera_with_msaa", |world, target| {
+        world.spawn((toon_camera(target), Msaa::Sample4));
+    });
+}
+
+#[test]
+fn camera_without_settings() {
+    settles("camera_without_settings", |world, target| {
+        world.spawn(toon_camera(target.clone()));
+        let other = new_target(world);
+        world.spawn((
+            Camera3d::default(),
+            Camera { target: RenderTarget::Image(other.into()), ..default() },
+            Msaa::Off,
+            Transform::from_xyz(0.0, 3.0, 5.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ));
+    });
+}
+
+fn has_adapter() -> bool {
+    let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+        backends: Backends::PRIMARY,
+        ..default()
+    });
+    bevy::tasks::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default())).is_some()
+}
+
+/// Runs the scene with `cameras` added, panicking if anything is logged at warning level or above once it has
+/// warmed up. Returns how many warnings `bevy_simpletoon` logged while warming up, or `None` without a GPU.
+fn settles(name: &str, cameras: impl FnOnce(&mut World, Handle<Image>)) -> Option<usize> {
+    if !has_adapter() {
+        eprintln!("skipping robustness test `{name}`: no GPU adapter available");
+        return None;
+    }
+    let _serial = SERIAL.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    let mut app = App::new();
+    app.add_plugins((
+        DefaultPlugins
+            .set(WindowPlugin {
+                primary_window: None,
+                exit_condition: ExitCondition::DontExit,
+                close_when_requested: false,
+            })
+            .set(RenderPlugin {
+                render_creation: WgpuSettings {
+                    backends: Some(Backends::PRIMARY),
+                    ..default()
+                }
+                .into(),
+                ..default()
+            })
+            .set(LogPlugin {
+                custom_layer: |_| Some(Box::new(CountWarnings) as BoxedLayer),
+                ..default()
+            })
+            .disable::<WinitPlugin>(),
+        ScheduleRunnerPlugin::default(),
+        SimpletoonPlugin::default(),
+    ));
+
+    let target = new_target(app.world_mut());
+    spawn_scene(app.world_mut());
+    cameras(app.world_mut(), target);
+
+    app.finish();
+    app.cleanup();
+
+    let toon_before = TOON_WARNINGS.load(Ordering::Relaxed);
+    for _ in 0..WARMUP_FRAMES {
+        app.update();
+    }
+    let toon_warnings = TOON_WARNINGS.load(Ordering::Relaxed) - toon_before;
+    let before = WARNINGS.load(Ordering::Relaxed);
+    for _ in 0..CHECKED_FRAMES {
+        app.update();
+    }
+    let logged = WARNINGS.load(Ordering::Relaxed) - before;
+    assert_eq!(logged, 0, "`{name}` logged {logged} warnings over {CHECKED_FRAMES} frames after warming up");
+    Some(toon_warnings)
+}
+
+fn new_target(world: &mut World) -> Handle<Image> {
+    let mut image = Image::new_fill(
+        Extent3d { width: SIZE, height: SIZE, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        &[0; 4],
+        TextureFormat::Rgba8UnormSrgb,
+        default(),
+    );
+    image.texture_descriptor.usage |= TextureUsages::RENDER_ATTACHMENT | TextureUsages::TEXTURE_BINDING;
+    world.resource_mut::<Assets<Image>>().add(image)
+}
+
+fn toon_camera(target: Handle<Image>) -> impl Bundle {
+    (
+        Camera3d::default(),
+        Camera { target: RenderTarget::Image(target.into()), ..default() },
+        Transform::from_xyz(0.0, 3.0, 5.0).looking_at(Vec3::ZERO, Vec3::Y),
+        SimpletoonSettings::default(),
+    )
+}
+
+fn spawn_scene(world: &mut World) {
+    let cube = world.resource_mut::<Assets<Mesh>>().add(Cuboid::default());
+    let material = world.resource_mut::<Assets<StandardMaterial>>().add(Color::srgb_u8(124, 144, 255));
+    world.spawn((Mesh3d(cube), MeshMaterial3d(material), Transform::from_xyz(0.0, 0.5, 0.0)));
+    world.spawn((
+        DirectionalLight { shadows_enabled: true, ..default() },
+        Transform::from_xyz(4.0, 8.0, 4.0).looking_at(Vec3::ZERO, Vec3::Y),
+    ));
+}
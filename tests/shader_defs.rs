@@ -0,0 +1,95 @@
+//! Checks the shader defs `toon.wgsl` is compiled with across every normal prepass encoding and pipeline layout, so
+//! the decoder picked for one Bevy version's prepass can't silently change with another option, and that the
+//! octahedral decoder gets known normals back.
+
+use bevy::{math::Vec3, render::render_resource::ShaderDefVal};
+use bevy_simpletoon::shader_defs::{NormalEncoding, ToonShaderDefs};
+
+fn has(defs: &[ShaderDefVal], name: &str) -> bool {
+    defs.iter().any(|def| match def {
+        ShaderDefVal::Bool(def, enabled) => def == name && *enabled,
+        _ => false,
+    })
+}
+
+#[test]
+fn normal_encoding_matrix() {
+    for encoding in [NormalEncoding::Rgb, NormalEncoding::Octahedral] {
+        for compatible in [false, true] {
+            for multisampled_prepass in [false, true] {
+                let defs = ToonShaderDefs::default()
+                    .normal_encoding(encoding)
+                    .compatible(compatible)
+                    .multisampled_prepass(multisampled_prepass)
+                    .build();
+                let case = format!("{encoding:?}, compatible: {compatible}, multisampled: {multisampled_prepass}");
+                assert_eq!(has(&defs, "NORMAL_OCTAHEDRAL"), encoding == NormalEncoding::Octahedral, "{case}");
+                assert_eq!(has(&defs, "MULTISAMPLED_PREPASS"), multisampled_prepass, "{case}");
+                assert_eq!(has(&defs, "COMPATIBILITY"), compatible, "{case}");
+                assert_eq!(has(&defs, "ENTITY_OVERRIDES"), !compatible, "{case}");
+            }
+        }
+    }
+}
+
+#[test]
+fn default_encoding_matches_bevy() {
+    // Bevy 0.16's prepass writes normals as RGB remapped to 0..1
+    assert_eq!(NormalEncoding::default(), NormalEncoding::Rgb);
+    assert!(!has(&ToonShaderDefs::default().build(), "NORMAL_OCTAHEDRAL"));
+}
+
+/// `octahedral_decode` in `toon.wgsl`, line for line, which [`octahedral_decoder_matches_shader`] keeps in step.
+const WGSL_DECODE: &str = "fn octahedral_decode(encoded: vec2f) -> vec3f {
+    let f = encoded * 2.0 - 1.0;
+    var n = vec3f(f, 1.0 - abs(f.x) - abs(f.y));
+    let t = saturate(-n.z);
+    n.x += select(t, -t, n.x >= 0.0);
+    n.y += select(t, -t, n.y >= 0.0);
+    return normalize(n);
+}";
+
+fn octahedral_decode(encoded: [f32; 2]) -> Vec3 {
+    let f = [encoded[0] * 2.0 - 1.0, encoded[1] * 2.0 - 1.0];
+    let mut n = Vec3::new(f[0], f[1], 1.0 - f[0].abs() - f[1].abs());
+    let t = (-n.z).clamp(0.0, 1.0);
+    // WGSL's select(f, t, cond) picks t when cond holds
+    n.x += if n.x >= 0.0 { -t } else { t };
+    n.y += if n.y >= 0.0 { -t } else { t };
+    n.normalize()
+}
+
+/// The usual octahedral encoding, as a prepass writing [`NormalEncoding::Octahedral`] would store it.
+fn octahedral_encode(normal: Vec3) -> [f32; 2] {
+    let n = normal / (normal.x.abs() + normal.y.abs() + normal.z.abs());
+    let folded = match n.z >= 0.0 {
+        true => [n.x, n.y],
+        false => [(1.0 - n.y.abs()) * n.x.signum(), (1.0 - n.x.abs()) * n.y.signum()],
+    };
+    [folded[0] * 0.5 + 0.5, folded[1] * 0.5 + 0.5]
+}
+
+#[test]
+fn octahedral_decoder_matches_shader() {
+    assert!(include_str!("../src/assets/toon.wgsl").contains(WGSL_DECODE), "octahedral_decode in toon.wgsl changed, update the copy here");
+}
+
+#[test]
+fn octahedral_round_trip() {
+    let normals = [
+        Vec3::X,
+        Vec3::NEG_X,
+        Vec3::Y,
+        Vec3::NEG_Y,
+        Vec3::Z,
+        Vec3::NEG_Z,
+        Vec3::new(1.0, 1.0, 1.0),
+        Vec3::new(-1.0, 2.0, -3.0),
+        Vec3::new(0.3, -0.8, -0.2),
+        Vec3::new(-0.5, -0.5, 0.7),
+    ];
+    for normal in normals.map(Vec3::normalize) {
+        let decoded = octahedral_decode(octahedral_encode(normal));
+        assert!(decoded.abs_diff_eq(normal, 1e-5), "{normal} came back as {decoded}");
+    }
+}